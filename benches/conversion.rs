@@ -0,0 +1,111 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rawsample::{Sample, SampleFormat, SampleReader, SampleWriter};
+use std::convert::TryInto;
+
+const NBR_SAMPLES: usize = 4096;
+
+fn test_values() -> Vec<f64> {
+    (0..NBR_SAMPLES)
+        .map(|n| (n as f64 * 0.01).sin() * 0.9)
+        .collect()
+}
+
+macro_rules! bench_format {
+    ($c:expr, $t:ident, $name:expr, $format:expr, $to:ident, $from:ident, $n:expr) => {{
+        let values: Vec<$t> = test_values().iter().map(|v| *v as $t).collect();
+        let mut rawbytes = vec![0; $n * NBR_SAMPLES];
+        let mut group = $c.benchmark_group(concat!(stringify!($t), " ", $name));
+        group.bench_function("write scalar", |b| {
+            b.iter(|| {
+                for (value, chunk) in black_box(&values).iter().zip(rawbytes.chunks_exact_mut($n)) {
+                    chunk.copy_from_slice(&value.$to().0);
+                }
+            })
+        });
+        group.bench_function("write", |b| {
+            b.iter(|| $t::write_samples_to_slice(black_box(&values), &mut rawbytes, &$format))
+        });
+        let mut samples = vec![0.0; NBR_SAMPLES];
+        group.bench_function("read scalar", |b| {
+            b.iter(|| {
+                for (value, chunk) in samples
+                    .iter_mut()
+                    .zip(black_box(&rawbytes).chunks_exact($n))
+                {
+                    *value = $t::$from(chunk.try_into().unwrap());
+                }
+            })
+        });
+        group.bench_function("read", |b| {
+            b.iter(|| $t::read_samples_from_slice(black_box(&rawbytes), &mut samples, &$format))
+        });
+        group.finish();
+    }};
+}
+
+macro_rules! bench_formats {
+    ($c:expr, $t:ident) => {{
+        bench_format!(
+            $c,
+            $t,
+            "S16LE",
+            SampleFormat::S16LE,
+            to_s16_le,
+            from_s16_le,
+            2
+        );
+        bench_format!(
+            $c,
+            $t,
+            "S16BE",
+            SampleFormat::S16BE,
+            to_s16_be,
+            from_s16_be,
+            2
+        );
+        bench_format!(
+            $c,
+            $t,
+            "S24LE3",
+            SampleFormat::S24LE3,
+            to_s24_3_le,
+            from_s24_3_le,
+            3
+        );
+        bench_format!(
+            $c,
+            $t,
+            "S32LE",
+            SampleFormat::S32LE,
+            to_s32_le,
+            from_s32_le,
+            4
+        );
+    }};
+}
+
+fn conversion(c: &mut Criterion) {
+    bench_formats!(c, f32);
+    bench_format!(
+        c,
+        f32,
+        "F32BE",
+        SampleFormat::F32BE,
+        to_f32_be,
+        from_f32_be,
+        4
+    );
+    bench_formats!(c, f64);
+    bench_format!(
+        c,
+        f64,
+        "F64BE",
+        SampleFormat::F64BE,
+        to_f64_be,
+        from_f64_be,
+        8
+    );
+}
+
+criterion_group!(benches, conversion);
+criterion_main!(benches);
@@ -0,0 +1,679 @@
+//! Block based ADPCM codecs.
+//!
+//! IMA/DVI ADPCM and Microsoft ADPCM store 4 bit samples in blocks,
+//! where each block starts with a header holding the initial state of the decoder.
+//! This module provides readers and writers for the block layouts used in WAV files,
+//! for both mono and stereo streams.
+//! The samples are read and written as interleaved f32 or f64 values, just like for [crate::SampleReader] and [crate::SampleWriter].
+//!
+//! ```rust
+//! use rawsample::adpcm::{AdpcmFormat, AdpcmReader, AdpcmWriter};
+//! let format = AdpcmFormat::ima(1, 256).unwrap();
+//! // create a vec with one block of samples
+//! let values: Vec<f64> = (0..format.samples_per_block()).map(|n| 0.01 * n as f64).collect();
+//! let mut rawbytes: Vec<u8> = Vec::new();
+//! f64::write_adpcm_samples(&values, &mut rawbytes, &format).unwrap();
+//! assert_eq!(rawbytes.len(), 256);
+//!
+//! let mut values2 = Vec::new();
+//! let mut slice: &[u8] = &rawbytes;
+//! f64::read_all_adpcm_samples(&mut slice, &mut values2, &format).unwrap();
+//! assert_eq!(values.len(), values2.len());
+//! ```
+
+use crate::{read_fully, Error, Sample};
+use std::io::{Read, Write};
+
+const IMA_INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+const IMA_STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+const MS_ADAPTATION_TABLE: [i32; 16] = [
+    230, 230, 230, 230, 307, 409, 512, 614, 768, 614, 512, 409, 307, 230, 230, 230,
+];
+
+// the largest delta that can be multiplied by the adaptation table without overflow
+const MS_MAX_DELTA: i32 = i32::MAX / 768;
+
+const MS_COEFF1: [i32; 7] = [256, 512, 0, 192, 240, 460, 392];
+const MS_COEFF2: [i32; 7] = [0, -256, 0, 64, 0, -208, -232];
+
+/// The ADPCM variants supported by the block codecs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdpcmKind {
+    /// IMA/DVI ADPCM, as used by WAV format tag 0x11.
+    Ima,
+    /// Microsoft ADPCM, as used by WAV format tag 0x02, with the standard 7 coefficient sets.
+    Microsoft,
+}
+
+/// The block layout of an ADPCM stream.
+#[derive(Debug, Clone, Copy)]
+pub struct AdpcmFormat {
+    kind: AdpcmKind,
+    channels: usize,
+    block_align: usize,
+}
+
+impl AdpcmFormat {
+    /// Create a new IMA ADPCM layout, for mono or stereo with the given block size in bytes.
+    /// Each block contains a 4 byte header per channel, followed by the sample data in groups of 4 bytes per channel.
+    pub fn ima(channels: usize, block_align: usize) -> Result<Self, Error> {
+        Self::check_channels(channels)?;
+        let header = 4 * channels;
+        if block_align <= header || (block_align - header) % (4 * channels) != 0 {
+            return Err(Error::InvalidParameter(
+                "IMA ADPCM blocks must contain a header and a whole number of 4 byte groups per channel",
+            ));
+        }
+        Ok(AdpcmFormat {
+            kind: AdpcmKind::Ima,
+            channels,
+            block_align,
+        })
+    }
+
+    /// Create a new Microsoft ADPCM layout, for mono or stereo with the given block size in bytes.
+    /// Each block contains a 7 byte header per channel, followed by the sample data.
+    pub fn microsoft(channels: usize, block_align: usize) -> Result<Self, Error> {
+        Self::check_channels(channels)?;
+        if block_align <= 7 * channels {
+            return Err(Error::InvalidParameter(
+                "Microsoft ADPCM blocks must contain a header and sample data",
+            ));
+        }
+        Ok(AdpcmFormat {
+            kind: AdpcmKind::Microsoft,
+            channels,
+            block_align,
+        })
+    }
+
+    fn check_channels(channels: usize) -> Result<(), Error> {
+        if channels == 0 || channels > 2 {
+            return Err(Error::UnsupportedFormat(
+                "ADPCM block layouts are only supported for mono and stereo",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get the ADPCM variant.
+    pub fn kind(&self) -> AdpcmKind {
+        self.kind
+    }
+
+    /// Get the number of channels.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Get the size of a block in bytes.
+    pub fn block_align(&self) -> usize {
+        self.block_align
+    }
+
+    /// Get the number of samples per channel stored in a full block.
+    pub fn samples_per_block(&self) -> usize {
+        self.samples_in_bytes(self.block_align)
+    }
+
+    /// Get the number of samples per channel that can be decoded from a block of the given length.
+    /// This is less than [AdpcmFormat::samples_per_block] for a truncated last block.
+    fn samples_in_bytes(&self, nbr_bytes: usize) -> usize {
+        match self.kind {
+            AdpcmKind::Ima => {
+                let header = 4 * self.channels;
+                if nbr_bytes < header {
+                    return 0;
+                }
+                let groups = (nbr_bytes - header) / (4 * self.channels);
+                1 + 8 * groups
+            }
+            AdpcmKind::Microsoft => {
+                let header = 7 * self.channels;
+                if nbr_bytes < header {
+                    return 0;
+                }
+                2 + 2 * (nbr_bytes - header) / self.channels
+            }
+        }
+    }
+
+    /// Decode one block to interleaved 16 bit values.
+    fn decode_block(&self, block: &[u8], output: &mut Vec<i16>) {
+        let nbr_samples = self.samples_in_bytes(block.len());
+        if nbr_samples == 0 {
+            return;
+        }
+        let start = output.len();
+        output.resize(start + nbr_samples * self.channels, 0);
+        let output = &mut output[start..];
+        match self.kind {
+            AdpcmKind::Ima => decode_ima_block(block, output, self.channels, nbr_samples),
+            AdpcmKind::Microsoft => decode_ms_block(block, output, self.channels, nbr_samples),
+        }
+    }
+
+    /// Encode one full block of interleaved 16 bit values.
+    fn encode_block(&self, values: &[i16], ima_states: &mut [ImaState], block: &mut [u8]) {
+        match self.kind {
+            AdpcmKind::Ima => encode_ima_block(values, block, ima_states),
+            AdpcmKind::Microsoft => encode_ms_block(values, block, self.channels),
+        }
+    }
+}
+
+/// Decoder and encoder state of one IMA ADPCM channel.
+#[derive(Debug, Clone, Copy, Default)]
+struct ImaState {
+    predictor: i32,
+    step_index: i32,
+}
+
+impl ImaState {
+    /// Update the state with a new 4 bit code, and return the new predicted value.
+    fn decode(&mut self, nibble: u8) -> i16 {
+        let step = IMA_STEP_TABLE[self.step_index as usize];
+        let mut diff = step >> 3;
+        if nibble & 1 != 0 {
+            diff += step >> 2;
+        }
+        if nibble & 2 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 4 != 0 {
+            diff += step;
+        }
+        if nibble & 8 != 0 {
+            self.predictor -= diff;
+        } else {
+            self.predictor += diff;
+        }
+        self.predictor = self.predictor.clamp(i16::MIN as i32, i16::MAX as i32);
+        self.step_index = (self.step_index + IMA_INDEX_TABLE[nibble as usize]).clamp(0, 88);
+        self.predictor as i16
+    }
+
+    /// Find the 4 bit code that best represents a value, and update the state with it.
+    fn encode(&mut self, value: i16) -> u8 {
+        let mut diff = value as i32 - self.predictor;
+        let mut nibble = 0;
+        if diff < 0 {
+            nibble = 8;
+            diff = -diff;
+        }
+        let mut step = IMA_STEP_TABLE[self.step_index as usize];
+        let mut mask = 4;
+        while mask > 0 {
+            if diff >= step {
+                nibble |= mask;
+                diff -= step;
+            }
+            step >>= 1;
+            mask >>= 1;
+        }
+        self.decode(nibble);
+        nibble
+    }
+}
+
+/// Create the encoder states for the first block,
+/// with step sizes matching the difference between the first two values of each channel.
+fn initial_ima_states(values: &[i16], channels: usize) -> Vec<ImaState> {
+    (0..channels)
+        .map(|ch| {
+            let diff = (values[channels + ch] as i32 - values[ch] as i32).abs();
+            let step_index = IMA_STEP_TABLE
+                .iter()
+                .position(|step| 2 * step >= diff)
+                .unwrap_or(88) as i32;
+            ImaState {
+                predictor: 0,
+                step_index,
+            }
+        })
+        .collect()
+}
+
+/// Decoder and encoder state of one Microsoft ADPCM channel.
+#[derive(Debug, Clone, Copy)]
+struct MsState {
+    coeff_index: usize,
+    delta: i32,
+    sample1: i32,
+    sample2: i32,
+}
+
+impl MsState {
+    fn predict(&self) -> i32 {
+        (self.sample1 * MS_COEFF1[self.coeff_index] + self.sample2 * MS_COEFF2[self.coeff_index])
+            >> 8
+    }
+
+    /// Update the state with a new 4 bit code, and return the new value.
+    fn decode(&mut self, nibble: u8) -> i16 {
+        let signed = ((nibble << 4) as i8 >> 4) as i32;
+        let value = (self.predict() + signed * self.delta).clamp(i16::MIN as i32, i16::MAX as i32);
+        self.sample2 = self.sample1;
+        self.sample1 = value;
+        // limit the step size, to avoid overflow when decoding corrupt data
+        self.delta =
+            ((MS_ADAPTATION_TABLE[nibble as usize] * self.delta) >> 8).clamp(16, MS_MAX_DELTA);
+        value as i16
+    }
+
+    /// Find the 4 bit code that best represents a value, and update the state with it.
+    fn encode(&mut self, value: i16) -> u8 {
+        let error = value as i32 - self.predict();
+        let bias = if error >= 0 {
+            self.delta / 2
+        } else {
+            -self.delta / 2
+        };
+        let nibble = ((error + bias) / self.delta).clamp(-8, 7) as u8 & 0x0F;
+        self.decode(nibble);
+        nibble
+    }
+}
+
+fn decode_ima_block(block: &[u8], output: &mut [i16], channels: usize, nbr_samples: usize) {
+    let mut states = [ImaState::default(); 2];
+    for (ch, state) in states.iter_mut().enumerate().take(channels) {
+        let header = &block[4 * ch..4 * ch + 4];
+        state.predictor = i16::from_le_bytes([header[0], header[1]]) as i32;
+        state.step_index = (header[2] as i32).clamp(0, 88);
+        output[ch] = state.predictor as i16;
+    }
+    let data = &block[4 * channels..];
+    for (group, chunk) in data
+        .chunks_exact(4 * channels)
+        .take((nbr_samples - 1) / 8)
+        .enumerate()
+    {
+        for (ch, channel_bytes) in chunk.chunks_exact(4).enumerate() {
+            for (n, byte) in channel_bytes.iter().enumerate() {
+                let frame = 1 + 8 * group + 2 * n;
+                output[frame * channels + ch] = states[ch].decode(byte & 0x0F);
+                output[(frame + 1) * channels + ch] = states[ch].decode(byte >> 4);
+            }
+        }
+    }
+}
+
+fn encode_ima_block(values: &[i16], block: &mut [u8], states: &mut [ImaState]) {
+    let channels = states.len();
+    for (ch, state) in states.iter_mut().enumerate() {
+        state.predictor = values[ch] as i32;
+        let predictor = values[ch].to_le_bytes();
+        block[4 * ch..4 * ch + 4].copy_from_slice(&[
+            predictor[0],
+            predictor[1],
+            state.step_index as u8,
+            0,
+        ]);
+    }
+    let data = &mut block[4 * channels..];
+    for (group, chunk) in data.chunks_exact_mut(4 * channels).enumerate() {
+        for (ch, channel_bytes) in chunk.chunks_exact_mut(4).enumerate() {
+            for (n, byte) in channel_bytes.iter_mut().enumerate() {
+                let frame = 1 + 8 * group + 2 * n;
+                let low = states[ch].encode(values[frame * channels + ch]);
+                let high = states[ch].encode(values[(frame + 1) * channels + ch]);
+                *byte = low | high << 4;
+            }
+        }
+    }
+}
+
+fn decode_ms_block(block: &[u8], output: &mut [i16], channels: usize, nbr_samples: usize) {
+    let mut states = Vec::with_capacity(channels);
+    for ch in 0..channels {
+        let word = |n: usize| {
+            let offset = channels + 2 * (n * channels + ch);
+            i16::from_le_bytes([block[offset], block[offset + 1]]) as i32
+        };
+        let state = MsState {
+            coeff_index: (block[ch] as usize).min(6),
+            delta: word(0),
+            sample1: word(1),
+            sample2: word(2),
+        };
+        output[ch] = state.sample2 as i16;
+        output[channels + ch] = state.sample1 as i16;
+        states.push(state);
+    }
+    let nibbles = block[7 * channels..]
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0F]);
+    for (n, nibble) in nibbles.take((nbr_samples - 2) * channels).enumerate() {
+        let ch = n % channels;
+        output[2 * channels + n] = states[ch].decode(nibble);
+    }
+}
+
+fn encode_ms_block(values: &[i16], block: &mut [u8], channels: usize) {
+    let mut states = Vec::with_capacity(channels);
+    for ch in 0..channels {
+        // pick the coefficient set that gives the smallest error for this block
+        let channel_values: Vec<i16> = values.iter().skip(ch).step_by(channels).copied().collect();
+        let state = (0..MS_COEFF1.len())
+            .map(|coeff_index| ms_initial_state(&channel_values, coeff_index))
+            .min_by_key(|state| ms_block_error(*state, &channel_values[2..]))
+            .unwrap();
+        block[ch] = state.coeff_index as u8;
+        for (n, word) in [state.delta, state.sample1, state.sample2]
+            .iter()
+            .enumerate()
+        {
+            let offset = channels + 2 * (n * channels + ch);
+            block[offset..offset + 2].copy_from_slice(&(*word as i16).to_le_bytes());
+        }
+        states.push(state);
+    }
+    let data = &mut block[7 * channels..];
+    let mut nibbles = values[2 * channels..]
+        .iter()
+        .enumerate()
+        .map(|(n, value)| states[n % channels].encode(*value));
+    for byte in data.iter_mut() {
+        let high = nibbles.next().unwrap_or(0);
+        let low = nibbles.next().unwrap_or(0);
+        *byte = high << 4 | low;
+    }
+}
+
+/// Create the initial state for encoding a block of one channel using the given coefficient set.
+fn ms_initial_state(values: &[i16], coeff_index: usize) -> MsState {
+    let mut state = MsState {
+        coeff_index,
+        delta: 16,
+        sample1: values[1] as i32,
+        sample2: values[0] as i32,
+    };
+    if let Some(value) = values.get(2) {
+        state.delta = ((*value as i32 - state.predict()).abs() / 4).clamp(16, i16::MAX as i32);
+    }
+    state
+}
+
+/// Calculate the total squared error from encoding some values, starting from the given state.
+fn ms_block_error(mut state: MsState, values: &[i16]) -> i64 {
+    values
+        .iter()
+        .map(|value| {
+            state.encode(*value);
+            let error = (*value as i32 - state.sample1) as i64;
+            error * error
+        })
+        .sum()
+}
+
+/// The AdpcmReader trait enables reading and decoding ADPCM blocks to multiple samples.
+pub trait AdpcmReader<T: Sample<T>> {
+    /// Read and decode ADPCM blocks from a source, and write the samples to a slice.
+    /// Whole blocks are decoded as long as they fit in the remaining part of the slice,
+    /// so the slice should normally have room for a whole number of blocks.
+    /// A truncated block at the end of the source is decoded as far as possible.
+    /// The number of samples read is returned.
+    fn read_adpcm_samples(
+        rawbytes: &mut dyn Read,
+        samples: &mut [T],
+        format: &AdpcmFormat,
+    ) -> Result<usize, Error> {
+        let samples_per_block = format.samples_per_block() * format.channels;
+        let mut block = vec![0; format.block_align];
+        let mut decoded = Vec::with_capacity(samples_per_block);
+        let mut nbr_read = 0;
+        while samples.len() - nbr_read >= samples_per_block {
+            let nbr_bytes = read_fully(rawbytes, &mut block)?;
+            decoded.clear();
+            format.decode_block(&block[0..nbr_bytes], &mut decoded);
+            for (sample, value) in samples[nbr_read..].iter_mut().zip(decoded.iter()) {
+                *sample = T::from_s16_le(value.to_le_bytes());
+            }
+            nbr_read += decoded.len();
+            if nbr_bytes < format.block_align {
+                break;
+            }
+        }
+        Ok(nbr_read)
+    }
+
+    /// Read and decode all ADPCM blocks from a source, and append the samples to a vec.
+    /// A truncated block at the end of the source is decoded as far as possible.
+    /// The number of samples read is returned.
+    fn read_all_adpcm_samples(
+        rawbytes: &mut dyn Read,
+        samples: &mut Vec<T>,
+        format: &AdpcmFormat,
+    ) -> Result<usize, Error> {
+        let start_len = samples.len();
+        let mut block = vec![0; format.block_align];
+        let mut decoded = Vec::with_capacity(format.samples_per_block() * format.channels);
+        loop {
+            let nbr_bytes = read_fully(rawbytes, &mut block)?;
+            decoded.clear();
+            format.decode_block(&block[0..nbr_bytes], &mut decoded);
+            samples.extend(
+                decoded
+                    .iter()
+                    .map(|value| T::from_s16_le(value.to_le_bytes())),
+            );
+            if nbr_bytes < format.block_align {
+                break;
+            }
+        }
+        Ok(samples.len() - start_len)
+    }
+}
+
+impl AdpcmReader<f64> for f64 {}
+impl AdpcmReader<f32> for f32 {}
+
+/// The AdpcmWriter trait enables encoding and writing many sample values as ADPCM blocks.
+pub trait AdpcmWriter<T: Sample<T>> {
+    /// Encode interleaved sample values as ADPCM blocks, and write them to a target.
+    /// The number of values must be a multiple of the number of channels.
+    /// If the values do not fill the last block, it is padded with silence.
+    /// The IMA step index of each channel is carried over from one block to the next.
+    /// The values are clamped to the 16 bit range, and the number of clipped samples is returned.
+    fn write_adpcm_samples(
+        values: &[T],
+        target: &mut dyn Write,
+        format: &AdpcmFormat,
+    ) -> Result<usize, Error> {
+        if values.len() % format.channels != 0 {
+            return Err(Error::InvalidParameter(
+                "the number of samples must be a multiple of the number of channels",
+            ));
+        }
+        let samples_per_block = format.samples_per_block() * format.channels;
+        let mut ima_states = Vec::new();
+        let mut block = vec![0; format.block_align];
+        let mut intvalues = Vec::with_capacity(samples_per_block);
+        let mut nbr_clipped = 0;
+        for chunk in values.chunks(samples_per_block) {
+            intvalues.clear();
+            for value in chunk.iter() {
+                let (bytes, clipped) = value.to_s16_le();
+                if clipped {
+                    nbr_clipped += 1;
+                }
+                intvalues.push(i16::from_le_bytes(bytes));
+            }
+            intvalues.resize(samples_per_block, 0);
+            if ima_states.is_empty() {
+                ima_states = initial_ima_states(&intvalues, format.channels);
+            }
+            format.encode_block(&intvalues, &mut ima_states, &mut block);
+            target.write_all(&block)?;
+        }
+        Ok(nbr_clipped)
+    }
+}
+
+impl AdpcmWriter<f64> for f64 {}
+impl AdpcmWriter<f32> for f32 {}
+
+#[cfg(test)]
+mod tests {
+    use crate::adpcm::{AdpcmFormat, AdpcmReader, AdpcmWriter};
+
+    fn sine(frames: usize, channels: usize) -> Vec<f64> {
+        let mut values = Vec::with_capacity(frames * channels);
+        for n in 0..frames {
+            for ch in 0..channels {
+                let freq = 0.01 * (ch + 1) as f64;
+                values.push(0.5 * (2.0 * std::f64::consts::PI * freq * n as f64).sin());
+            }
+        }
+        values
+    }
+
+    fn max_error(a: &[f64], b: &[f64]) -> f64 {
+        a.iter()
+            .zip(b.iter())
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn check_samples_per_block() {
+        assert_eq!(AdpcmFormat::ima(1, 256).unwrap().samples_per_block(), 505);
+        assert_eq!(AdpcmFormat::ima(2, 2048).unwrap().samples_per_block(), 2041);
+        assert_eq!(
+            AdpcmFormat::microsoft(1, 256).unwrap().samples_per_block(),
+            500
+        );
+        assert_eq!(
+            AdpcmFormat::microsoft(2, 512).unwrap().samples_per_block(),
+            500
+        );
+        assert!(AdpcmFormat::ima(1, 258).is_err());
+        assert!(AdpcmFormat::ima(3, 1024).is_err());
+        assert!(AdpcmFormat::microsoft(2, 14).is_err());
+    }
+
+    #[test]
+    fn decode_ima_mono() {
+        // header with predictor 256 and step index 0, followed by the codes 7, 0 and 8, 15
+        let data: Vec<u8> = vec![0, 1, 0, 0, 0x07, 0x8F, 0, 0];
+        let format = AdpcmFormat::ima(1, 8).unwrap();
+        let mut values = Vec::new();
+        let mut slice: &[u8] = &data;
+        let nbr_read = f64::read_all_adpcm_samples(&mut slice, &mut values, &format).unwrap();
+        assert_eq!(nbr_read, 9);
+        let intvalues: Vec<i32> = values.iter().map(|v| (v * 32768.0) as i32).collect();
+        assert_eq!(intvalues[0..5], [256, 267, 269, 244, 241]);
+    }
+
+    #[test]
+    fn decode_ms_mono() {
+        // header with coefficient set 1, delta 16, sample1 200 and sample2 100,
+        // followed by the codes 1 and -1
+        let data: Vec<u8> = vec![1, 16, 0, 200, 0, 100, 0, 0x1F];
+        let format = AdpcmFormat::microsoft(1, 8).unwrap();
+        let mut values = Vec::new();
+        let mut slice: &[u8] = &data;
+        let nbr_read = f64::read_all_adpcm_samples(&mut slice, &mut values, &format).unwrap();
+        assert_eq!(nbr_read, 4);
+        let intvalues: Vec<i32> = values.iter().map(|v| (v * 32768.0) as i32).collect();
+        // 2*200 - 100 + 16 = 316, then 2*316 - 200 - 16 = 416
+        assert_eq!(intvalues, vec![100, 200, 316, 416]);
+    }
+
+    #[test]
+    fn write_read_ima_stereo() {
+        let format = AdpcmFormat::ima(2, 1024).unwrap();
+        let values = sine(3 * format.samples_per_block(), 2);
+        let mut data: Vec<u8> = Vec::new();
+        let clipped = f64::write_adpcm_samples(&values, &mut data, &format).unwrap();
+        assert_eq!(clipped, 0);
+        assert_eq!(data.len(), 3 * 1024);
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f64::read_all_adpcm_samples(&mut slice, &mut values2, &format).unwrap();
+        assert_eq!(values.len(), values2.len());
+        assert!(max_error(&values, &values2) < 0.02);
+    }
+
+    #[test]
+    fn write_read_ms_stereo() {
+        let format = AdpcmFormat::microsoft(2, 1024).unwrap();
+        let values = sine(2 * format.samples_per_block(), 2);
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_adpcm_samples(&values, &mut data, &format).unwrap();
+        assert_eq!(data.len(), 2 * 1024);
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f64::read_all_adpcm_samples(&mut slice, &mut values2, &format).unwrap();
+        assert_eq!(values.len(), values2.len());
+        assert!(max_error(&values, &values2) < 0.02);
+    }
+
+    #[test]
+    fn write_read_to_slice_ms_mono_padded() {
+        // the last block is padded with silence
+        let format = AdpcmFormat::microsoft(1, 256).unwrap();
+        let values: Vec<f32> = sine(600, 1).iter().map(|v| *v as f32).collect();
+        let mut data: Vec<u8> = Vec::new();
+        f32::write_adpcm_samples(&values, &mut data, &format).unwrap();
+        assert_eq!(data.len(), 512);
+        let mut values2 = vec![0.0; 1500];
+        let mut slice: &[u8] = &data;
+        let nbr_read = f32::read_adpcm_samples(&mut slice, &mut values2, &format).unwrap();
+        assert_eq!(nbr_read, 1000);
+        for (a, b) in values.iter().zip(values2.iter()) {
+            assert!((a - b).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn read_truncated_ima_block() {
+        let format = AdpcmFormat::ima(1, 256).unwrap();
+        let values = sine(format.samples_per_block(), 1);
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_adpcm_samples(&values, &mut data, &format).unwrap();
+        // keep the header and 2 groups of 8 samples
+        let mut slice: &[u8] = &data[0..14];
+        let mut values2 = Vec::new();
+        let nbr_read = f64::read_all_adpcm_samples(&mut slice, &mut values2, &format).unwrap();
+        assert_eq!(nbr_read, 17);
+    }
+
+    #[test]
+    fn decode_ms_corrupt_blocks() {
+        let format = AdpcmFormat::microsoft(2, 512).unwrap();
+        // random bytes, from a simple linear congruential generator
+        let mut state: u32 = 12345;
+        let mut data: Vec<u8> = (0..4 * 512)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 24) as u8
+            })
+            .collect();
+        // a block with the largest delta, and codes that keep increasing it
+        data.extend(vec![0x88; 512]);
+        data[4 * 512 + 2..4 * 512 + 6].copy_from_slice(&[0xFF, 0x7F, 0xFF, 0x7F]);
+        let mut values = Vec::new();
+        let mut slice: &[u8] = &data;
+        let nbr_read = f64::read_all_adpcm_samples(&mut slice, &mut values, &format).unwrap();
+        assert_eq!(nbr_read, 5 * 2 * format.samples_per_block());
+        assert!(values.iter().all(|v| (-1.0..1.0).contains(v)));
+    }
+
+    #[test]
+    fn write_wrong_channel_count() {
+        let format = AdpcmFormat::ima(2, 256).unwrap();
+        let values = vec![0.0; 3];
+        let mut data: Vec<u8> = Vec::new();
+        assert!(f64::write_adpcm_samples(&values, &mut data, &format).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::fmt;
+
+/// The error type returned by the readers and writers.
+#[derive(Debug)]
+pub enum Error {
+    /// An error from the underlying source or target.
+    Io(std::io::Error),
+    /// The source ended in the middle of a sample.
+    /// The number of whole samples that were read, and the number of leftover bytes, are included.
+    TruncatedSample { samples: usize, bytes: usize },
+    /// The source ended in the middle of a frame.
+    /// The number of whole frames that were read, and the number of leftover bytes, are included.
+    TruncatedFrame { frames: usize, bytes: usize },
+    /// The sample format can't be used in the requested way,
+    /// for example because the frames would not be a whole number of bytes.
+    UnsupportedFormat(&'static str),
+    /// A parameter is invalid, for example a channel count, a channel map or the lengths of the buffers.
+    InvalidParameter(&'static str),
+    /// A value was outside the range of the output format, and the clip policy was to fail.
+    /// The index of the first clipped value is included.
+    Clipped(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::TruncatedSample { samples, bytes } => write!(
+                f,
+                "The source ended in the middle of a sample, after {} whole samples and with {} leftover bytes",
+                samples, bytes
+            ),
+            Error::TruncatedFrame { frames, bytes } => write!(
+                f,
+                "The source ended in the middle of a frame, after {} whole frames and with {} leftover bytes",
+                frames, bytes
+            ),
+            Error::UnsupportedFormat(desc) => write!(f, "Unsupported format: {}", desc),
+            Error::InvalidParameter(desc) => write!(f, "Invalid parameter: {}", desc),
+            Error::Clipped(index) => write!(
+                f,
+                "The value at index {} is outside the range of the output format",
+                index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -7,6 +7,9 @@
 //!
 //! This library aims to provide the low level tools for converting most common sample formats from raw bytes to float values. 
 //! Both f32 and f64 are supported, as well as both big-endian and little-endian byte order.
+//! IMA and Microsoft ADPCM streams are supported by the block codecs in the [adpcm] module.
+//! Dither can be added when writing to the integer formats, see the [dither] module.
+//! Streams that arrive or are written in pieces are handled by the stateful [StreamReader] and [StreamWriter].
 //!
 //! ```rust
 //! use rawsample::{SampleWriter, SampleReader, SampleFormat};
@@ -25,10 +28,18 @@
 //! ```
 
 extern crate num_traits;
+pub mod adpcm;
+pub mod dither;
+mod error;
+mod simd;
+mod stream;
+use dither::Dither;
+pub use error::Error;
 use num_traits::{Bounded, Float, ToPrimitive};
-use std::error::Error;
+use std::convert::TryInto;
 use std::io::ErrorKind;
 use std::io::{Read, Write};
+pub use stream::{StreamReader, StreamWriter};
 
 /// The Sample trait is used for low-level conversions of samples stored as raw bytes, to f32 or f64 sample values.
 ///
@@ -36,6 +47,9 @@ use std::io::{Read, Write};
 /// The integer types are mapped to this range.
 /// Using f32, up to 24 byte integers can be converted without loss to and from float.
 /// 32-bit integers required the use of f64 for lossless conversion.
+/// 64-bit integers can not be converted without loss by either type.
+/// An f64 keeps the 53 most significant bits of a 64-bit integer, and an f32 keeps 24 bits.
+/// The remaining low bits are rounded off when reading, and written as zeros.
 ///
 /// The exact range depends on the format. The lower limit is always -1.0. But the upper limit is (2^(n-1)-1)/2^(n-1).
 /// For example for 16-bit integer, the maximum value is (2^15-1)/2^15, approximately +0.99997.
@@ -44,13 +58,23 @@ use std::io::{Read, Write};
 /// Float values are read as they are, and are thus allowed to be outside the -1.0 to +1.0 range.
 ///
 /// When writing samples, the float sample values are clamped to the range supported by the chosen format.
+/// For the integer and fixed point formats, the values are rounded to the nearest integer value, with ties to even.
+/// Other ways of rounding, and other scaling conventions, can be selected with [WriteOptions] and [ReadOptions].
 /// Float output values are also clamped to the -1.0 to +1.0 range, since this is what most audio APIs expect.
-
+/// Other ways of handling values outside the range can be selected with [ClipPolicy].
 pub trait Sample<T: Sized> {
+    const MAX_I64: T;
     const MAX_I32: T;
     const MAX_I24: T;
+    const MAX_I20: T;
+    const MAX_I18: T;
     const MAX_I16: T;
+    const MAX_I8: T;
 
+    /// Convert a sample value to S64LE (8 bytes)
+    fn to_s64_le(&self) -> ([u8; 8], bool);
+    /// Convert a sample value to S64BE (8 bytes)
+    fn to_s64_be(&self) -> ([u8; 8], bool);
     /// Convert a sample value to S32LE (4 bytes)
     fn to_s32_le(&self) -> ([u8; 4], bool);
     /// Convert a sample value to S32BE (4 bytes)
@@ -63,10 +87,51 @@ pub trait Sample<T: Sized> {
     fn to_s24_4_le(&self) -> ([u8; 4], bool);
     /// Convert a sample value to S24BE4 (4 bytes)
     fn to_s24_4_be(&self) -> ([u8; 4], bool);
+    /// Convert a sample value to S24LE4Msb (4 bytes)
+    fn to_s24_4_msb_le(&self) -> ([u8; 4], bool);
+    /// Convert a sample value to S24BE4Msb (4 bytes)
+    fn to_s24_4_msb_be(&self) -> ([u8; 4], bool);
+    /// Convert a sample value to S20LE3 (3 bytes)
+    fn to_s20_3_le(&self) -> ([u8; 3], bool);
+    /// Convert a sample value to S20BE3 (3 bytes)
+    fn to_s20_3_be(&self) -> ([u8; 3], bool);
+    /// Convert a sample value to S18LE3 (3 bytes)
+    fn to_s18_3_le(&self) -> ([u8; 3], bool);
+    /// Convert a sample value to S18BE3 (3 bytes)
+    fn to_s18_3_be(&self) -> ([u8; 3], bool);
     /// Convert a sample value to S16LE (2 bytes)
     fn to_s16_le(&self) -> ([u8; 2], bool);
     /// Convert a sample value to S16BE (2 bytes)
     fn to_s16_be(&self) -> ([u8; 2], bool);
+    /// Convert a sample value to S8 (1 byte)
+    fn to_s8(&self) -> ([u8; 1], bool);
+    /// Convert a pair of sample values to S12PackedLE (3 bytes).
+    /// The number of clipped values in the pair is returned together with the bytes.
+    fn to_s12_packed_le(&self, second: &Self) -> ([u8; 3], usize);
+    /// Convert a pair of sample values to S12PackedBE (3 bytes).
+    /// The number of clipped values in the pair is returned together with the bytes.
+    fn to_s12_packed_be(&self, second: &Self) -> ([u8; 3], usize);
+    /// Convert a sample value to U32LE (4 bytes)
+    fn to_u32_le(&self) -> ([u8; 4], bool);
+    /// Convert a sample value to U32BE (4 bytes)
+    fn to_u32_be(&self) -> ([u8; 4], bool);
+    /// Convert a sample value to U24LE3 (3 bytes)
+    fn to_u24_3_le(&self) -> ([u8; 3], bool);
+    /// Convert a sample value to U24BE3 (3 bytes)
+    fn to_u24_3_be(&self) -> ([u8; 3], bool);
+    /// Convert a sample value to U16LE (2 bytes)
+    fn to_u16_le(&self) -> ([u8; 2], bool);
+    /// Convert a sample value to U16BE (2 bytes)
+    fn to_u16_be(&self) -> ([u8; 2], bool);
+    /// Convert a sample value to U8 (1 byte)
+    fn to_u8(&self) -> ([u8; 1], bool);
+    /// Convert a sample value to G.711 A-law (1 byte)
+    fn to_alaw(&self) -> ([u8; 1], bool);
+    /// Convert a sample value to G.711 mu-law (1 byte)
+    fn to_mulaw(&self) -> ([u8; 1], bool);
+    /// Convert a sample value to a fixed point format.
+    /// Only the first `format.bytes()` bytes of the returned array are used.
+    fn to_fixed(&self, format: &FixedPoint) -> ([u8; 8], bool);
     /// Convert a sample value to F64LE (8 bytes)
     fn to_f64_le(&self) -> ([u8; 8], bool);
     /// Convert a sample value to F64BE (8 bytes)
@@ -75,7 +140,19 @@ pub trait Sample<T: Sized> {
     fn to_f32_le(&self) -> ([u8; 4], bool);
     /// Convert a sample value to F32BE (4 bytes)
     fn to_f32_be(&self) -> ([u8; 4], bool);
+    /// Convert a sample value to F16LE (2 bytes)
+    fn to_f16_le(&self) -> ([u8; 2], bool);
+    /// Convert a sample value to F16BE (2 bytes)
+    fn to_f16_be(&self) -> ([u8; 2], bool);
+    /// Convert a sample value to BF16LE (2 bytes)
+    fn to_bf16_le(&self) -> ([u8; 2], bool);
+    /// Convert a sample value to BF16BE (2 bytes)
+    fn to_bf16_be(&self) -> ([u8; 2], bool);
 
+    /// Convert S64LE (8 bytes) to a sample value
+    fn from_s64_le(bytes: [u8; 8]) -> Self;
+    /// Convert S64BE (8 bytes) to a sample value
+    fn from_s64_be(bytes: [u8; 8]) -> Self;
     /// Convert S32LE (4 bytes) to a sample value
     fn from_s32_le(bytes: [u8; 4]) -> Self;
     /// Convert S32BE (4 bytes) to a sample value
@@ -84,6 +161,16 @@ pub trait Sample<T: Sized> {
     fn from_s16_le(bytes: [u8; 2]) -> Self;
     /// Convert S16BE (2 bytes) to a sample value
     fn from_s16_be(bytes: [u8; 2]) -> Self;
+    /// Convert S8 (1 byte) to a sample value
+    fn from_s8(bytes: [u8; 1]) -> Self;
+    /// Convert S12PackedLE (3 bytes) to a pair of sample values
+    fn from_s12_packed_le(bytes: [u8; 3]) -> [Self; 2]
+    where
+        Self: Sized;
+    /// Convert S12PackedBE (3 bytes) to a pair of sample values
+    fn from_s12_packed_be(bytes: [u8; 3]) -> [Self; 2]
+    where
+        Self: Sized;
     /// Convert S24LE3 (3 bytes) to a sample value
     fn from_s24_3_le(bytes: [u8; 3]) -> Self;
     /// Convert S24BE3 (3 bytes) to a sample value
@@ -92,6 +179,39 @@ pub trait Sample<T: Sized> {
     fn from_s24_4_le(bytes: [u8; 4]) -> Self;
     /// Convert S24BE4 (4 bytes) to a sample value
     fn from_s24_4_be(bytes: [u8; 4]) -> Self;
+    /// Convert S24LE4Msb (4 bytes) to a sample value
+    fn from_s24_4_msb_le(bytes: [u8; 4]) -> Self;
+    /// Convert S24BE4Msb (4 bytes) to a sample value
+    fn from_s24_4_msb_be(bytes: [u8; 4]) -> Self;
+    /// Convert S20LE3 (3 bytes) to a sample value
+    fn from_s20_3_le(bytes: [u8; 3]) -> Self;
+    /// Convert S20BE3 (3 bytes) to a sample value
+    fn from_s20_3_be(bytes: [u8; 3]) -> Self;
+    /// Convert S18LE3 (3 bytes) to a sample value
+    fn from_s18_3_le(bytes: [u8; 3]) -> Self;
+    /// Convert S18BE3 (3 bytes) to a sample value
+    fn from_s18_3_be(bytes: [u8; 3]) -> Self;
+    /// Convert U32LE (4 bytes) to a sample value
+    fn from_u32_le(bytes: [u8; 4]) -> Self;
+    /// Convert U32BE (4 bytes) to a sample value
+    fn from_u32_be(bytes: [u8; 4]) -> Self;
+    /// Convert U24LE3 (3 bytes) to a sample value
+    fn from_u24_3_le(bytes: [u8; 3]) -> Self;
+    /// Convert U24BE3 (3 bytes) to a sample value
+    fn from_u24_3_be(bytes: [u8; 3]) -> Self;
+    /// Convert U16LE (2 bytes) to a sample value
+    fn from_u16_le(bytes: [u8; 2]) -> Self;
+    /// Convert U16BE (2 bytes) to a sample value
+    fn from_u16_be(bytes: [u8; 2]) -> Self;
+    /// Convert U8 (1 byte) to a sample value
+    fn from_u8(bytes: [u8; 1]) -> Self;
+    /// Convert G.711 A-law (1 byte) to a sample value
+    fn from_alaw(bytes: [u8; 1]) -> Self;
+    /// Convert G.711 mu-law (1 byte) to a sample value
+    fn from_mulaw(bytes: [u8; 1]) -> Self;
+    /// Convert a fixed point format to a sample value.
+    /// Only the first `format.bytes()` bytes of the array are used.
+    fn from_fixed(bytes: [u8; 8], format: &FixedPoint) -> Self;
     /// Convert F32LE (4 bytes) to a sample value
     fn from_f32_le(bytes: [u8; 4]) -> Self;
     /// Convert F32BE (4 bytes) to a sample value
@@ -100,10 +220,31 @@ pub trait Sample<T: Sized> {
     fn from_f64_le(bytes: [u8; 8]) -> Self;
     /// Convert F64BE (8 bytes) to a sample value
     fn from_f64_be(bytes: [u8; 8]) -> Self;
+    /// Convert F16LE (2 bytes) to a sample value
+    fn from_f16_le(bytes: [u8; 2]) -> Self;
+    /// Convert F16BE (2 bytes) to a sample value
+    fn from_f16_be(bytes: [u8; 2]) -> Self;
+    /// Convert BF16LE (2 bytes) to a sample value
+    fn from_bf16_le(bytes: [u8; 2]) -> Self;
+    /// Convert BF16BE (2 bytes) to a sample value
+    fn from_bf16_be(bytes: [u8; 2]) -> Self;
 }
 
 /// The supported sample formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SampleFormat {
+    /// 8 bit signed integer.
+    S8,
+    /// 12 bit signed integer, with two samples packed in 3 bytes.
+    /// The first sample is stored in the first byte and the low nibble of the second byte,
+    /// and the second sample in the high nibble of the second byte and the third byte.
+    /// A single trailing sample is stored as 2 bytes.
+    S12PackedLE,
+    /// 12 bit signed integer, with two samples packed in 3 bytes.
+    /// The first sample is stored in the first byte and the high nibble of the second byte,
+    /// and the second sample in the low nibble of the second byte and the third byte.
+    /// A single trailing sample is stored as 2 bytes.
+    S12PackedBE,
     /// 16 bit signed integer, little endian.
     S16LE,
     /// 16 bit signed integer, big endian.
@@ -116,10 +257,46 @@ pub enum SampleFormat {
     S24LE4,
     /// 24 bit signed integer, big endian, stored as 4 bytes. The data is in the lower 3 bytes and the most significant byte is padding.
     S24BE4,
+    /// 24 bit signed integer, little endian, stored as 4 bytes. The data is in the upper 3 bytes and the least significant byte is padding.
+    S24LE4Msb,
+    /// 24 bit signed integer, big endian, stored as 4 bytes. The data is in the upper 3 bytes and the least significant byte is padding.
+    S24BE4Msb,
+    /// 20 bit signed integer, little endian, stored as 3 bytes. The data is in the lower 20 bits and the upper 4 bits are padding.
+    S20LE3,
+    /// 20 bit signed integer, big endian, stored as 3 bytes. The data is in the lower 20 bits and the upper 4 bits are padding.
+    S20BE3,
+    /// 18 bit signed integer, little endian, stored as 3 bytes. The data is in the lower 18 bits and the upper 6 bits are padding.
+    S18LE3,
+    /// 18 bit signed integer, big endian, stored as 3 bytes. The data is in the lower 18 bits and the upper 6 bits are padding.
+    S18BE3,
     /// 32 bit signed integer, little endian.
     S32LE,
     /// 32 bit signed integer, big endian.
     S32BE,
+    /// 64 bit signed integer, little endian.
+    S64LE,
+    /// 64 bit signed integer, big endian.
+    S64BE,
+    /// 8 bit unsigned integer, offset binary with 128 as the zero level.
+    U8,
+    /// 16 bit unsigned integer, little endian, offset binary with 2^15 as the zero level.
+    U16LE,
+    /// 16 bit unsigned integer, big endian, offset binary with 2^15 as the zero level.
+    U16BE,
+    /// 24 bit unsigned integer, little endian, stored as 3 bytes, offset binary with 2^23 as the zero level.
+    U24LE3,
+    /// 24 bit unsigned integer, big endian, stored as 3 bytes, offset binary with 2^23 as the zero level.
+    U24BE3,
+    /// 32 bit unsigned integer, little endian, offset binary with 2^31 as the zero level.
+    U32LE,
+    /// 32 bit unsigned integer, big endian, offset binary with 2^31 as the zero level.
+    U32BE,
+    /// 8 bit G.711 A-law companded, with 13 bits of linear resolution.
+    ALaw,
+    /// 8 bit G.711 mu-law companded, with 14 bits of linear resolution.
+    MuLaw,
+    /// Fixed point with a configurable number of integer and fraction bits, see `FixedPoint`.
+    FixedPoint(FixedPoint),
     /// 32 bit floating point, little endian.
     F32LE,
     /// 32 bit floating point, big endian.
@@ -128,1023 +305,5674 @@ pub enum SampleFormat {
     F64LE,
     /// 64 bit floating point, big endian.
     F64BE,
+    /// 16 bit IEEE 754 half precision floating point, little endian.
+    F16LE,
+    /// 16 bit IEEE 754 half precision floating point, big endian.
+    F16BE,
+    /// 16 bit bfloat16 floating point, little endian. This is the upper half of an f32.
+    BF16LE,
+    /// 16 bit bfloat16 floating point, big endian. This is the upper half of an f32.
+    BF16BE,
 }
 
-macro_rules! write_samples {
-    ($values:expr, $target:expr, $conv:ident) => {{
-        let mut nbr_clipped = 0;
-        for value in $values.iter() {
-            let (bytes, clipped) = value.$conv();
-            if clipped {
-                nbr_clipped += 1;
-            }
-            $target.write_all(&bytes)?;
-        }
-        nbr_clipped
-    }};
-}
-
-/// The SampleWriter trait enables converting and writing many sample values from a slice.
-pub trait SampleWriter<T: Sample<T>> {
-    /// Write sample values from a slice to anything that implements the "Write" trait.
-    /// This can be for example a file, or a Vec of u8.
-    /// Input samples are f32 or f64, and are converted to the given sample format.
-    /// The sample values are clamped to the range supported by the output format.
-    /// For the float types, the input range is -1.0 to +1.0.
-    /// For the integer types, the input range doesn't include 1.0.
-    /// For example for I16 the maximum value is (2^15-1)/2^15, approximately +0.99997.
-    /// The number of clipped samples is returned.
-    fn write_samples(
-        values: &[T],
-        target: &mut dyn Write,
-        sformat: &SampleFormat,
-    ) -> Result<usize, Box<dyn Error>> {
-        let nbr_clipped;
-        match sformat {
-            SampleFormat::S16LE => {
-                nbr_clipped = write_samples!(values, target, to_s16_le);
-            }
-            SampleFormat::S16BE => {
-                nbr_clipped = write_samples!(values, target, to_s16_be);
-            }
-            SampleFormat::S24LE3 => {
-                nbr_clipped = write_samples!(values, target, to_s24_3_le);
-            }
-            SampleFormat::S24BE3 => {
-                nbr_clipped = write_samples!(values, target, to_s24_3_be);
-            }
-            SampleFormat::S24LE4 => {
-                nbr_clipped = write_samples!(values, target, to_s24_4_le);
-            }
-            SampleFormat::S24BE4 => {
-                nbr_clipped = write_samples!(values, target, to_s24_4_be);
-            }
-            SampleFormat::S32LE => {
-                nbr_clipped = write_samples!(values, target, to_s32_le);
-            }
-            SampleFormat::S32BE => {
-                nbr_clipped = write_samples!(values, target, to_s32_be);
-            }
-            SampleFormat::F32LE => {
-                nbr_clipped = write_samples!(values, target, to_f32_le);
-            }
-            SampleFormat::F32BE => {
-                nbr_clipped = write_samples!(values, target, to_f32_be);
-            }
-            SampleFormat::F64LE => {
-                nbr_clipped = write_samples!(values, target, to_f64_le);
-            }
-            SampleFormat::F64BE => {
-                nbr_clipped = write_samples!(values, target, to_f64_be);
-            }
+impl SampleFormat {
+    /// Get the number of bits used to store one sample, including any padding.
+    /// This is 12 for the packed 12 bit formats, where two samples share 3 bytes.
+    pub fn storage_bits(&self) -> usize {
+        match self {
+            SampleFormat::S8 | SampleFormat::U8 | SampleFormat::ALaw | SampleFormat::MuLaw => 8,
+            SampleFormat::S12PackedLE | SampleFormat::S12PackedBE => 12,
+            SampleFormat::S16LE
+            | SampleFormat::S16BE
+            | SampleFormat::U16LE
+            | SampleFormat::U16BE
+            | SampleFormat::F16LE
+            | SampleFormat::F16BE
+            | SampleFormat::BF16LE
+            | SampleFormat::BF16BE => 16,
+            SampleFormat::S24LE3
+            | SampleFormat::S24BE3
+            | SampleFormat::S20LE3
+            | SampleFormat::S20BE3
+            | SampleFormat::S18LE3
+            | SampleFormat::S18BE3
+            | SampleFormat::U24LE3
+            | SampleFormat::U24BE3 => 24,
+            SampleFormat::S24LE4
+            | SampleFormat::S24BE4
+            | SampleFormat::S24LE4Msb
+            | SampleFormat::S24BE4Msb
+            | SampleFormat::S32LE
+            | SampleFormat::S32BE
+            | SampleFormat::U32LE
+            | SampleFormat::U32BE
+            | SampleFormat::F32LE
+            | SampleFormat::F32BE => 32,
+            SampleFormat::S64LE
+            | SampleFormat::S64BE
+            | SampleFormat::F64LE
+            | SampleFormat::F64BE => 64,
+            SampleFormat::FixedPoint(fixed) => 8 * fixed.bytes(),
         }
-        Ok(nbr_clipped)
     }
-}
 
-impl SampleWriter<f64> for f64 {}
-impl SampleWriter<f32> for f32 {}
-
-macro_rules! read_samples_to_slice {
-    ($data:expr, $values:expr, $conv:ident, $n:expr) => {{
-        let mut nbr_read = 0;
-        for value in $values.iter_mut() {
-            let mut bytes = [0; $n];
-            match $data.read_exact(&mut bytes) {
-                Ok(()) => {}
-                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => {
-                    break;
-                }
-                Err(err) => return Err(Box::new(err)),
-            }
-            let newvalue = T::$conv(bytes);
-            *value = newvalue;
-            nbr_read += 1;
+    /// Get the size of the least significant bit of the linear integer and fixed point formats, as a float value.
+    /// This is `None` for the float formats, and for the companded A-law and mu-law formats.
+    fn lsb(&self) -> Option<f64> {
+        match self {
+            SampleFormat::FixedPoint(fixed) => Some(0.5.powi(fixed.frac_bits as i32)),
+            _ => self.full_scale().map(|full_scale| 1.0 / full_scale),
         }
-        nbr_read
-    }};
-}
+    }
 
-macro_rules! read_all_samples_to_vec {
-    ($data:expr, $values:expr, $conv:ident, $n:expr) => {{
-        let mut bytes = [0; $n];
-        loop {
-            match $data.read_exact(&mut bytes) {
-                Ok(()) => {}
-                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => {
-                    break;
-                }
-                Err(err) => return Err(Box::new(err)),
-            }
-            let newvalue = T::$conv(bytes);
-            $values.push(newvalue);
+    /// Get the smallest and largest integer values of the linear integer and fixed point formats.
+    fn int_range(&self) -> Option<(f64, f64)> {
+        match self {
+            SampleFormat::FixedPoint(fixed) => Some(fixed.int_range::<f64>()),
+            _ => self
+                .full_scale()
+                .map(|full_scale| (-full_scale, full_scale - 1.0)),
         }
-    }};
-}
+    }
 
-/// The SampleReader trait enables reading and converting raw bytes and to multiple samples.
+    /// Get the full scale value 2^(n-1) of the linear n bit integer formats.
+    /// This is `None` for the fixed point, float, and companded formats.
+    fn full_scale(&self) -> Option<f64> {
+        let bits = match self {
+            SampleFormat::S8 | SampleFormat::U8 => 8,
+            SampleFormat::S12PackedLE | SampleFormat::S12PackedBE => 12,
+            SampleFormat::S16LE
+            | SampleFormat::S16BE
+            | SampleFormat::U16LE
+            | SampleFormat::U16BE => 16,
+            SampleFormat::S18LE3 | SampleFormat::S18BE3 => 18,
+            SampleFormat::S20LE3 | SampleFormat::S20BE3 => 20,
+            SampleFormat::S24LE3
+            | SampleFormat::S24BE3
+            | SampleFormat::S24LE4
+            | SampleFormat::S24BE4
+            | SampleFormat::S24LE4Msb
+            | SampleFormat::S24BE4Msb
+            | SampleFormat::U24LE3
+            | SampleFormat::U24BE3 => 24,
+            SampleFormat::S32LE
+            | SampleFormat::S32BE
+            | SampleFormat::U32LE
+            | SampleFormat::U32BE => 32,
+            SampleFormat::S64LE | SampleFormat::S64BE => 64,
+            _ => return None,
+        };
+        Some(2.0.powi(bits - 1))
+    }
 
-pub trait SampleReader<T: Sample<T>> {
-    /// Read bytes from anything that implements the "Read" trait.
-    /// This can be for example a file, or a slice of u8.
-    /// The bytes are then converted to f32 or f64 values, and stored in a slice.
-    /// It will read until the samples slice is filled.
-    /// If end-of-file of the source is reached before the slice is filled, the remaining values of the slice are left untouched.
-    /// The number of samples read is returned.
-    fn read_samples(
-        rawbytes: &mut dyn Read,
-        samples: &mut [T],
-        sampleformat: &SampleFormat,
-    ) -> Result<usize, Box<dyn Error>> {
-        let nbr_read;
-        match sampleformat {
-            SampleFormat::S16LE => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_s16_le, 2);
-            }
-            SampleFormat::S16BE => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_s16_be, 2);
-            }
-            SampleFormat::S24LE3 => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_s24_3_le, 3);
-            }
-            SampleFormat::S24BE3 => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_s24_3_be, 3);
-            }
-            SampleFormat::S24LE4 => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_s24_4_le, 4);
-            }
-            SampleFormat::S24BE4 => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_s24_4_be, 4);
-            }
-            SampleFormat::S32LE => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_s32_le, 4);
-            }
-            SampleFormat::S32BE => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_s32_be, 4);
-            }
-            SampleFormat::F32LE => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_f32_le, 4);
-            }
-            SampleFormat::F32BE => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_f32_be, 4);
-            }
-            SampleFormat::F64LE => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_f64_le, 8);
-            }
-            SampleFormat::F64BE => {
-                nbr_read = read_samples_to_slice!(rawbytes, samples, from_f64_be, 8);
-            }
+    /// Get the number of samples that can be read individually from a number of samples.
+    /// The packed formats are read in pairs, and then this is rounded down to an even number.
+    fn whole_samples(&self, nbr_samples: usize) -> usize {
+        match self {
+            SampleFormat::S12PackedLE | SampleFormat::S12PackedBE => nbr_samples & !1,
+            _ => nbr_samples,
         }
-        Ok(nbr_read)
     }
 
-    /// Read all bytes from anything that implements the "Read" trait.
-    /// This can be for example a file, or a slice of u8.
-    /// The bytes are then converted to f32 or f64 values, and appended to a vec.
-    /// It will continue reading until reaching end-of-file of the source.
-    /// The number of samples read is returned.
-    fn read_all_samples(
-        rawbytes: &mut dyn Read,
-        samples: &mut Vec<T>,
-        sampleformat: &SampleFormat,
-    ) -> Result<usize, Box<dyn Error>> {
-        let start_len = samples.len();
-        match sampleformat {
-            SampleFormat::S16LE => {
-                read_all_samples_to_vec!(rawbytes, samples, from_s16_le, 2);
-            }
-            SampleFormat::S16BE => {
-                read_all_samples_to_vec!(rawbytes, samples, from_s16_be, 2);
-            }
-            SampleFormat::S24LE3 => {
-                read_all_samples_to_vec!(rawbytes, samples, from_s24_3_le, 3);
-            }
-            SampleFormat::S24BE3 => {
-                read_all_samples_to_vec!(rawbytes, samples, from_s24_3_be, 3);
-            }
-            SampleFormat::S24LE4 => {
-                read_all_samples_to_vec!(rawbytes, samples, from_s24_4_le, 4);
-            }
-            SampleFormat::S24BE4 => {
-                read_all_samples_to_vec!(rawbytes, samples, from_s24_4_be, 4);
-            }
-            SampleFormat::S32LE => {
-                read_all_samples_to_vec!(rawbytes, samples, from_s32_le, 4);
-            }
-            SampleFormat::S32BE => {
-                read_all_samples_to_vec!(rawbytes, samples, from_s32_be, 4);
-            }
-            SampleFormat::F32LE => {
-                read_all_samples_to_vec!(rawbytes, samples, from_f32_le, 4);
-            }
-            SampleFormat::F32BE => {
-                read_all_samples_to_vec!(rawbytes, samples, from_f32_be, 4);
-            }
-            SampleFormat::F64LE => {
-                read_all_samples_to_vec!(rawbytes, samples, from_f64_le, 8);
-            }
-            SampleFormat::F64BE => {
-                read_all_samples_to_vec!(rawbytes, samples, from_f64_be, 8);
-            }
+    /// Get the number of bytes that hold whole pairs of the packed formats.
+    /// For the other formats, the number of bytes is returned unchanged.
+    fn whole_pair_bytes(&self, nbr_bytes: usize) -> usize {
+        match self {
+            SampleFormat::S12PackedLE | SampleFormat::S12PackedBE => nbr_bytes - nbr_bytes % 3,
+            _ => nbr_bytes,
         }
-        Ok(samples.len() - start_len)
     }
-}
 
-impl SampleReader<f64> for f64 {}
-impl SampleReader<f32> for f32 {}
-
-/// Clamp a float value to the range supported by an integer type
-fn clamp_int<T: Float, U: Bounded + ToPrimitive>(value: T) -> (T, bool) {
-    if value > T::from(U::max_value()).unwrap() {
-        return (T::from(U::max_value()).unwrap(), true);
-    } else if value < T::from(U::min_value()).unwrap() {
-        return (T::from(U::min_value()).unwrap(), true);
+    /// Get the number of bytes used to store a number of samples.
+    /// For the packed formats, this only includes whole pairs.
+    fn bytes_for_samples(&self, nbr_samples: usize) -> usize {
+        match self {
+            SampleFormat::S12PackedLE | SampleFormat::S12PackedBE => 3 * (nbr_samples / 2),
+            _ => nbr_samples * self.storage_bits() / 8,
+        }
     }
-    (value, false)
-}
 
-/// Clamp a float value to the -1.0 .. +1.0
-fn clamp_float<T: Float>(value: T) -> (T, bool) {
-    if value >= T::one() {
-        return (T::one(), true);
-    } else if value < -T::one() {
-        return (-T::one(), true);
+    /// Get the number of bytes used to store one frame of the given number of channels.
+    fn frame_bytes(&self, channels: usize) -> Result<usize, Error> {
+        if channels == 0 {
+            return Err(Error::InvalidParameter(
+                "the number of channels must be at least 1",
+            ));
+        }
+        let bits = self.storage_bits() * channels;
+        if bits % 8 != 0 {
+            return Err(Error::UnsupportedFormat(
+                "the frames of this format and number of channels are not a whole number of bytes",
+            ));
+        }
+        Ok(bits / 8)
     }
-    (value, false)
 }
 
-impl Sample<f64> for f64 {
-    const MAX_I32: f64 = 2147483648.0;
-    const MAX_I24: f64 = 8388608.0;
-    const MAX_I16: f64 = 32768.0;
-
-    fn to_s16_le(&self) -> ([u8; 2], bool) {
-        let val = self * f64::MAX_I16;
-        let (val, clipped) = clamp_int::<f64, i16>(val);
-        ((val as i16).to_le_bytes(), clipped)
-    }
-
-    fn to_s16_be(&self) -> ([u8; 2], bool) {
-        let val = self * f64::MAX_I16;
-        let (val, clipped) = clamp_int::<f64, i16>(val);
-        ((val as i16).to_be_bytes(), clipped)
-    }
+/// A fixed point sample format, also known as a Q format.
+///
+/// The format has `int_bits` integer bits, including the sign bit, and `frac_bits` fraction bits.
+/// The value is stored in the lowest `int_bits + frac_bits` bits of a word of `bytes` bytes.
+/// When writing, any unused upper bits of the word are filled with copies of the sign bit.
+/// When reading, they are ignored.
+///
+/// Unlike the other formats, the range is not limited to -1.0 to +1.0.
+/// The integer bits give headroom, and the range is -2^(int_bits-1) <= value < +2^(int_bits-1).
+/// For example Q1.15 in 2 bytes is equivalent to S16LE or S16BE,
+/// while Q8.23 in 4 bytes can represent values from -128.0 up to almost +128.0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedPoint {
+    int_bits: u32,
+    frac_bits: u32,
+    bytes: usize,
+    big_endian: bool,
+}
 
-    fn to_s32_le(&self) -> ([u8; 4], bool) {
-        let val = self * f64::MAX_I32;
-        let (val, clipped) = clamp_int::<f64, i32>(val);
-        ((val as i32).to_le_bytes(), clipped)
+impl FixedPoint {
+    /// Create a new little endian fixed point format.
+    /// Returns an error if `int_bits` is zero, `bytes` is not in the range 1 to 8,
+    /// or if the number of bits does not fit in the given number of bytes.
+    pub fn le(int_bits: u32, frac_bits: u32, bytes: usize) -> Result<Self, Error> {
+        FixedPoint::new(int_bits, frac_bits, bytes, false)
     }
 
-    fn to_s32_be(&self) -> ([u8; 4], bool) {
-        let val = self * f64::MAX_I32;
-        let (val, clipped) = clamp_int::<f64, i32>(val);
-        ((val as i32).to_be_bytes(), clipped)
+    /// Create a new big endian fixed point format.
+    /// Returns an error if `int_bits` is zero, `bytes` is not in the range 1 to 8,
+    /// or if the number of bits does not fit in the given number of bytes.
+    pub fn be(int_bits: u32, frac_bits: u32, bytes: usize) -> Result<Self, Error> {
+        FixedPoint::new(int_bits, frac_bits, bytes, true)
     }
 
-    fn to_s24_3_le(&self) -> ([u8; 3], bool) {
-        let val = self * f64::MAX_I32;
-        let (val, clipped) = clamp_int::<f64, i32>(val);
-        let bytes = (val as i32).to_le_bytes();
-        ([bytes[1], bytes[2], bytes[3]], clipped)
+    fn new(int_bits: u32, frac_bits: u32, bytes: usize, big_endian: bool) -> Result<Self, Error> {
+        if int_bits == 0 {
+            return Err(Error::InvalidParameter(
+                "a fixed point format needs at least one integer bit",
+            ));
+        }
+        if bytes == 0 || bytes > 8 {
+            return Err(Error::InvalidParameter(
+                "a fixed point format must use between 1 and 8 bytes",
+            ));
+        }
+        if int_bits
+            .checked_add(frac_bits)
+            .filter(|bits| *bits <= 8 * bytes as u32)
+            .is_none()
+        {
+            return Err(Error::InvalidParameter(
+                "the fixed point bits do not fit in the given number of bytes",
+            ));
+        }
+        Ok(FixedPoint {
+            int_bits,
+            frac_bits,
+            bytes,
+            big_endian,
+        })
     }
 
-    fn to_s24_3_be(&self) -> ([u8; 3], bool) {
-        let val = self * f64::MAX_I32;
-        let (val, clipped) = clamp_int::<f64, i32>(val);
-        let bytes = (val as i32).to_be_bytes();
-        ([bytes[0], bytes[1], bytes[2]], clipped)
+    /// The number of integer bits, including the sign bit.
+    pub fn int_bits(&self) -> u32 {
+        self.int_bits
     }
 
-    fn to_s24_4_le(&self) -> ([u8; 4], bool) {
-        let val = self * f64::MAX_I32;
-        let (val, clipped) = clamp_int::<f64, i32>(val);
-        let bytes = (val as i32).to_le_bytes();
-        ([bytes[1], bytes[2], bytes[3], 0], clipped)
+    /// The number of fraction bits.
+    pub fn frac_bits(&self) -> u32 {
+        self.frac_bits
     }
 
-    fn to_s24_4_be(&self) -> ([u8; 4], bool) {
-        let val = self * f64::MAX_I32;
-        let (val, clipped) = clamp_int::<f64, i32>(val);
-        let bytes = (val as i32).to_be_bytes();
-        ([0, bytes[0], bytes[1], bytes[2]], clipped)
+    /// The number of bytes used to store each sample.
+    pub fn bytes(&self) -> usize {
+        self.bytes
     }
 
-    fn to_f64_le(&self) -> ([u8; 8], bool) {
-        let val = *self;
-        let (val, clipped) = clamp_float(val);
-        (val.to_le_bytes(), clipped)
+    /// Returns true if the bytes are stored in big endian order.
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
     }
 
-    fn to_f64_be(&self) -> ([u8; 8], bool) {
-        let val = *self;
-        let (val, clipped) = clamp_float(val);
-        (val.to_be_bytes(), clipped)
+    /// The factor between a sample value and the stored integer, 2^frac_bits.
+    fn scale<T: Float>(&self) -> T {
+        T::from(1u64 << self.frac_bits).unwrap()
     }
 
-    fn to_f32_le(&self) -> ([u8; 4], bool) {
-        let val = *self as f32;
-        let (val, clipped) = clamp_float(val);
-        (val.to_le_bytes(), clipped)
+    /// The smallest and largest integers that fit in the used bits.
+    fn int_range<T: Float>(&self) -> (T, T) {
+        let shift = 64 - self.int_bits - self.frac_bits;
+        (
+            T::from(i64::MIN >> shift).unwrap(),
+            T::from(i64::MAX >> shift).unwrap(),
+        )
     }
 
-    fn to_f32_be(&self) -> ([u8; 4], bool) {
-        let val = *self as f32;
-        let (val, clipped) = clamp_float(val);
-        (val.to_be_bytes(), clipped)
+    /// Clamp an integer value to the range that fits in the used bits.
+    fn clamp(&self, value: i64) -> (i64, bool) {
+        let shift = 64 - self.int_bits - self.frac_bits;
+        let (min, max) = (i64::MIN >> shift, i64::MAX >> shift);
+        if value > max {
+            return (max, true);
+        } else if value < min {
+            return (min, true);
+        }
+        (value, false)
     }
 
-    fn from_s32_le(bytes: [u8; 4]) -> Self {
-        let intvalue = i32::from_le_bytes(bytes);
-        f64::from(intvalue) / f64::MAX_I32
+    /// Store an integer value as bytes, sign extended to fill the word.
+    fn encode(&self, value: i64) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        if self.big_endian {
+            bytes[..self.bytes].copy_from_slice(&value.to_be_bytes()[8 - self.bytes..]);
+        } else {
+            bytes[..self.bytes].copy_from_slice(&value.to_le_bytes()[..self.bytes]);
+        }
+        bytes
     }
 
-    fn from_s32_be(bytes: [u8; 4]) -> Self {
-        let intvalue = i32::from_be_bytes(bytes);
-        f64::from(intvalue) / f64::MAX_I32
+    /// Read an integer value from bytes, ignoring any unused upper bits of the word.
+    fn decode(&self, bytes: [u8; 8]) -> i64 {
+        let mut padded = [0; 8];
+        padded[..self.bytes].copy_from_slice(&bytes[..self.bytes]);
+        if !self.big_endian {
+            padded[..self.bytes].reverse();
+        }
+        let word = i64::from_be_bytes(padded) >> (64 - 8 * self.bytes as u32);
+        let shift = 64 - self.int_bits - self.frac_bits;
+        (word << shift) >> shift
     }
+}
 
-    fn from_s16_le(bytes: [u8; 2]) -> Self {
-        let intvalue = i16::from_le_bytes(bytes);
-        f64::from(intvalue) / f64::MAX_I16
-    }
+/// The rounding used when a value is quantised to an integer or fixed point format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quantization {
+    /// Round to the nearest value, and values exactly halfway between two values to the even one.
+    /// This is the default. It is symmetric for positive and negative values, and has no bias.
+    #[default]
+    RoundHalfEven,
+    /// Round to the nearest value, and values exactly halfway between two values towards positive infinity.
+    RoundHalfUp,
+    /// Round towards negative infinity.
+    Floor,
+    /// Round towards zero, like a plain integer cast.
+    Truncate,
+}
 
-    fn from_s16_be(bytes: [u8; 2]) -> Self {
-        let intvalue = i16::from_be_bytes(bytes);
-        f64::from(intvalue) / f64::MAX_I16
+impl Quantization {
+    /// Quantise a value to a multiple of the given least significant bit.
+    pub(crate) fn quantise<T: Float>(&self, value: T, lsb: T) -> T {
+        let scaled = value / lsb;
+        let half = T::from(0.5).unwrap();
+        let rounded = match self {
+            Quantization::RoundHalfEven => {
+                let floor = scaled.floor();
+                let fraction = scaled - floor;
+                if fraction > half || (fraction == half && (floor * half).fract() != T::zero()) {
+                    floor + T::one()
+                } else {
+                    floor
+                }
+            }
+            Quantization::RoundHalfUp => {
+                let floor = scaled.floor();
+                if scaled - floor >= half {
+                    floor + T::one()
+                } else {
+                    floor
+                }
+            }
+            Quantization::Floor => scaled.floor(),
+            Quantization::Truncate => scaled.trunc(),
+        };
+        rounded * lsb
     }
+}
 
-    fn from_s24_3_le(bytes: [u8; 3]) -> Self {
-        let padded = [0, bytes[0], bytes[1], bytes[2]];
-        let intvalue = i32::from_le_bytes(padded);
-        f64::from(intvalue) / f64::MAX_I32
-    }
+/// The convention used for scaling between float values and the linear integer formats.
+///
+/// The conventions differ in how the float range -1.0 to +1.0 is mapped to the range of an n bit integer.
+/// Data written with one convention should be read with the same one, to get the original values back.
+/// Some libraries use different conventions for reading and writing.
+/// For example libsndfile writes normalized float values with [Scaling::MaxInteger], and reads them with [Scaling::PowerOfTwo].
+/// When reading the 32 bit formats as f32, or the 64 bit formats, the sample type can't represent 2^(n-1)-1,
+/// and the values are read the same as with [Scaling::PowerOfTwo].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scaling {
+    /// Scale both negative and positive values by 2^(n-1).
+    /// -1.0 maps to the minimum value, and the maximum value is (2^(n-1)-1)/2^(n-1), just below +1.0.
+    /// This is the default.
+    #[default]
+    PowerOfTwo,
+    /// Scale both negative and positive values by 2^(n-1)-1.
+    /// +1.0 maps to the maximum value, and -1.0 to the value just above the minimum.
+    /// The minimum value is read as slightly below -1.0.
+    MaxInteger,
+    /// Scale negative values by 2^(n-1), and positive values by 2^(n-1)-1.
+    /// -1.0 maps to the minimum value, and +1.0 to the maximum value.
+    Asymmetric,
+}
 
-    fn from_s24_3_be(bytes: [u8; 3]) -> Self {
-        let padded = [bytes[0], bytes[1], bytes[2], 0];
-        let intvalue = i32::from_be_bytes(padded);
-        f64::from(intvalue) / f64::MAX_I32
+impl Scaling {
+    /// Get the factor to multiply a value with, for an integer format with the full scale value 2^(n-1).
+    fn scale(&self, value: f64, full_scale: f64) -> f64 {
+        match self {
+            Scaling::PowerOfTwo => full_scale,
+            Scaling::MaxInteger => full_scale - 1.0,
+            Scaling::Asymmetric if value > 0.0 => full_scale - 1.0,
+            Scaling::Asymmetric => full_scale,
+        }
     }
 
-    fn from_s24_4_le(bytes: [u8; 4]) -> Self {
-        let padded = [0, bytes[0], bytes[1], bytes[2]];
-        let intvalue = i32::from_le_bytes(padded);
-        f64::from(intvalue) / f64::MAX_I32
+    /// Rescale values that were read with the default scaling to this scaling.
+    /// The default scaling divides the integers by 2^(n-1), which only changes the exponent.
+    /// When T represents the integers of the format exactly, multiplying by 2^(n-1) gives them back,
+    /// and they are then divided by the scale of this scaling, with a single rounding.
+    /// That is not the case for the 32 bit formats read as f32, or the 64 bit formats.
+    /// The integers are then already rounded when read, and 2^(n-1)-1 also rounds to 2^(n-1),
+    /// which means that the values are the same as with the default scaling.
+    fn rescale<T: Float>(&self, values: &mut [T], sformat: &SampleFormat) {
+        let full_scale = match sformat.full_scale() {
+            Some(full_scale) if *self != Scaling::PowerOfTwo => full_scale,
+            _ => return,
+        };
+        let power_of_two = T::from(full_scale).unwrap();
+        let positive = T::from(self.scale(1.0, full_scale)).unwrap();
+        let negative = T::from(self.scale(-1.0, full_scale)).unwrap();
+        for value in values.iter_mut() {
+            let intvalue = *value * power_of_two;
+            *value = if intvalue > T::zero() {
+                intvalue / positive
+            } else {
+                intvalue / negative
+            };
+        }
     }
+}
 
-    fn from_s24_4_be(bytes: [u8; 4]) -> Self {
-        let padded = [bytes[1], bytes[2], bytes[3], 0];
-        let intvalue = i32::from_be_bytes(padded);
-        f64::from(intvalue) / f64::MAX_I32
-    }
+/// What to do with values that are outside the range of the output format when writing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipPolicy {
+    /// Clamp the values to the range of the output format, and return the number of clipped values.
+    /// For the float formats the range is -1.0 to +1.0.
+    /// This is the default.
+    #[default]
+    Clamp,
+    /// Fail with [Error::Clipped] at the first value outside the range.
+    /// The values before it are still converted.
+    Fail,
+    /// Wrap values outside the range around to the other end, like a plain integer cast.
+    /// This applies to the linear integer and fixed point formats, while the other formats are clamped.
+    /// The wrapped values are included in the number of clipped values.
+    Wrap,
+    /// Write values outside the -1.0 to +1.0 range as they are to the float formats, to keep the headroom.
+    /// The other formats are clamped.
+    PassThrough,
+}
 
-    fn from_f32_le(bytes: [u8; 4]) -> Self {
-        f64::from(f32::from_le_bytes(bytes))
-    }
+/// Options for how the values are converted when writing.
+/// The default options give the same result as the plain write functions, such as [SampleWriter::write_samples].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteOptions {
+    /// The rounding used for the linear integer and fixed point formats.
+    /// The companded A-law and mu-law formats are always rounded to the nearest 16 bit value before companding.
+    pub quantization: Quantization,
+    /// The scaling used for the linear integer formats.
+    /// The fixed point formats are always scaled by 2^frac_bits.
+    pub scaling: Scaling,
+    /// What to do with values outside the range of the output format.
+    pub clipping: ClipPolicy,
+}
 
-    fn from_f32_be(bytes: [u8; 4]) -> Self {
-        f64::from(f32::from_be_bytes(bytes))
-    }
+/// Options for how the values are converted when reading.
+/// The default options give the same result as the plain read functions, such as [SampleReader::read_samples].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReadOptions {
+    /// The scaling used for the linear integer formats.
+    /// The fixed point formats are always scaled by 2^frac_bits.
+    pub scaling: Scaling,
+}
 
-    fn from_f64_le(bytes: [u8; 8]) -> Self {
-        f64::from_le_bytes(bytes)
-    }
+macro_rules! convert_samples_to_bytes {
+    ($values:expr, $rawbytes:expr, $conv:ident, $n:expr) => {{
+        let mut nbr_converted = 0;
+        let mut nbr_clipped = 0;
+        for (value, chunk) in $values.iter().zip($rawbytes.chunks_exact_mut($n)) {
+            let (bytes, clipped) = value.$conv();
+            if clipped {
+                nbr_clipped += 1;
+            }
+            chunk.copy_from_slice(&bytes);
+            nbr_converted += 1;
+        }
+        (nbr_converted, nbr_converted * $n, nbr_clipped)
+    }};
+    ($values:expr, $rawbytes:expr, $conv:ident, $format:expr, $n:expr) => {{
+        let mut nbr_converted = 0;
+        let mut nbr_clipped = 0;
+        for (value, chunk) in $values.iter().zip($rawbytes.chunks_exact_mut($n)) {
+            let (bytes, clipped) = value.$conv($format);
+            if clipped {
+                nbr_clipped += 1;
+            }
+            chunk.copy_from_slice(&bytes[..$n]);
+            nbr_converted += 1;
+        }
+        (nbr_converted, nbr_converted * $n, nbr_clipped)
+    }};
+}
 
-    fn from_f64_be(bytes: [u8; 8]) -> Self {
-        f64::from_be_bytes(bytes)
-    }
+macro_rules! convert_sample_pairs_to_bytes {
+    ($values:expr, $rawbytes:expr, $conv:ident) => {{
+        let mut nbr_converted = 0;
+        let mut nbr_bytes = 0;
+        let mut nbr_clipped = 0;
+        for pair in $values.chunks(2) {
+            let remaining = $rawbytes.len() - nbr_bytes;
+            if pair.len() == 2 && remaining >= 3 {
+                let (bytes, clipped) = pair[0].$conv(&pair[1]);
+                nbr_clipped += clipped;
+                $rawbytes[nbr_bytes..nbr_bytes + 3].copy_from_slice(&bytes);
+                nbr_converted += 2;
+                nbr_bytes += 3;
+            } else if pair.len() == 1 && remaining >= 2 {
+                // pad with a zero sample, and write only the bytes of the first one
+                let (bytes, clipped) = pair[0].$conv(&T::from_s8([0]));
+                nbr_clipped += clipped;
+                $rawbytes[nbr_bytes..nbr_bytes + 2].copy_from_slice(&bytes[0..2]);
+                nbr_converted += 1;
+                nbr_bytes += 2;
+            } else {
+                break;
+            }
+        }
+        (nbr_converted, nbr_bytes, nbr_clipped)
+    }};
 }
 
-impl Sample<f32> for f32 {
-    const MAX_I32: f32 = 2147483648.0;
+/// The SampleWriter trait enables converting and writing many sample values from a slice.
+pub trait SampleWriter<T: Sample<T>> {
+    /// Write sample values from a slice to anything that implements the "Write" trait.
+    /// This can be for example a file, or a Vec of u8.
+    /// Input samples are f32 or f64, and are converted to the given sample format.
+    /// The sample values are clamped to the range supported by the output format.
+    /// For the float types, the input range is -1.0 to +1.0.
+    /// For the integer types, the input range doesn't include 1.0.
+    /// For example for I16 the maximum value is (2^15-1)/2^15, approximately +0.99997.
+    /// The values are rounded to the nearest value of the output format, see [Quantization::RoundHalfEven].
+    /// For the packed 12 bit formats, a single trailing sample is written as 2 bytes.
+    /// The values are converted in chunks, and each chunk is written with a single call to `write_all`.
+    /// The number of clipped samples is returned.
+    fn write_samples(
+        values: &[T],
+        target: &mut dyn Write,
+        sformat: &SampleFormat,
+    ) -> Result<usize, Error> {
+        let mut buffer = vec![0; 8 * CHUNK_SAMPLES];
+        let mut nbr_clipped = 0;
+        for chunk in values.chunks(CHUNK_SAMPLES) {
+            let (_, nbr_bytes, clipped) = Self::write_samples_to_slice(chunk, &mut buffer, sformat);
+            nbr_clipped += clipped;
+            target.write_all(&buffer[0..nbr_bytes])?;
+        }
+        Ok(nbr_clipped)
+    }
+
+    /// Write sample values from a slice like [SampleWriter::write_samples], with the conversion controlled by the given options.
+    /// With [ClipPolicy::Fail], the values before the first clipped value are written, and then [Error::Clipped] is returned.
+    /// The number of clipped samples is returned.
+    fn write_samples_with(
+        values: &[T],
+        target: &mut dyn Write,
+        sformat: &SampleFormat,
+        options: &WriteOptions,
+    ) -> Result<usize, Error>
+    where
+        T: Float,
+    {
+        let mut buffer = vec![0; 8 * CHUNK_SAMPLES];
+        let mut nbr_clipped = 0;
+        for (chunk_index, chunk) in values.chunks(CHUNK_SAMPLES).enumerate() {
+            match Self::write_samples_to_slice_with(chunk, &mut buffer, sformat, options) {
+                Ok((_, nbr_bytes, clipped)) => {
+                    nbr_clipped += clipped;
+                    target.write_all(&buffer[0..nbr_bytes])?;
+                }
+                Err(Error::Clipped(index)) => {
+                    // write the values before the clipped one
+                    let (_, nbr_bytes, _) = Self::write_samples_to_slice_with(
+                        &chunk[0..index],
+                        &mut buffer,
+                        sformat,
+                        options,
+                    )?;
+                    target.write_all(&buffer[0..nbr_bytes])?;
+                    return Err(Error::Clipped(chunk_index * CHUNK_SAMPLES + index));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(nbr_clipped)
+    }
+
+    /// Write sample values from a slice like [SampleWriter::write_samples], with dither added before the values are quantised.
+    /// The noise is scaled to the least significant bit of the output format, see the [dither] module.
+    /// The float formats and the companded A-law and mu-law formats are written without dither.
+    /// Use the same `Dither` for consecutive calls, to continue the sequence of random values.
+    /// The number of clipped samples is returned.
+    fn write_samples_dithered(
+        values: &[T],
+        target: &mut dyn Write,
+        sformat: &SampleFormat,
+        dither: &mut Dither,
+    ) -> Result<usize, Error>
+    where
+        T: Float,
+    {
+        let lsb = match sformat.lsb() {
+            Some(lsb) => lsb,
+            None => return Self::write_samples(values, target, sformat),
+        };
+        let mut buffer = vec![0; 8 * CHUNK_SAMPLES];
+        let mut dithered = Vec::with_capacity(CHUNK_SAMPLES);
+        let mut nbr_clipped = 0;
+        for chunk in values.chunks(CHUNK_SAMPLES) {
+            dithered.clear();
+            dither.add_noise(chunk, lsb, &mut dithered);
+            let (_, nbr_bytes, clipped) =
+                Self::write_samples_to_slice(&dithered, &mut buffer, sformat);
+            nbr_clipped += clipped;
+            target.write_all(&buffer[0..nbr_bytes])?;
+        }
+        Ok(nbr_clipped)
+    }
+
+    /// Convert sample values from a slice, and store the raw bytes in another slice.
+    /// This works directly on the slices, without going through the "Write" trait.
+    /// The values are converted in the same way as for [SampleWriter::write_samples].
+    /// It will convert values until either all values are converted, or the byte slice is full.
+    /// Any remaining bytes at the end of the byte slice are left untouched.
+    /// The number of samples converted, the number of bytes written, and the number of clipped samples are returned.
+    fn write_samples_to_slice(
+        values: &[T],
+        rawbytes: &mut [u8],
+        sformat: &SampleFormat,
+    ) -> (usize, usize, usize) {
+        convert_values_to_bytes(values, rawbytes, sformat)
+    }
+
+    /// Convert sample values from a slice like [SampleWriter::write_samples_to_slice],
+    /// with the conversion controlled by the given options.
+    /// With [ClipPolicy::Fail], [Error::Clipped] is returned if any value is clipped,
+    /// and the byte slice may then be partly written.
+    /// The number of samples converted, the number of bytes written, and the number of clipped samples are returned.
+    fn write_samples_to_slice_with(
+        values: &[T],
+        rawbytes: &mut [u8],
+        sformat: &SampleFormat,
+        options: &WriteOptions,
+    ) -> Result<(usize, usize, usize), Error>
+    where
+        T: Float,
+    {
+        let (nbr_converted, nbr_bytes, nbr_clipped) =
+            convert_with_options::<T, Self>(values, rawbytes, sformat, options);
+        if nbr_clipped > 0 && options.clipping == ClipPolicy::Fail {
+            // find the first clipped value by converting them one by one
+            let mut scratch = [0; 8];
+            let index = values[0..nbr_converted]
+                .iter()
+                .position(|value| {
+                    let (_, _, clipped) = convert_with_options::<T, Self>(
+                        std::slice::from_ref(value),
+                        &mut scratch,
+                        sformat,
+                        options,
+                    );
+                    clipped > 0
+                })
+                .unwrap_or(0);
+            return Err(Error::Clipped(index));
+        }
+        Ok((nbr_converted, nbr_bytes, nbr_clipped))
+    }
+
+    /// Write interleaved frames of sample values from a slice to anything that implements the "Write" trait.
+    /// The values are converted in the same way as for [SampleWriter::write_samples].
+    /// Each frame contains one sample for each of the given number of channels,
+    /// and the length of the slice must be a whole number of frames.
+    /// The number of clipped samples is returned.
+    fn write_frames(
+        values: &[T],
+        channels: usize,
+        target: &mut dyn Write,
+        sformat: &SampleFormat,
+    ) -> Result<usize, Error> {
+        let frame_bytes = sformat.frame_bytes(channels)?;
+        if values.len() % channels != 0 {
+            return Err(Error::InvalidParameter(
+                "the number of samples must be a whole number of frames",
+            ));
+        }
+        let mut buffer = vec![0; CHUNK_FRAMES * frame_bytes];
+        let mut nbr_clipped = 0;
+        for chunk in values.chunks(CHUNK_FRAMES * channels) {
+            let (_, nbr_bytes, clipped) = Self::write_samples_to_slice(chunk, &mut buffer, sformat);
+            nbr_clipped += clipped;
+            target.write_all(&buffer[0..nbr_bytes])?;
+        }
+        Ok(nbr_clipped)
+    }
+
+    /// Write interleaved frames of sample values from a slice, with the channels remapped according to a channel map.
+    /// The input frames have the given number of channels, and the output frames have one channel per entry in the map.
+    /// Each entry gives the input channel to write to that output channel,
+    /// which means that channels can be selected, reordered or duplicated.
+    /// Output channels mapped to `None` are filled with silence.
+    /// For example the map `[Some(1), Some(0), None]` swaps the first two channels, and adds a silent third channel.
+    /// The number of clipped samples is returned. A sample written to several output channels is counted once for each.
+    fn write_frames_mapped(
+        values: &[T],
+        channels: usize,
+        map: &[Option<usize>],
+        target: &mut dyn Write,
+        sformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy + Default,
+    {
+        check_channel_map(map, channels)?;
+        sformat.frame_bytes(map.len())?;
+        if values.len() % channels != 0 {
+            return Err(Error::InvalidParameter(
+                "the number of samples must be a whole number of frames",
+            ));
+        }
+        let mut mapped = Vec::with_capacity(CHUNK_FRAMES * map.len());
+        let mut nbr_clipped = 0;
+        for chunk in values.chunks(CHUNK_FRAMES * channels) {
+            mapped.clear();
+            remap_frames(chunk, channels, map, T::default(), &mut mapped);
+            nbr_clipped += Self::write_frames(&mapped, map.len(), target, sformat)?;
+        }
+        Ok(nbr_clipped)
+    }
+
+    /// Write sample values from planar buffers, one per channel, as interleaved frames
+    /// to anything that implements the "Write" trait.
+    /// The values are converted in the same way as for [SampleWriter::write_samples].
+    /// All channels must have the same number of values.
+    /// The frames are interleaved in chunks, without first interleaving all the values.
+    /// The number of clipped samples is returned, in the same way as for [SampleWriter::write_planar_raw].
+    fn write_planar<V: AsRef<[T]>>(
+        values: &[V],
+        target: &mut dyn Write,
+        sformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy,
+    {
+        let channels = values.len();
+        let frame_bytes = sformat.frame_bytes(channels)?;
+        let nbr_frames = values[0].as_ref().len();
+        if values.iter().any(|chan| chan.as_ref().len() != nbr_frames) {
+            return Err(Error::InvalidParameter(
+                "all channels must have the same number of samples",
+            ));
+        }
+        let mut interleaved = Vec::with_capacity(CHUNK_FRAMES * channels);
+        let mut buffer = vec![0; CHUNK_FRAMES * frame_bytes];
+        let mut nbr_clipped = 0;
+        for start in (0..nbr_frames).step_by(CHUNK_FRAMES) {
+            let end = nbr_frames.min(start + CHUNK_FRAMES);
+            interleaved.clear();
+            for frame in start..end {
+                interleaved.extend(values.iter().map(|chan| chan.as_ref()[frame]));
+            }
+            let (_, nbr_bytes, clipped) =
+                Self::write_samples_to_slice(&interleaved, &mut buffer, sformat);
+            target.write_all(&buffer[0..nbr_bytes])?;
+            nbr_clipped += clipped;
+        }
+        Ok(nbr_clipped)
+    }
+
+    /// Write sample values from planar buffers, one per channel, to a planar raw layout
+    /// where each channel is written as a contiguous block of bytes to a separate target.
+    /// The values are converted in the same way as for [SampleWriter::write_samples].
+    /// The number of targets must match the number of channels, and all channels must have the same number of values.
+    /// The number of clipped samples is returned, counted over all channels.
+    fn write_planar_raw<V: AsRef<[T]>>(
+        values: &[V],
+        targets: &mut [&mut dyn Write],
+        sformat: &SampleFormat,
+    ) -> Result<usize, Error> {
+        if values.len() != targets.len() {
+            return Err(Error::InvalidParameter(
+                "the number of targets must match the number of channels",
+            ));
+        }
+        let nbr_frames = values.first().map(|chan| chan.as_ref().len()).unwrap_or(0);
+        if values.iter().any(|chan| chan.as_ref().len() != nbr_frames) {
+            return Err(Error::InvalidParameter(
+                "all channels must have the same number of samples",
+            ));
+        }
+        let mut nbr_clipped = 0;
+        for (chan, target) in values.iter().zip(targets.iter_mut()) {
+            nbr_clipped += Self::write_samples(chan.as_ref(), *target, sformat)?;
+        }
+        Ok(nbr_clipped)
+    }
+}
+
+impl SampleWriter<f64> for f64 {
+    fn write_samples_to_slice(
+        values: &[f64],
+        rawbytes: &mut [u8],
+        sformat: &SampleFormat,
+    ) -> (usize, usize, usize) {
+        convert_values_to_bytes_vectorised(values, rawbytes, sformat, simd::f64_to_bytes)
+    }
+}
+
+impl SampleWriter<f32> for f32 {
+    fn write_samples_to_slice(
+        values: &[f32],
+        rawbytes: &mut [u8],
+        sformat: &SampleFormat,
+    ) -> (usize, usize, usize) {
+        convert_values_to_bytes_vectorised(values, rawbytes, sformat, simd::f32_to_bytes)
+    }
+}
+
+/// Convert sample values from a slice to raw bytes one by one, for [SampleWriter::write_samples_to_slice].
+fn convert_values_to_bytes<T: Sample<T>>(
+    values: &[T],
+    rawbytes: &mut [u8],
+    sformat: &SampleFormat,
+) -> (usize, usize, usize) {
+    match sformat {
+        SampleFormat::S8 => {
+            convert_samples_to_bytes!(values, rawbytes, to_s8, 1)
+        }
+        SampleFormat::S12PackedLE => {
+            convert_sample_pairs_to_bytes!(values, rawbytes, to_s12_packed_le)
+        }
+        SampleFormat::S12PackedBE => {
+            convert_sample_pairs_to_bytes!(values, rawbytes, to_s12_packed_be)
+        }
+        SampleFormat::S16LE => {
+            convert_samples_to_bytes!(values, rawbytes, to_s16_le, 2)
+        }
+        SampleFormat::S16BE => {
+            convert_samples_to_bytes!(values, rawbytes, to_s16_be, 2)
+        }
+        SampleFormat::S24LE3 => {
+            convert_samples_to_bytes!(values, rawbytes, to_s24_3_le, 3)
+        }
+        SampleFormat::S24BE3 => {
+            convert_samples_to_bytes!(values, rawbytes, to_s24_3_be, 3)
+        }
+        SampleFormat::S24LE4 => {
+            convert_samples_to_bytes!(values, rawbytes, to_s24_4_le, 4)
+        }
+        SampleFormat::S24BE4 => {
+            convert_samples_to_bytes!(values, rawbytes, to_s24_4_be, 4)
+        }
+        SampleFormat::S24LE4Msb => {
+            convert_samples_to_bytes!(values, rawbytes, to_s24_4_msb_le, 4)
+        }
+        SampleFormat::S24BE4Msb => {
+            convert_samples_to_bytes!(values, rawbytes, to_s24_4_msb_be, 4)
+        }
+        SampleFormat::S20LE3 => {
+            convert_samples_to_bytes!(values, rawbytes, to_s20_3_le, 3)
+        }
+        SampleFormat::S20BE3 => {
+            convert_samples_to_bytes!(values, rawbytes, to_s20_3_be, 3)
+        }
+        SampleFormat::S18LE3 => {
+            convert_samples_to_bytes!(values, rawbytes, to_s18_3_le, 3)
+        }
+        SampleFormat::S18BE3 => {
+            convert_samples_to_bytes!(values, rawbytes, to_s18_3_be, 3)
+        }
+        SampleFormat::S32LE => {
+            convert_samples_to_bytes!(values, rawbytes, to_s32_le, 4)
+        }
+        SampleFormat::S32BE => {
+            convert_samples_to_bytes!(values, rawbytes, to_s32_be, 4)
+        }
+        SampleFormat::S64LE => {
+            convert_samples_to_bytes!(values, rawbytes, to_s64_le, 8)
+        }
+        SampleFormat::S64BE => {
+            convert_samples_to_bytes!(values, rawbytes, to_s64_be, 8)
+        }
+        SampleFormat::U8 => {
+            convert_samples_to_bytes!(values, rawbytes, to_u8, 1)
+        }
+        SampleFormat::U16LE => {
+            convert_samples_to_bytes!(values, rawbytes, to_u16_le, 2)
+        }
+        SampleFormat::U16BE => {
+            convert_samples_to_bytes!(values, rawbytes, to_u16_be, 2)
+        }
+        SampleFormat::U24LE3 => {
+            convert_samples_to_bytes!(values, rawbytes, to_u24_3_le, 3)
+        }
+        SampleFormat::U24BE3 => {
+            convert_samples_to_bytes!(values, rawbytes, to_u24_3_be, 3)
+        }
+        SampleFormat::U32LE => {
+            convert_samples_to_bytes!(values, rawbytes, to_u32_le, 4)
+        }
+        SampleFormat::U32BE => {
+            convert_samples_to_bytes!(values, rawbytes, to_u32_be, 4)
+        }
+        SampleFormat::ALaw => {
+            convert_samples_to_bytes!(values, rawbytes, to_alaw, 1)
+        }
+        SampleFormat::MuLaw => {
+            convert_samples_to_bytes!(values, rawbytes, to_mulaw, 1)
+        }
+        SampleFormat::FixedPoint(format) => {
+            convert_samples_to_bytes!(values, rawbytes, to_fixed, format, format.bytes)
+        }
+        SampleFormat::F32LE => {
+            convert_samples_to_bytes!(values, rawbytes, to_f32_le, 4)
+        }
+        SampleFormat::F32BE => {
+            convert_samples_to_bytes!(values, rawbytes, to_f32_be, 4)
+        }
+        SampleFormat::F64LE => {
+            convert_samples_to_bytes!(values, rawbytes, to_f64_le, 8)
+        }
+        SampleFormat::F64BE => {
+            convert_samples_to_bytes!(values, rawbytes, to_f64_be, 8)
+        }
+        SampleFormat::F16LE => {
+            convert_samples_to_bytes!(values, rawbytes, to_f16_le, 2)
+        }
+        SampleFormat::F16BE => {
+            convert_samples_to_bytes!(values, rawbytes, to_f16_be, 2)
+        }
+        SampleFormat::BF16LE => {
+            convert_samples_to_bytes!(values, rawbytes, to_bf16_le, 2)
+        }
+        SampleFormat::BF16BE => {
+            convert_samples_to_bytes!(values, rawbytes, to_bf16_be, 2)
+        }
+    }
+}
+
+/// Convert sample values from a slice to raw bytes, with whole blocks converted by a vectorised kernel
+/// and the rest one by one.
+fn convert_values_to_bytes_vectorised<T: Sample<T>>(
+    values: &[T],
+    rawbytes: &mut [u8],
+    sformat: &SampleFormat,
+    kernel: simd::WriteKernel<T>,
+) -> (usize, usize, usize) {
+    let (nbr_vectorised, nbr_clipped_vectorised) = kernel(values, rawbytes, sformat);
+    let nbr_bytes_vectorised = sformat.bytes_for_samples(nbr_vectorised);
+    let (nbr_converted, nbr_bytes, nbr_clipped) = convert_values_to_bytes(
+        &values[nbr_vectorised..],
+        &mut rawbytes[nbr_bytes_vectorised..],
+        sformat,
+    );
+    (
+        nbr_vectorised + nbr_converted,
+        nbr_bytes_vectorised + nbr_bytes,
+        nbr_clipped_vectorised + nbr_clipped,
+    )
+}
+
+macro_rules! convert_bytes_to_samples {
+    ($rawbytes:expr, $values:expr, $conv:ident, $n:expr) => {{
+        let mut nbr_converted = 0;
+        for (value, chunk) in $values.iter_mut().zip($rawbytes.chunks_exact($n)) {
+            *value = T::$conv(chunk.try_into().unwrap());
+            nbr_converted += 1;
+        }
+        (nbr_converted, &$rawbytes[nbr_converted * $n..])
+    }};
+    ($rawbytes:expr, $values:expr, $conv:ident, $format:expr, $n:expr) => {{
+        let mut nbr_converted = 0;
+        for (value, chunk) in $values.iter_mut().zip($rawbytes.chunks_exact($n)) {
+            let mut bytes = [0; 8];
+            bytes[..$n].copy_from_slice(chunk);
+            *value = T::$conv(bytes, $format);
+            nbr_converted += 1;
+        }
+        (nbr_converted, &$rawbytes[nbr_converted * $n..])
+    }};
+}
+
+macro_rules! convert_bytes_to_sample_pairs {
+    ($rawbytes:expr, $values:expr, $conv:ident) => {{
+        let mut nbr_converted = 0;
+        let mut nbr_bytes = 0;
+        for pair in $values.chunks_exact_mut(2) {
+            let remaining = $rawbytes.len() - nbr_bytes;
+            if remaining >= 3 {
+                let bytes = $rawbytes[nbr_bytes..nbr_bytes + 3].try_into().unwrap();
+                let [first, second] = T::$conv(bytes);
+                pair[0] = first;
+                pair[1] = second;
+                nbr_converted += 2;
+                nbr_bytes += 3;
+            } else {
+                if remaining == 2 {
+                    let bytes = [$rawbytes[nbr_bytes], $rawbytes[nbr_bytes + 1], 0];
+                    let [first, _] = T::$conv(bytes);
+                    pair[0] = first;
+                    nbr_converted += 1;
+                    nbr_bytes += 2;
+                }
+                break;
+            }
+        }
+        (nbr_converted, &$rawbytes[nbr_bytes..])
+    }};
+}
+
+/// The number of frames converted at a time when reading or writing in chunks.
+const CHUNK_FRAMES: usize = 1024;
+
+/// The number of samples converted at a time when reading or writing in chunks.
+/// This must be even, to keep the pairs of the packed formats together.
+const CHUNK_SAMPLES: usize = 1024;
+
+/// Check that a channel map only refers to channels that exist.
+fn check_channel_map(map: &[Option<usize>], channels: usize) -> Result<(), Error> {
+    if map.is_empty() {
+        return Err(Error::InvalidParameter(
+            "the channel map must contain at least one channel",
+        ));
+    }
+    if map.iter().flatten().any(|source| *source >= channels) {
+        return Err(Error::InvalidParameter(
+            "the channel map refers to a channel that does not exist",
+        ));
+    }
+    Ok(())
+}
+
+/// Remap interleaved frames with the given number of channels, and append the new frames to a vec.
+fn remap_frames<T: Copy>(
+    input: &[T],
+    channels: usize,
+    map: &[Option<usize>],
+    silence: T,
+    output: &mut Vec<T>,
+) {
+    for frame in input.chunks(channels) {
+        output.extend(
+            map.iter()
+                .map(|source| source.map_or(silence, |chan| frame[chan])),
+        );
+    }
+}
+
+/// Read from a source until the buffer is full or the source reaches end-of-file.
+/// The number of bytes read is returned.
+fn read_fully(source: &mut dyn Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut nbr_read = 0;
+    while nbr_read < buffer.len() {
+        match source.read(&mut buffer[nbr_read..]) {
+            Ok(0) => break,
+            Ok(n) => nbr_read += n,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(nbr_read)
+}
+
+/// Read whole frames to a buffer of raw bytes, and convert them to interleaved samples in a slice,
+/// using the slice conversion of a SampleReader.
+/// The buffer is filled unless the source reaches end-of-file.
+/// The number of whole frames read, and the number of bytes of an incomplete frame at the end of the source, are returned.
+fn read_frame_chunk<T: Sample<T>, R: SampleReader<T> + ?Sized>(
+    rawbytes: &mut dyn Read,
+    buffer: &mut [u8],
+    samples: &mut [T],
+    frame_bytes: usize,
+    sampleformat: &SampleFormat,
+) -> Result<(usize, usize), Error> {
+    let nbr_bytes = read_fully(rawbytes, buffer)?;
+    let nbr_frames = nbr_bytes / frame_bytes;
+    R::read_samples_from_slice(&buffer[0..nbr_frames * frame_bytes], samples, sampleformat);
+    Ok((nbr_frames, nbr_bytes % frame_bytes))
+}
+
+/// Read samples in chunks and store them in a slice, using the slice conversion of a SampleReader.
+/// The bytes of an incomplete sample at the end of the source are stored in `leftover`.
+/// With `whole_pairs`, the packed formats are only converted in whole pairs,
+/// and the bytes of an incomplete pair are also stored in `leftover`.
+/// The number of samples read is returned.
+fn read_chunks_to_slice<T: Sample<T>, R: SampleReader<T> + ?Sized>(
+    rawbytes: &mut dyn Read,
+    samples: &mut [T],
+    sampleformat: &SampleFormat,
+    leftover: &mut Vec<u8>,
+    whole_pairs: bool,
+) -> Result<usize, Error> {
+    leftover.clear();
+    let mut buffer = vec![0; 8 * CHUNK_SAMPLES];
+    // the packed formats are read in pairs
+    let max_samples = sampleformat.whole_samples(samples.len());
+    let mut nbr_read = 0;
+    while nbr_read < max_samples {
+        let chunk_samples = CHUNK_SAMPLES.min(max_samples - nbr_read);
+        let chunk_bytes = sampleformat.bytes_for_samples(chunk_samples);
+        let nbr_bytes = read_fully(rawbytes, &mut buffer[0..chunk_bytes])?;
+        let nbr_used = if whole_pairs {
+            sampleformat.whole_pair_bytes(nbr_bytes)
+        } else {
+            nbr_bytes
+        };
+        let (nbr_converted, remaining) = R::read_samples_from_slice(
+            &buffer[0..nbr_used],
+            &mut samples[nbr_read..nbr_read + chunk_samples],
+            sampleformat,
+        );
+        nbr_read += nbr_converted;
+        if nbr_bytes < chunk_bytes {
+            leftover.extend_from_slice(remaining);
+            leftover.extend_from_slice(&buffer[nbr_used..nbr_bytes]);
+            break;
+        }
+    }
+    Ok(nbr_read)
+}
+
+/// Read all samples in chunks and append them to a vec, using the slice conversion of a SampleReader.
+/// The bytes of an incomplete sample at the end of the source are stored in `leftover`.
+/// With `whole_pairs`, the packed formats are only converted in whole pairs,
+/// and the bytes of an incomplete pair are also stored in `leftover`.
+/// The number of samples read is returned.
+fn read_all_chunks_to_vec<T: Sample<T> + Copy + Default, R: SampleReader<T> + ?Sized>(
+    rawbytes: &mut dyn Read,
+    samples: &mut Vec<T>,
+    sampleformat: &SampleFormat,
+    leftover: &mut Vec<u8>,
+    whole_pairs: bool,
+) -> Result<usize, Error> {
+    leftover.clear();
+    let start_len = samples.len();
+    let chunk_bytes = sampleformat.bytes_for_samples(CHUNK_SAMPLES);
+    let mut buffer = vec![0; chunk_bytes];
+    loop {
+        let nbr_bytes = read_fully(rawbytes, &mut buffer)?;
+        let nbr_used = if whole_pairs {
+            sampleformat.whole_pair_bytes(nbr_bytes)
+        } else {
+            nbr_bytes
+        };
+        // extend the vec with zeros, and then shorten it to the number of converted samples
+        let offset = samples.len();
+        samples.resize(offset + CHUNK_SAMPLES, T::default());
+        let (nbr_converted, remaining) =
+            R::read_samples_from_slice(&buffer[0..nbr_used], &mut samples[offset..], sampleformat);
+        samples.truncate(offset + nbr_converted);
+        if nbr_bytes < chunk_bytes {
+            leftover.extend_from_slice(remaining);
+            leftover.extend_from_slice(&buffer[nbr_used..nbr_bytes]);
+            break;
+        }
+    }
+    Ok(samples.len() - start_len)
+}
+
+/// Convert sample values to raw bytes with the given options, using the slice conversion of a SampleWriter.
+/// With [ClipPolicy::Fail] the values are clamped, and it is up to the caller to check the number of clipped values.
+/// The number of samples converted, the number of bytes written, and the number of clipped samples are returned.
+fn convert_with_options<T: Sample<T> + Float, W: SampleWriter<T> + ?Sized>(
+    values: &[T],
+    rawbytes: &mut [u8],
+    sformat: &SampleFormat,
+    options: &WriteOptions,
+) -> (usize, usize, usize) {
+    let lsb = match sformat.lsb() {
+        Some(lsb) => lsb,
+        None if options.clipping == ClipPolicy::PassThrough => {
+            return write_floats_unclamped::<T, W>(values, rawbytes, sformat);
+        }
+        None => return W::write_samples_to_slice(values, rawbytes, sformat),
+    };
+    if options.quantization == Quantization::RoundHalfEven
+        && options.scaling == Scaling::PowerOfTwo
+        && options.clipping != ClipPolicy::Wrap
+    {
+        return W::write_samples_to_slice(values, rawbytes, sformat);
+    }
+    // quantise the values as f64, on the grid of the default scaling,
+    // where the conversion then keeps them exactly
+    let full_scale = sformat.full_scale();
+    let (min, max) = sformat.int_range().unwrap_or((f64::MIN, f64::MAX));
+    let nbr_values = values
+        .len()
+        .min(8 * rawbytes.len() / sformat.storage_bits());
+    let mut nbr_wrapped = 0;
+    let quantised: Vec<f64> = values[0..nbr_values]
+        .iter()
+        .map(|value| {
+            let value = value.to_f64().unwrap_or(0.0);
+            let scale = match full_scale {
+                Some(full_scale) => options.scaling.scale(value, full_scale),
+                None => 1.0 / lsb,
+            };
+            let mut intvalue = options.quantization.quantise(value * scale, 1.0);
+            // the largest value of the 64 bit formats rounds up to 2^63, compare with max + 1 to wrap that too
+            if options.clipping == ClipPolicy::Wrap && (intvalue < min || intvalue >= max + 1.0) {
+                intvalue = (intvalue - min).rem_euclid(max - min + 1.0) + min;
+                nbr_wrapped += 1;
+            }
+            intvalue * lsb
+        })
+        .collect();
+    let (nbr_converted, nbr_bytes, nbr_clipped) =
+        f64::write_samples_to_slice(&quantised, rawbytes, sformat);
+    (nbr_converted, nbr_bytes, nbr_clipped + nbr_wrapped)
+}
+
+/// Convert sample values to the float formats without clamping them to the -1.0 to +1.0 range.
+/// The other formats are converted as usual by the SampleWriter.
+fn write_floats_unclamped<T: Sample<T> + Float, W: SampleWriter<T> + ?Sized>(
+    values: &[T],
+    rawbytes: &mut [u8],
+    sformat: &SampleFormat,
+) -> (usize, usize, usize) {
+    let (nbr_bytes, encode): (usize, fn(f64, &mut [u8])) = match sformat {
+        SampleFormat::F32LE => (4, |val, bytes| {
+            bytes.copy_from_slice(&(val as f32).to_le_bytes())
+        }),
+        SampleFormat::F32BE => (4, |val, bytes| {
+            bytes.copy_from_slice(&(val as f32).to_be_bytes())
+        }),
+        SampleFormat::F64LE => (8, |val, bytes| bytes.copy_from_slice(&val.to_le_bytes())),
+        SampleFormat::F64BE => (8, |val, bytes| bytes.copy_from_slice(&val.to_be_bytes())),
+        SampleFormat::F16LE => (2, |val, bytes| {
+            bytes.copy_from_slice(&f32_to_f16_bits(val as f32).to_le_bytes())
+        }),
+        SampleFormat::F16BE => (2, |val, bytes| {
+            bytes.copy_from_slice(&f32_to_f16_bits(val as f32).to_be_bytes())
+        }),
+        SampleFormat::BF16LE => (2, |val, bytes| {
+            bytes.copy_from_slice(&f32_to_bf16_bits(val as f32).to_le_bytes())
+        }),
+        SampleFormat::BF16BE => (2, |val, bytes| {
+            bytes.copy_from_slice(&f32_to_bf16_bits(val as f32).to_be_bytes())
+        }),
+        _ => return W::write_samples_to_slice(values, rawbytes, sformat),
+    };
+    let mut nbr_converted = 0;
+    for (value, chunk) in values.iter().zip(rawbytes.chunks_exact_mut(nbr_bytes)) {
+        encode(value.to_f64().unwrap_or(0.0), chunk);
+        nbr_converted += 1;
+    }
+    (nbr_converted, nbr_converted * nbr_bytes, 0)
+}
+
+/// The SampleReader trait enables reading and converting raw bytes and to multiple samples.
+pub trait SampleReader<T: Sample<T>> {
+    /// Read bytes from anything that implements the "Read" trait.
+    /// This can be for example a file, or a slice of u8.
+    /// The bytes are then converted to f32 or f64 values, and stored in a slice.
+    /// It will read until the samples slice is filled.
+    /// If end-of-file of the source is reached before the slice is filled, the remaining values of the slice are left untouched.
+    /// The packed 12 bit formats are read in pairs, and if the slice has an odd length the last value is left untouched.
+    /// A single trailing sample at the end of the source is included.
+    /// The bytes are read in chunks, and no more bytes than needed to fill the slice are read from the source.
+    /// If the source ends in the middle of a sample, [Error::TruncatedSample] is returned
+    /// with the number of samples read and the number of leftover bytes.
+    /// The complete samples before that are still stored in the slice.
+    /// Use [SampleReader::read_samples_with_leftover] to get the leftover bytes instead of an error.
+    /// The number of samples read is returned.
+    fn read_samples(
+        rawbytes: &mut dyn Read,
+        samples: &mut [T],
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error> {
+        let mut leftover = Vec::new();
+        let nbr_read =
+            read_chunks_to_slice::<T, Self>(rawbytes, samples, sampleformat, &mut leftover, false)?;
+        if !leftover.is_empty() {
+            return Err(Error::TruncatedSample {
+                samples: nbr_read,
+                bytes: leftover.len(),
+            });
+        }
+        Ok(nbr_read)
+    }
+
+    /// Read bytes from anything that implements the "Read" trait, and store the samples in a slice,
+    /// while keeping the bytes of an incomplete sample at the end of the source.
+    /// Any bytes already in `leftover`, for example kept by a previous call, are used before the bytes from the source.
+    /// On return, `leftover` holds the bytes of an incomplete sample at the end of the source, or is empty.
+    /// The packed 12 bit formats are only converted in whole pairs, and the bytes of an incomplete pair are kept in `leftover`.
+    /// This allows a stream to be read in pieces that don't end on sample boundaries.
+    /// Otherwise it works like [SampleReader::read_samples].
+    /// The number of samples read is returned.
+    fn read_samples_with_leftover(
+        rawbytes: &mut dyn Read,
+        samples: &mut [T],
+        leftover: &mut Vec<u8>,
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error> {
+        let previous = std::mem::take(leftover);
+        let mut source = previous.as_slice().chain(rawbytes);
+        let result =
+            read_chunks_to_slice::<T, Self>(&mut source, samples, sampleformat, leftover, true);
+        // the slice may be filled before all the previous bytes are used, keep the rest of them
+        let (unused, _) = source.into_inner();
+        leftover.splice(0..0, unused.iter().copied());
+        result
+    }
+
+    /// Read all bytes from anything that implements the "Read" trait.
+    /// This can be for example a file, or a slice of u8.
+    /// The bytes are then converted to f32 or f64 values, and appended to a vec.
+    /// It will continue reading until reaching end-of-file of the source.
+    /// The bytes are read in chunks.
+    /// If the source ends in the middle of a sample, [Error::TruncatedSample] is returned
+    /// with the number of samples read and the number of leftover bytes.
+    /// The complete samples before that are still appended to the vec.
+    /// Use [SampleReader::read_all_samples_with_leftover] to get the leftover bytes instead of an error.
+    /// The number of samples read is returned.
+    fn read_all_samples(
+        rawbytes: &mut dyn Read,
+        samples: &mut Vec<T>,
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy + Default,
+    {
+        let mut leftover = Vec::new();
+        let nbr_read = read_all_chunks_to_vec::<T, Self>(
+            rawbytes,
+            samples,
+            sampleformat,
+            &mut leftover,
+            false,
+        )?;
+        if !leftover.is_empty() {
+            return Err(Error::TruncatedSample {
+                samples: nbr_read,
+                bytes: leftover.len(),
+            });
+        }
+        Ok(nbr_read)
+    }
+
+    /// Read all bytes from anything that implements the "Read" trait, and append the samples to a vec,
+    /// while keeping the bytes of an incomplete sample at the end of the source.
+    /// Any bytes already in `leftover`, for example kept by a previous call, are used before the bytes from the source.
+    /// On return, `leftover` holds the bytes of an incomplete sample at the end of the source, or is empty.
+    /// The packed 12 bit formats are only converted in whole pairs, and the bytes of an incomplete pair are kept in `leftover`.
+    /// Otherwise it works like [SampleReader::read_all_samples].
+    /// The number of samples read is returned.
+    fn read_all_samples_with_leftover(
+        rawbytes: &mut dyn Read,
+        samples: &mut Vec<T>,
+        leftover: &mut Vec<u8>,
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy + Default,
+    {
+        let previous = std::mem::take(leftover);
+        let mut source = previous.as_slice().chain(rawbytes);
+        read_all_chunks_to_vec::<T, Self>(&mut source, samples, sampleformat, leftover, true)
+    }
+
+    /// Convert raw bytes from a slice, and store the sample values in another slice.
+    /// This works directly on the slices, without going through the "Read" trait.
+    /// It will convert samples until either the sample slice is filled, or there are no more whole samples in the byte slice.
+    /// Any remaining values at the end of the sample slice are left untouched.
+    /// For the packed 12 bit formats, the samples are converted in pairs in the same way as for [SampleReader::read_samples],
+    /// and 2 remaining bytes at the end of the byte slice are converted as a single trailing sample.
+    /// The number of samples converted is returned, together with the remaining bytes that were not converted.
+    fn read_samples_from_slice<'a>(
+        rawbytes: &'a [u8],
+        samples: &mut [T],
+        sampleformat: &SampleFormat,
+    ) -> (usize, &'a [u8]) {
+        convert_bytes_to_values(rawbytes, samples, sampleformat)
+    }
+
+    /// Read bytes from anything that implements the "Read" trait, and store the samples in a slice,
+    /// like [SampleReader::read_samples], with the conversion controlled by the given options.
+    /// The number of samples read is returned.
+    fn read_samples_with(
+        rawbytes: &mut dyn Read,
+        samples: &mut [T],
+        sampleformat: &SampleFormat,
+        options: &ReadOptions,
+    ) -> Result<usize, Error>
+    where
+        T: Float,
+    {
+        let mut leftover = Vec::new();
+        let nbr_read =
+            read_chunks_to_slice::<T, Self>(rawbytes, samples, sampleformat, &mut leftover, false)?;
+        options
+            .scaling
+            .rescale(&mut samples[0..nbr_read], sampleformat);
+        if !leftover.is_empty() {
+            return Err(Error::TruncatedSample {
+                samples: nbr_read,
+                bytes: leftover.len(),
+            });
+        }
+        Ok(nbr_read)
+    }
+
+    /// Read all bytes from anything that implements the "Read" trait, and append the samples to a vec,
+    /// like [SampleReader::read_all_samples], with the conversion controlled by the given options.
+    /// The number of samples read is returned.
+    fn read_all_samples_with(
+        rawbytes: &mut dyn Read,
+        samples: &mut Vec<T>,
+        sampleformat: &SampleFormat,
+        options: &ReadOptions,
+    ) -> Result<usize, Error>
+    where
+        T: Float + Default,
+    {
+        let start_len = samples.len();
+        let mut leftover = Vec::new();
+        let nbr_read = read_all_chunks_to_vec::<T, Self>(
+            rawbytes,
+            samples,
+            sampleformat,
+            &mut leftover,
+            false,
+        )?;
+        options
+            .scaling
+            .rescale(&mut samples[start_len..], sampleformat);
+        if !leftover.is_empty() {
+            return Err(Error::TruncatedSample {
+                samples: nbr_read,
+                bytes: leftover.len(),
+            });
+        }
+        Ok(nbr_read)
+    }
+
+    /// Convert raw bytes from a slice, and store the sample values in another slice,
+    /// like [SampleReader::read_samples_from_slice], with the conversion controlled by the given options.
+    /// The number of samples converted is returned, together with the remaining bytes that were not converted.
+    fn read_samples_from_slice_with<'a>(
+        rawbytes: &'a [u8],
+        samples: &mut [T],
+        sampleformat: &SampleFormat,
+        options: &ReadOptions,
+    ) -> (usize, &'a [u8])
+    where
+        T: Float,
+    {
+        let (nbr_converted, remaining) =
+            Self::read_samples_from_slice(rawbytes, samples, sampleformat);
+        options
+            .scaling
+            .rescale(&mut samples[0..nbr_converted], sampleformat);
+        (nbr_converted, remaining)
+    }
+
+    /// Read interleaved frames from anything that implements the "Read" trait, and store the samples in a slice.
+    /// Each frame contains one sample for each of the given number of channels.
+    /// It will read as many whole frames as fit in the slice,
+    /// and any remaining values at the end of the slice are left untouched.
+    /// If end-of-file of the source is reached in the middle of a frame, [Error::TruncatedFrame] is returned.
+    /// The whole frames before that are still stored in the slice, and their number is included in the error.
+    /// The number of frames read is returned.
+    fn read_frames(
+        rawbytes: &mut dyn Read,
+        samples: &mut [T],
+        channels: usize,
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error> {
+        let frame_bytes = sampleformat.frame_bytes(channels)?;
+        let max_frames = samples.len() / channels;
+        let mut buffer = vec![0; CHUNK_FRAMES.min(max_frames) * frame_bytes];
+        let mut nbr_frames = 0;
+        while nbr_frames < max_frames {
+            let chunk_frames = CHUNK_FRAMES.min(max_frames - nbr_frames);
+            let nbr_bytes = read_fully(rawbytes, &mut buffer[0..chunk_frames * frame_bytes])?;
+            let nbr_read = nbr_bytes / frame_bytes;
+            Self::read_samples_from_slice(
+                &buffer[0..nbr_read * frame_bytes],
+                &mut samples[nbr_frames * channels..(nbr_frames + nbr_read) * channels],
+                sampleformat,
+            );
+            nbr_frames += nbr_read;
+            if nbr_bytes % frame_bytes != 0 {
+                return Err(Error::TruncatedFrame {
+                    frames: nbr_frames,
+                    bytes: nbr_bytes % frame_bytes,
+                });
+            }
+            if nbr_read < chunk_frames {
+                break;
+            }
+        }
+        Ok(nbr_frames)
+    }
+
+    /// Read all interleaved frames from anything that implements the "Read" trait, and append the samples to a vec.
+    /// Each frame contains one sample for each of the given number of channels.
+    /// If end-of-file of the source is reached in the middle of a frame, [Error::TruncatedFrame] is returned.
+    /// The whole frames before that are still appended to the vec, and their number is included in the error.
+    /// The number of frames read is returned.
+    fn read_all_frames(
+        rawbytes: &mut dyn Read,
+        samples: &mut Vec<T>,
+        channels: usize,
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy + Default,
+    {
+        let frame_bytes = sampleformat.frame_bytes(channels)?;
+        let mut buffer = vec![0; CHUNK_FRAMES * frame_bytes];
+        let mut nbr_frames = 0;
+        loop {
+            // extend the vec with zeros, and then shorten it to the number of converted frames
+            let offset = samples.len();
+            samples.resize(offset + CHUNK_FRAMES * channels, T::default());
+            let (nbr_read, partial_bytes) = read_frame_chunk::<T, Self>(
+                rawbytes,
+                &mut buffer,
+                &mut samples[offset..],
+                frame_bytes,
+                sampleformat,
+            )?;
+            samples.truncate(offset + nbr_read * channels);
+            nbr_frames += nbr_read;
+            if partial_bytes > 0 {
+                return Err(Error::TruncatedFrame {
+                    frames: nbr_frames,
+                    bytes: partial_bytes,
+                });
+            }
+            if nbr_read < CHUNK_FRAMES {
+                break;
+            }
+        }
+        Ok(nbr_frames)
+    }
+
+    /// Read interleaved frames from anything that implements the "Read" trait,
+    /// and store them in a slice with the channels remapped according to a channel map.
+    /// The raw frames have the given number of channels, and the output frames have one channel per entry in the map.
+    /// Each entry gives the raw channel to store in that output channel,
+    /// which means that channels can be selected, reordered or duplicated.
+    /// Output channels mapped to `None` are filled with silence.
+    /// For example the map `[Some(2), Some(5)]` keeps only the third and sixth channels.
+    /// It will read as many whole frames as fit in the slice,
+    /// and any remaining values at the end of the slice are left untouched.
+    /// If end-of-file of the source is reached in the middle of a frame, [Error::TruncatedFrame] is returned,
+    /// after storing the whole frames before that.
+    /// The number of frames read is returned.
+    fn read_frames_mapped(
+        rawbytes: &mut dyn Read,
+        samples: &mut [T],
+        channels: usize,
+        map: &[Option<usize>],
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy + Default,
+    {
+        check_channel_map(map, channels)?;
+        let frame_bytes = sampleformat.frame_bytes(channels)?;
+        let max_frames = samples.len() / map.len();
+        let mut buffer = vec![0; CHUNK_FRAMES * frame_bytes];
+        let mut raw_frames = vec![T::default(); CHUNK_FRAMES * channels];
+        let mut mapped = Vec::with_capacity(CHUNK_FRAMES * map.len());
+        let mut nbr_frames = 0;
+        while nbr_frames < max_frames {
+            let chunk_frames = CHUNK_FRAMES.min(max_frames - nbr_frames);
+            let (nbr_read, partial_bytes) = read_frame_chunk::<T, Self>(
+                rawbytes,
+                &mut buffer[0..chunk_frames * frame_bytes],
+                &mut raw_frames,
+                frame_bytes,
+                sampleformat,
+            )?;
+            mapped.clear();
+            remap_frames(
+                &raw_frames[0..nbr_read * channels],
+                channels,
+                map,
+                T::default(),
+                &mut mapped,
+            );
+            let start = nbr_frames * map.len();
+            samples[start..start + mapped.len()].copy_from_slice(&mapped);
+            nbr_frames += nbr_read;
+            if partial_bytes > 0 {
+                return Err(Error::TruncatedFrame {
+                    frames: nbr_frames,
+                    bytes: partial_bytes,
+                });
+            }
+            if nbr_read < chunk_frames {
+                break;
+            }
+        }
+        Ok(nbr_frames)
+    }
+
+    /// Read all interleaved frames from anything that implements the "Read" trait,
+    /// and append them to a vec with the channels remapped according to a channel map.
+    /// The channel map works in the same way as for [SampleReader::read_frames_mapped].
+    /// If end-of-file of the source is reached in the middle of a frame, [Error::TruncatedFrame] is returned,
+    /// after appending the whole frames before that.
+    /// The number of frames read is returned.
+    fn read_all_frames_mapped(
+        rawbytes: &mut dyn Read,
+        samples: &mut Vec<T>,
+        channels: usize,
+        map: &[Option<usize>],
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy + Default,
+    {
+        check_channel_map(map, channels)?;
+        let frame_bytes = sampleformat.frame_bytes(channels)?;
+        let mut buffer = vec![0; CHUNK_FRAMES * frame_bytes];
+        let mut raw_frames = vec![T::default(); CHUNK_FRAMES * channels];
+        let mut nbr_frames = 0;
+        loop {
+            let (nbr_read, partial_bytes) = read_frame_chunk::<T, Self>(
+                rawbytes,
+                &mut buffer,
+                &mut raw_frames,
+                frame_bytes,
+                sampleformat,
+            )?;
+            remap_frames(
+                &raw_frames[0..nbr_read * channels],
+                channels,
+                map,
+                T::default(),
+                samples,
+            );
+            nbr_frames += nbr_read;
+            if partial_bytes > 0 {
+                return Err(Error::TruncatedFrame {
+                    frames: nbr_frames,
+                    bytes: partial_bytes,
+                });
+            }
+            if nbr_read < CHUNK_FRAMES {
+                break;
+            }
+        }
+        Ok(nbr_frames)
+    }
+
+    /// Read interleaved frames from anything that implements the "Read" trait,
+    /// and store the samples in planar buffers, one per channel.
+    /// The number of channels is given by the number of buffers.
+    /// It will read until the shortest buffer is filled,
+    /// and any remaining values at the end of the buffers are left untouched.
+    /// The frames are deinterleaved in chunks, without first reading all the values to an interleaved buffer.
+    /// If end-of-file of the source is reached in the middle of a frame, [Error::TruncatedFrame] is returned,
+    /// after storing the whole frames before that.
+    /// The number of frames read is returned.
+    fn read_planar<V: AsMut<[T]>>(
+        rawbytes: &mut dyn Read,
+        samples: &mut [V],
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy + Default,
+    {
+        let channels = samples.len();
+        let frame_bytes = sampleformat.frame_bytes(channels)?;
+        let max_frames = samples
+            .iter_mut()
+            .map(|chan| chan.as_mut().len())
+            .min()
+            .unwrap_or(0);
+        let mut buffer = vec![0; CHUNK_FRAMES * frame_bytes];
+        let mut interleaved = vec![T::default(); CHUNK_FRAMES * channels];
+        let mut nbr_frames = 0;
+        while nbr_frames < max_frames {
+            let chunk_frames = CHUNK_FRAMES.min(max_frames - nbr_frames);
+            let (nbr_read, partial_bytes) = read_frame_chunk::<T, Self>(
+                rawbytes,
+                &mut buffer[0..chunk_frames * frame_bytes],
+                &mut interleaved,
+                frame_bytes,
+                sampleformat,
+            )?;
+            for (frame, values) in interleaved[0..nbr_read * channels]
+                .chunks(channels)
+                .enumerate()
+            {
+                for (chan, value) in samples.iter_mut().zip(values.iter()) {
+                    chan.as_mut()[nbr_frames + frame] = *value;
+                }
+            }
+            nbr_frames += nbr_read;
+            if partial_bytes > 0 {
+                return Err(Error::TruncatedFrame {
+                    frames: nbr_frames,
+                    bytes: partial_bytes,
+                });
+            }
+            if nbr_read < chunk_frames {
+                break;
+            }
+        }
+        Ok(nbr_frames)
+    }
+
+    /// Read all interleaved frames from anything that implements the "Read" trait,
+    /// and append the samples to planar vecs, one per channel.
+    /// The number of channels is given by the number of vecs.
+    /// If end-of-file of the source is reached in the middle of a frame, [Error::TruncatedFrame] is returned,
+    /// after appending the whole frames before that.
+    /// The number of frames read is returned.
+    fn read_all_planar(
+        rawbytes: &mut dyn Read,
+        samples: &mut [Vec<T>],
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy + Default,
+    {
+        let channels = samples.len();
+        let frame_bytes = sampleformat.frame_bytes(channels)?;
+        let mut buffer = vec![0; CHUNK_FRAMES * frame_bytes];
+        let mut interleaved = vec![T::default(); CHUNK_FRAMES * channels];
+        let mut nbr_frames = 0;
+        loop {
+            let (nbr_read, partial_bytes) = read_frame_chunk::<T, Self>(
+                rawbytes,
+                &mut buffer,
+                &mut interleaved,
+                frame_bytes,
+                sampleformat,
+            )?;
+            for values in interleaved[0..nbr_read * channels].chunks(channels) {
+                for (chan, value) in samples.iter_mut().zip(values.iter()) {
+                    chan.push(*value);
+                }
+            }
+            nbr_frames += nbr_read;
+            if partial_bytes > 0 {
+                return Err(Error::TruncatedFrame {
+                    frames: nbr_frames,
+                    bytes: partial_bytes,
+                });
+            }
+            if nbr_read < CHUNK_FRAMES {
+                break;
+            }
+        }
+        Ok(nbr_frames)
+    }
+
+    /// Read a planar raw layout, where each channel is stored as a contiguous block of bytes in a separate source,
+    /// and store the samples in planar buffers, one per channel.
+    /// The number of sources must match the number of channels.
+    /// Each channel is read in the same way as for [SampleReader::read_samples].
+    /// The number of frames read is returned.
+    /// If the channels reach end-of-file after different numbers of samples, this is the number of samples of the shortest channel.
+    fn read_planar_raw<V: AsMut<[T]>>(
+        rawbytes: &mut [&mut dyn Read],
+        samples: &mut [V],
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error> {
+        if rawbytes.len() != samples.len() {
+            return Err(Error::InvalidParameter(
+                "the number of sources must match the number of channels",
+            ));
+        }
+        let mut nbr_frames = None;
+        for (source, chan) in rawbytes.iter_mut().zip(samples.iter_mut()) {
+            let nbr_read = Self::read_samples(*source, chan.as_mut(), sampleformat)?;
+            nbr_frames = Some(nbr_frames.map_or(nbr_read, |n: usize| n.min(nbr_read)));
+        }
+        Ok(nbr_frames.unwrap_or(0))
+    }
+
+    /// Read all of a planar raw layout, where each channel is stored as a contiguous block of bytes in a separate source,
+    /// and append the samples to planar vecs, one per channel.
+    /// The number of sources must match the number of channels.
+    /// The number of frames read is returned.
+    /// If the channels reach end-of-file after different numbers of samples, this is the number of samples of the shortest channel.
+    fn read_all_planar_raw(
+        rawbytes: &mut [&mut dyn Read],
+        samples: &mut [Vec<T>],
+        sampleformat: &SampleFormat,
+    ) -> Result<usize, Error>
+    where
+        T: Copy + Default,
+    {
+        if rawbytes.len() != samples.len() {
+            return Err(Error::InvalidParameter(
+                "the number of sources must match the number of channels",
+            ));
+        }
+        let mut nbr_frames = None;
+        for (source, chan) in rawbytes.iter_mut().zip(samples.iter_mut()) {
+            let nbr_read = Self::read_all_samples(*source, chan, sampleformat)?;
+            nbr_frames = Some(nbr_frames.map_or(nbr_read, |n: usize| n.min(nbr_read)));
+        }
+        Ok(nbr_frames.unwrap_or(0))
+    }
+}
+
+impl SampleReader<f64> for f64 {
+    fn read_samples_from_slice<'a>(
+        rawbytes: &'a [u8],
+        samples: &mut [f64],
+        sampleformat: &SampleFormat,
+    ) -> (usize, &'a [u8]) {
+        convert_bytes_to_values_vectorised(rawbytes, samples, sampleformat, simd::bytes_to_f64)
+    }
+}
+
+impl SampleReader<f32> for f32 {
+    fn read_samples_from_slice<'a>(
+        rawbytes: &'a [u8],
+        samples: &mut [f32],
+        sampleformat: &SampleFormat,
+    ) -> (usize, &'a [u8]) {
+        convert_bytes_to_values_vectorised(rawbytes, samples, sampleformat, simd::bytes_to_f32)
+    }
+}
+
+/// Convert raw bytes from a slice to sample values one by one, for [SampleReader::read_samples_from_slice].
+fn convert_bytes_to_values<'a, T: Sample<T>>(
+    rawbytes: &'a [u8],
+    samples: &mut [T],
+    sampleformat: &SampleFormat,
+) -> (usize, &'a [u8]) {
+    match sampleformat {
+        SampleFormat::S8 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s8, 1)
+        }
+        SampleFormat::S12PackedLE => {
+            convert_bytes_to_sample_pairs!(rawbytes, samples, from_s12_packed_le)
+        }
+        SampleFormat::S12PackedBE => {
+            convert_bytes_to_sample_pairs!(rawbytes, samples, from_s12_packed_be)
+        }
+        SampleFormat::S16LE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s16_le, 2)
+        }
+        SampleFormat::S16BE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s16_be, 2)
+        }
+        SampleFormat::S24LE3 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s24_3_le, 3)
+        }
+        SampleFormat::S24BE3 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s24_3_be, 3)
+        }
+        SampleFormat::S24LE4 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s24_4_le, 4)
+        }
+        SampleFormat::S24BE4 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s24_4_be, 4)
+        }
+        SampleFormat::S24LE4Msb => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s24_4_msb_le, 4)
+        }
+        SampleFormat::S24BE4Msb => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s24_4_msb_be, 4)
+        }
+        SampleFormat::S20LE3 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s20_3_le, 3)
+        }
+        SampleFormat::S20BE3 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s20_3_be, 3)
+        }
+        SampleFormat::S18LE3 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s18_3_le, 3)
+        }
+        SampleFormat::S18BE3 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s18_3_be, 3)
+        }
+        SampleFormat::S32LE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s32_le, 4)
+        }
+        SampleFormat::S32BE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s32_be, 4)
+        }
+        SampleFormat::S64LE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s64_le, 8)
+        }
+        SampleFormat::S64BE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_s64_be, 8)
+        }
+        SampleFormat::U8 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_u8, 1)
+        }
+        SampleFormat::U16LE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_u16_le, 2)
+        }
+        SampleFormat::U16BE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_u16_be, 2)
+        }
+        SampleFormat::U24LE3 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_u24_3_le, 3)
+        }
+        SampleFormat::U24BE3 => {
+            convert_bytes_to_samples!(rawbytes, samples, from_u24_3_be, 3)
+        }
+        SampleFormat::U32LE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_u32_le, 4)
+        }
+        SampleFormat::U32BE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_u32_be, 4)
+        }
+        SampleFormat::ALaw => {
+            convert_bytes_to_samples!(rawbytes, samples, from_alaw, 1)
+        }
+        SampleFormat::MuLaw => {
+            convert_bytes_to_samples!(rawbytes, samples, from_mulaw, 1)
+        }
+        SampleFormat::FixedPoint(format) => {
+            convert_bytes_to_samples!(rawbytes, samples, from_fixed, format, format.bytes)
+        }
+        SampleFormat::F32LE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_f32_le, 4)
+        }
+        SampleFormat::F32BE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_f32_be, 4)
+        }
+        SampleFormat::F64LE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_f64_le, 8)
+        }
+        SampleFormat::F64BE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_f64_be, 8)
+        }
+        SampleFormat::F16LE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_f16_le, 2)
+        }
+        SampleFormat::F16BE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_f16_be, 2)
+        }
+        SampleFormat::BF16LE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_bf16_le, 2)
+        }
+        SampleFormat::BF16BE => {
+            convert_bytes_to_samples!(rawbytes, samples, from_bf16_be, 2)
+        }
+    }
+}
+
+/// Convert raw bytes from a slice to sample values, with whole blocks converted by a vectorised kernel
+/// and the rest one by one.
+fn convert_bytes_to_values_vectorised<'a, T: Sample<T>>(
+    rawbytes: &'a [u8],
+    samples: &mut [T],
+    sampleformat: &SampleFormat,
+    kernel: simd::ReadKernel<T>,
+) -> (usize, &'a [u8]) {
+    let nbr_vectorised = kernel(rawbytes, samples, sampleformat);
+    let (nbr_converted, remaining) = convert_bytes_to_values(
+        &rawbytes[sampleformat.bytes_for_samples(nbr_vectorised)..],
+        &mut samples[nbr_vectorised..],
+        sampleformat,
+    );
+    (nbr_vectorised + nbr_converted, remaining)
+}
+
+/// Clamp a float value to the range supported by an integer type
+#[inline]
+fn clamp_int<T: Float, U: Bounded + ToPrimitive>(value: T) -> (T, bool) {
+    if value > T::from(U::max_value()).unwrap() {
+        return (T::from(U::max_value()).unwrap(), true);
+    } else if value < T::from(U::min_value()).unwrap() {
+        return (T::from(U::min_value()).unwrap(), true);
+    }
+    (value, false)
+}
+
+/// Clamp a float value to the range of an i64.
+/// The largest i64 can't be represented exactly as a float, and rounds up to 2^63,
+/// which means that all values from 2^63 and up are clipped.
+#[inline]
+fn clamp_i64<T: Float>(value: T) -> (T, bool) {
+    let limit = -T::from(i64::MIN).unwrap();
+    if value >= limit {
+        return (limit, true);
+    } else if value < -limit {
+        return (-limit, true);
+    }
+    (value, false)
+}
+
+/// Pack the upper 12 bits of two 16 bit values into 3 bytes
+fn pack_s12(values: [i16; 2], big_endian: bool) -> [u8; 3] {
+    let first = (values[0] as u16) >> 4;
+    let second = (values[1] as u16) >> 4;
+    if big_endian {
+        [
+            (first >> 4) as u8,
+            ((first & 0x0F) << 4) as u8 | (second >> 8) as u8,
+            (second & 0xFF) as u8,
+        ]
+    } else {
+        [
+            (first & 0xFF) as u8,
+            (first >> 8) as u8 | ((second & 0x0F) << 4) as u8,
+            (second >> 4) as u8,
+        ]
+    }
+}
+
+/// Unpack two 12 bit values from 3 bytes, to the upper 12 bits of two 16 bit values
+fn unpack_s12(bytes: [u8; 3], big_endian: bool) -> [i16; 2] {
+    let (first, second) = if big_endian {
+        (
+            u16::from(bytes[0]) << 4 | u16::from(bytes[1]) >> 4,
+            u16::from(bytes[1] & 0x0F) << 8 | u16::from(bytes[2]),
+        )
+    } else {
+        (
+            u16::from(bytes[0]) | u16::from(bytes[1] & 0x0F) << 8,
+            u16::from(bytes[1]) >> 4 | u16::from(bytes[2]) << 4,
+        )
+    };
+    [(first << 4) as i16, (second << 4) as i16]
+}
+
+/// Clamp a float value to the -1.0 .. +1.0
+fn clamp_float<T: Float>(value: T) -> (T, bool) {
+    if value >= T::one() {
+        return (T::one(), true);
+    } else if value < -T::one() {
+        return (-T::one(), true);
+    }
+    (value, false)
+}
+
+/// Find the G.711 segment of a magnitude, given the upper end of each segment
+fn g711_segment(value: i16, segment_ends: &[i16; 8]) -> usize {
+    segment_ends
+        .iter()
+        .position(|end| value <= *end)
+        .unwrap_or(8)
+}
+
+/// Compress a 16 bit linear value to G.711 A-law
+fn linear_to_alaw(value: i16) -> u8 {
+    const SEGMENT_ENDS: [i16; 8] = [0x1F, 0x3F, 0x7F, 0xFF, 0x1FF, 0x3FF, 0x7FF, 0xFFF];
+    let mut value = value >> 3;
+    let mask = if value >= 0 {
+        0xD5
+    } else {
+        value = -value - 1;
+        0x55
+    };
+    let segment = g711_segment(value, &SEGMENT_ENDS);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    let shift = if segment < 2 { 1 } else { segment };
+    let aval = (segment << 4) as u8 | ((value >> shift) & 0x0F) as u8;
+    aval ^ mask
+}
+
+/// Expand a G.711 A-law value to 16 bit linear
+fn alaw_to_linear(value: u8) -> i16 {
+    let value = value ^ 0x55;
+    let mut linear = i16::from(value & 0x0F) << 4;
+    let segment = (value & 0x70) >> 4;
+    match segment {
+        0 => linear += 8,
+        1 => linear += 0x108,
+        _ => {
+            linear += 0x108;
+            linear <<= segment - 1;
+        }
+    }
+    if value & 0x80 != 0 {
+        linear
+    } else {
+        -linear
+    }
+}
+
+/// Compress a 16 bit linear value to G.711 mu-law
+fn linear_to_mulaw(value: i16) -> u8 {
+    const SEGMENT_ENDS: [i16; 8] = [0x3F, 0x7F, 0xFF, 0x1FF, 0x3FF, 0x7FF, 0xFFF, 0x1FFF];
+    const BIAS: i16 = 0x84;
+    const CLIP: i16 = 8159;
+    let mut value = value >> 2;
+    let mask = if value < 0 {
+        value = -value;
+        0x7F
+    } else {
+        0xFF
+    };
+    value = value.min(CLIP) + (BIAS >> 2);
+    let segment = g711_segment(value, &SEGMENT_ENDS);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    let uval = (segment << 4) as u8 | ((value >> (segment + 1)) & 0x0F) as u8;
+    uval ^ mask
+}
+
+/// Expand a G.711 mu-law value to 16 bit linear
+fn mulaw_to_linear(value: u8) -> i16 {
+    const BIAS: i16 = 0x84;
+    let value = !value;
+    let mut linear = (i16::from(value & 0x0F) << 3) + BIAS;
+    linear <<= (value & 0x70) >> 4;
+    if value & 0x80 != 0 {
+        BIAS - linear
+    } else {
+        linear - BIAS
+    }
+}
+
+/// Convert an f32 value to the bits of an IEEE 754 half precision float.
+/// The value is rounded to nearest, ties to even.
+/// Values too large for half precision become infinity.
+fn f32_to_f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+    if exponent == 0xFF {
+        // infinity stays infinity, NaN stays a quiet NaN
+        if mantissa == 0 {
+            return sign | 0x7C00;
+        }
+        return sign | 0x7E00 | (mantissa >> 13) as u16;
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+    if half_exponent <= 0 {
+        // the result is subnormal, or zero
+        if half_exponent < -10 {
+            return sign;
+        }
+        let full = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        let halfway = 1 << (shift - 1);
+        let remainder = full & ((1 << shift) - 1);
+        let mut half = full >> shift;
+        if remainder > halfway || (remainder == halfway && half & 1 == 1) {
+            half += 1;
+        }
+        return sign | half as u16;
+    }
+    let mut half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let remainder = mantissa & 0x1FFF;
+    if remainder > 0x1000 || (remainder == 0x1000 && half & 1 == 1) {
+        // a carry into the exponent is correct, and may give infinity
+        half += 1;
+    }
+    sign | half as u16
+}
+
+/// Convert the bits of an IEEE 754 half precision float to an f32 value.
+/// This conversion is exact.
+fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exponent = u32::from((bits >> 10) & 0x1F);
+    let mantissa = u32::from(bits & 0x3FF);
+    if exponent == 0 {
+        let magnitude = mantissa as f32 / 16777216.0;
+        return if sign == 0 { magnitude } else { -magnitude };
+    }
+    if exponent == 0x1F {
+        return f32::from_bits(sign | 0x7F80_0000 | (mantissa << 13));
+    }
+    f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13))
+}
+
+/// Convert an f32 value to the bits of a bfloat16 float.
+/// The value is rounded to nearest, ties to even.
+/// Values too large for bfloat16 become infinity.
+fn f32_to_bf16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    if value.is_nan() {
+        return (bits >> 16) as u16 | 0x0040;
+    }
+    let rounding = 0x7FFF + ((bits >> 16) & 1);
+    ((bits + rounding) >> 16) as u16
+}
+
+/// Convert the bits of a bfloat16 float to an f32 value.
+/// This conversion is exact.
+fn bf16_bits_to_f32(bits: u16) -> f32 {
+    f32::from_bits(u32::from(bits) << 16)
+}
+
+impl Sample<f64> for f64 {
+    const MAX_I64: f64 = 9223372036854775808.0;
+    const MAX_I32: f64 = 2147483648.0;
+    const MAX_I24: f64 = 8388608.0;
+    const MAX_I20: f64 = 524288.0;
+    const MAX_I18: f64 = 131072.0;
+    const MAX_I16: f64 = 32768.0;
+    const MAX_I8: f64 = 128.0;
+
+    #[inline]
+    fn to_s16_le(&self) -> ([u8; 2], bool) {
+        let val = (self * f64::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i16>(val);
+        ((val as i16).to_le_bytes(), clipped)
+    }
+
+    #[inline]
+    fn to_s16_be(&self) -> ([u8; 2], bool) {
+        let val = (self * f64::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i16>(val);
+        ((val as i16).to_be_bytes(), clipped)
+    }
+
+    fn to_s8(&self) -> ([u8; 1], bool) {
+        let val = (self * f64::MAX_I8).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i8>(val);
+        ((val as i8).to_le_bytes(), clipped)
+    }
+
+    fn to_s12_packed_le(&self, second: &Self) -> ([u8; 3], usize) {
+        let (first, clipped_first) =
+            clamp_int::<f64, i16>((self * f64::MAX_I16 / 16.0).round_ties_even() * 16.0);
+        let (second, clipped_second) =
+            clamp_int::<f64, i16>((second * f64::MAX_I16 / 16.0).round_ties_even() * 16.0);
+        let bytes = pack_s12([first as i16, second as i16], false);
+        (bytes, clipped_first as usize + clipped_second as usize)
+    }
+
+    fn to_s12_packed_be(&self, second: &Self) -> ([u8; 3], usize) {
+        let (first, clipped_first) =
+            clamp_int::<f64, i16>((self * f64::MAX_I16 / 16.0).round_ties_even() * 16.0);
+        let (second, clipped_second) =
+            clamp_int::<f64, i16>((second * f64::MAX_I16 / 16.0).round_ties_even() * 16.0);
+        let bytes = pack_s12([first as i16, second as i16], true);
+        (bytes, clipped_first as usize + clipped_second as usize)
+    }
+
+    fn to_s64_le(&self) -> ([u8; 8], bool) {
+        let val = (self * f64::MAX_I64).round_ties_even();
+        let (val, clipped) = clamp_i64(val);
+        ((val as i64).to_le_bytes(), clipped)
+    }
+
+    fn to_s64_be(&self) -> ([u8; 8], bool) {
+        let val = (self * f64::MAX_I64).round_ties_even();
+        let (val, clipped) = clamp_i64(val);
+        ((val as i64).to_be_bytes(), clipped)
+    }
+
+    #[inline]
+    fn to_s32_le(&self) -> ([u8; 4], bool) {
+        let val = (self * f64::MAX_I32).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        ((val as i32).to_le_bytes(), clipped)
+    }
+
+    #[inline]
+    fn to_s32_be(&self) -> ([u8; 4], bool) {
+        let val = (self * f64::MAX_I32).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        ((val as i32).to_be_bytes(), clipped)
+    }
+
+    #[inline]
+    fn to_s24_3_le(&self) -> ([u8; 3], bool) {
+        let val = (self * f64::MAX_I24).round_ties_even() * (f64::MAX_I32 / f64::MAX_I24);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = (val as i32).to_le_bytes();
+        ([bytes[1], bytes[2], bytes[3]], clipped)
+    }
+
+    #[inline]
+    fn to_s24_3_be(&self) -> ([u8; 3], bool) {
+        let val = (self * f64::MAX_I24).round_ties_even() * (f64::MAX_I32 / f64::MAX_I24);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = (val as i32).to_be_bytes();
+        ([bytes[0], bytes[1], bytes[2]], clipped)
+    }
+
+    #[inline]
+    fn to_s24_4_le(&self) -> ([u8; 4], bool) {
+        let val = (self * f64::MAX_I24).round_ties_even() * (f64::MAX_I32 / f64::MAX_I24);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = (val as i32).to_le_bytes();
+        ([bytes[1], bytes[2], bytes[3], 0], clipped)
+    }
+
+    #[inline]
+    fn to_s24_4_be(&self) -> ([u8; 4], bool) {
+        let val = (self * f64::MAX_I24).round_ties_even() * (f64::MAX_I32 / f64::MAX_I24);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = (val as i32).to_be_bytes();
+        ([0, bytes[0], bytes[1], bytes[2]], clipped)
+    }
+
+    #[inline]
+    fn to_s24_4_msb_le(&self) -> ([u8; 4], bool) {
+        let val = (self * f64::MAX_I24).round_ties_even() * (f64::MAX_I32 / f64::MAX_I24);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = (val as i32).to_le_bytes();
+        ([0, bytes[1], bytes[2], bytes[3]], clipped)
+    }
+
+    #[inline]
+    fn to_s24_4_msb_be(&self) -> ([u8; 4], bool) {
+        let val = (self * f64::MAX_I24).round_ties_even() * (f64::MAX_I32 / f64::MAX_I24);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = (val as i32).to_be_bytes();
+        ([bytes[0], bytes[1], bytes[2], 0], clipped)
+    }
+
+    fn to_s20_3_le(&self) -> ([u8; 3], bool) {
+        let val = (self * f64::MAX_I20).round_ties_even() * (f64::MAX_I32 / f64::MAX_I20);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = ((val as i32) >> 12).to_le_bytes();
+        ([bytes[0], bytes[1], bytes[2]], clipped)
+    }
+
+    fn to_s20_3_be(&self) -> ([u8; 3], bool) {
+        let val = (self * f64::MAX_I20).round_ties_even() * (f64::MAX_I32 / f64::MAX_I20);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = ((val as i32) >> 12).to_be_bytes();
+        ([bytes[1], bytes[2], bytes[3]], clipped)
+    }
+
+    fn to_s18_3_le(&self) -> ([u8; 3], bool) {
+        let val = (self * f64::MAX_I18).round_ties_even() * (f64::MAX_I32 / f64::MAX_I18);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = ((val as i32) >> 14).to_le_bytes();
+        ([bytes[0], bytes[1], bytes[2]], clipped)
+    }
+
+    fn to_s18_3_be(&self) -> ([u8; 3], bool) {
+        let val = (self * f64::MAX_I18).round_ties_even() * (f64::MAX_I32 / f64::MAX_I18);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = ((val as i32) >> 14).to_be_bytes();
+        ([bytes[1], bytes[2], bytes[3]], clipped)
+    }
+
+    fn to_u32_le(&self) -> ([u8; 4], bool) {
+        let val = (self * f64::MAX_I32).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        (((val as i32) as u32 ^ 0x8000_0000).to_le_bytes(), clipped)
+    }
+
+    fn to_u32_be(&self) -> ([u8; 4], bool) {
+        let val = (self * f64::MAX_I32).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        (((val as i32) as u32 ^ 0x8000_0000).to_be_bytes(), clipped)
+    }
+
+    fn to_u24_3_le(&self) -> ([u8; 3], bool) {
+        let val = (self * f64::MAX_I24).round_ties_even() * (f64::MAX_I32 / f64::MAX_I24);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = (val as i32).to_le_bytes();
+        ([bytes[1], bytes[2], bytes[3] ^ 0x80], clipped)
+    }
+
+    fn to_u24_3_be(&self) -> ([u8; 3], bool) {
+        let val = (self * f64::MAX_I24).round_ties_even() * (f64::MAX_I32 / f64::MAX_I24);
+        let (val, clipped) = clamp_int::<f64, i32>(val);
+        let bytes = (val as i32).to_be_bytes();
+        ([bytes[0] ^ 0x80, bytes[1], bytes[2]], clipped)
+    }
+
+    fn to_u16_le(&self) -> ([u8; 2], bool) {
+        let val = (self * f64::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i16>(val);
+        (((val as i16) as u16 ^ 0x8000).to_le_bytes(), clipped)
+    }
+
+    fn to_u16_be(&self) -> ([u8; 2], bool) {
+        let val = (self * f64::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i16>(val);
+        (((val as i16) as u16 ^ 0x8000).to_be_bytes(), clipped)
+    }
+
+    fn to_u8(&self) -> ([u8; 1], bool) {
+        let val = (self * f64::MAX_I8).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i8>(val);
+        ([(val as i8) as u8 ^ 0x80], clipped)
+    }
+
+    fn to_alaw(&self) -> ([u8; 1], bool) {
+        let val = (self * f64::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i16>(val);
+        ([linear_to_alaw(val as i16)], clipped)
+    }
+
+    fn to_mulaw(&self) -> ([u8; 1], bool) {
+        let val = (self * f64::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f64, i16>(val);
+        ([linear_to_mulaw(val as i16)], clipped)
+    }
+
+    fn to_fixed(&self, format: &FixedPoint) -> ([u8; 8], bool) {
+        let val = (self * format.scale::<f64>()).round_ties_even();
+        // the largest integer of the wider formats can't be represented exactly as a float,
+        // clamp to the i64 range first and then to the used bits as an integer
+        let (val, clipped_i64) = clamp_i64(val);
+        let (val, clipped) = format.clamp(val as i64);
+        (format.encode(val), clipped || clipped_i64)
+    }
+
+    fn to_f64_le(&self) -> ([u8; 8], bool) {
+        let val = *self;
+        let (val, clipped) = clamp_float(val);
+        (val.to_le_bytes(), clipped)
+    }
+
+    fn to_f64_be(&self) -> ([u8; 8], bool) {
+        let val = *self;
+        let (val, clipped) = clamp_float(val);
+        (val.to_be_bytes(), clipped)
+    }
+
+    fn to_f32_le(&self) -> ([u8; 4], bool) {
+        let val = *self as f32;
+        let (val, clipped) = clamp_float(val);
+        (val.to_le_bytes(), clipped)
+    }
+
+    fn to_f32_be(&self) -> ([u8; 4], bool) {
+        let val = *self as f32;
+        let (val, clipped) = clamp_float(val);
+        (val.to_be_bytes(), clipped)
+    }
+
+    fn to_f16_le(&self) -> ([u8; 2], bool) {
+        let val = *self as f32;
+        let (val, clipped) = clamp_float(val);
+        (f32_to_f16_bits(val).to_le_bytes(), clipped)
+    }
+
+    fn to_f16_be(&self) -> ([u8; 2], bool) {
+        let val = *self as f32;
+        let (val, clipped) = clamp_float(val);
+        (f32_to_f16_bits(val).to_be_bytes(), clipped)
+    }
+
+    fn to_bf16_le(&self) -> ([u8; 2], bool) {
+        let val = *self as f32;
+        let (val, clipped) = clamp_float(val);
+        (f32_to_bf16_bits(val).to_le_bytes(), clipped)
+    }
+
+    fn to_bf16_be(&self) -> ([u8; 2], bool) {
+        let val = *self as f32;
+        let (val, clipped) = clamp_float(val);
+        (f32_to_bf16_bits(val).to_be_bytes(), clipped)
+    }
+
+    fn from_s64_le(bytes: [u8; 8]) -> Self {
+        let intvalue = i64::from_le_bytes(bytes);
+        intvalue as f64 / f64::MAX_I64
+    }
+
+    fn from_s64_be(bytes: [u8; 8]) -> Self {
+        let intvalue = i64::from_be_bytes(bytes);
+        intvalue as f64 / f64::MAX_I64
+    }
+
+    #[inline]
+    fn from_s32_le(bytes: [u8; 4]) -> Self {
+        let intvalue = i32::from_le_bytes(bytes);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    #[inline]
+    fn from_s32_be(bytes: [u8; 4]) -> Self {
+        let intvalue = i32::from_be_bytes(bytes);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    #[inline]
+    fn from_s16_le(bytes: [u8; 2]) -> Self {
+        let intvalue = i16::from_le_bytes(bytes);
+        f64::from(intvalue) / f64::MAX_I16
+    }
+
+    #[inline]
+    fn from_s16_be(bytes: [u8; 2]) -> Self {
+        let intvalue = i16::from_be_bytes(bytes);
+        f64::from(intvalue) / f64::MAX_I16
+    }
+
+    fn from_s8(bytes: [u8; 1]) -> Self {
+        let intvalue = i8::from_le_bytes(bytes);
+        f64::from(intvalue) / f64::MAX_I8
+    }
+
+    fn from_s12_packed_le(bytes: [u8; 3]) -> [Self; 2] {
+        let [first, second] = unpack_s12(bytes, false);
+        [
+            f64::from(first) / f64::MAX_I16,
+            f64::from(second) / f64::MAX_I16,
+        ]
+    }
+
+    fn from_s12_packed_be(bytes: [u8; 3]) -> [Self; 2] {
+        let [first, second] = unpack_s12(bytes, true);
+        [
+            f64::from(first) / f64::MAX_I16,
+            f64::from(second) / f64::MAX_I16,
+        ]
+    }
+
+    #[inline]
+    fn from_s24_3_le(bytes: [u8; 3]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2]];
+        let intvalue = i32::from_le_bytes(padded);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_3_be(bytes: [u8; 3]) -> Self {
+        let padded = [bytes[0], bytes[1], bytes[2], 0];
+        let intvalue = i32::from_be_bytes(padded);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_4_le(bytes: [u8; 4]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2]];
+        let intvalue = i32::from_le_bytes(padded);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_4_be(bytes: [u8; 4]) -> Self {
+        let padded = [bytes[1], bytes[2], bytes[3], 0];
+        let intvalue = i32::from_be_bytes(padded);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_4_msb_le(bytes: [u8; 4]) -> Self {
+        let padded = [0, bytes[1], bytes[2], bytes[3]];
+        let intvalue = i32::from_le_bytes(padded);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_4_msb_be(bytes: [u8; 4]) -> Self {
+        let padded = [bytes[0], bytes[1], bytes[2], 0];
+        let intvalue = i32::from_be_bytes(padded);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    fn from_s20_3_le(bytes: [u8; 3]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2]];
+        let intvalue = (i32::from_le_bytes(padded) << 4) >> 12;
+        f64::from(intvalue) / f64::MAX_I20
+    }
+
+    fn from_s20_3_be(bytes: [u8; 3]) -> Self {
+        let padded = [bytes[0], bytes[1], bytes[2], 0];
+        let intvalue = (i32::from_be_bytes(padded) << 4) >> 12;
+        f64::from(intvalue) / f64::MAX_I20
+    }
+
+    fn from_s18_3_le(bytes: [u8; 3]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2]];
+        let intvalue = (i32::from_le_bytes(padded) << 6) >> 14;
+        f64::from(intvalue) / f64::MAX_I18
+    }
+
+    fn from_s18_3_be(bytes: [u8; 3]) -> Self {
+        let padded = [bytes[0], bytes[1], bytes[2], 0];
+        let intvalue = (i32::from_be_bytes(padded) << 6) >> 14;
+        f64::from(intvalue) / f64::MAX_I18
+    }
+
+    fn from_u32_le(bytes: [u8; 4]) -> Self {
+        let intvalue = (u32::from_le_bytes(bytes) ^ 0x8000_0000) as i32;
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    fn from_u32_be(bytes: [u8; 4]) -> Self {
+        let intvalue = (u32::from_be_bytes(bytes) ^ 0x8000_0000) as i32;
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    fn from_u24_3_le(bytes: [u8; 3]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2] ^ 0x80];
+        let intvalue = i32::from_le_bytes(padded);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    fn from_u24_3_be(bytes: [u8; 3]) -> Self {
+        let padded = [bytes[0] ^ 0x80, bytes[1], bytes[2], 0];
+        let intvalue = i32::from_be_bytes(padded);
+        f64::from(intvalue) / f64::MAX_I32
+    }
+
+    fn from_u16_le(bytes: [u8; 2]) -> Self {
+        let intvalue = (u16::from_le_bytes(bytes) ^ 0x8000) as i16;
+        f64::from(intvalue) / f64::MAX_I16
+    }
+
+    fn from_u16_be(bytes: [u8; 2]) -> Self {
+        let intvalue = (u16::from_be_bytes(bytes) ^ 0x8000) as i16;
+        f64::from(intvalue) / f64::MAX_I16
+    }
+
+    fn from_u8(bytes: [u8; 1]) -> Self {
+        let intvalue = (bytes[0] ^ 0x80) as i8;
+        f64::from(intvalue) / f64::MAX_I8
+    }
+
+    fn from_alaw(bytes: [u8; 1]) -> Self {
+        let intvalue = alaw_to_linear(bytes[0]);
+        f64::from(intvalue) / f64::MAX_I16
+    }
+
+    fn from_mulaw(bytes: [u8; 1]) -> Self {
+        let intvalue = mulaw_to_linear(bytes[0]);
+        f64::from(intvalue) / f64::MAX_I16
+    }
+
+    fn from_fixed(bytes: [u8; 8], format: &FixedPoint) -> Self {
+        let intvalue = format.decode(bytes);
+        intvalue as f64 / format.scale::<f64>()
+    }
+
+    fn from_f32_le(bytes: [u8; 4]) -> Self {
+        f64::from(f32::from_le_bytes(bytes))
+    }
+
+    fn from_f32_be(bytes: [u8; 4]) -> Self {
+        f64::from(f32::from_be_bytes(bytes))
+    }
+
+    fn from_f64_le(bytes: [u8; 8]) -> Self {
+        f64::from_le_bytes(bytes)
+    }
+
+    fn from_f64_be(bytes: [u8; 8]) -> Self {
+        f64::from_be_bytes(bytes)
+    }
+
+    fn from_f16_le(bytes: [u8; 2]) -> Self {
+        f64::from(f16_bits_to_f32(u16::from_le_bytes(bytes)))
+    }
+
+    fn from_f16_be(bytes: [u8; 2]) -> Self {
+        f64::from(f16_bits_to_f32(u16::from_be_bytes(bytes)))
+    }
+
+    fn from_bf16_le(bytes: [u8; 2]) -> Self {
+        f64::from(bf16_bits_to_f32(u16::from_le_bytes(bytes)))
+    }
+
+    fn from_bf16_be(bytes: [u8; 2]) -> Self {
+        f64::from(bf16_bits_to_f32(u16::from_be_bytes(bytes)))
+    }
+}
+
+impl Sample<f32> for f32 {
+    const MAX_I64: f32 = 9223372036854775808.0;
+    const MAX_I32: f32 = 2147483648.0;
     const MAX_I24: f32 = 8388608.0;
+    const MAX_I20: f32 = 524288.0;
+    const MAX_I18: f32 = 131072.0;
     const MAX_I16: f32 = 32768.0;
+    const MAX_I8: f32 = 128.0;
+
+    #[inline]
+    fn to_s16_le(&self) -> ([u8; 2], bool) {
+        let val = (self * f32::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i16>(val);
+        ((val as i16).to_le_bytes(), clipped)
+    }
+
+    #[inline]
+    fn to_s16_be(&self) -> ([u8; 2], bool) {
+        let val = (self * f32::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i16>(val);
+        ((val as i16).to_be_bytes(), clipped)
+    }
+
+    fn to_s8(&self) -> ([u8; 1], bool) {
+        let val = (self * f32::MAX_I8).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i8>(val);
+        ((val as i8).to_le_bytes(), clipped)
+    }
+
+    fn to_s12_packed_le(&self, second: &Self) -> ([u8; 3], usize) {
+        let (first, clipped_first) =
+            clamp_int::<f32, i16>((self * f32::MAX_I16 / 16.0).round_ties_even() * 16.0);
+        let (second, clipped_second) =
+            clamp_int::<f32, i16>((second * f32::MAX_I16 / 16.0).round_ties_even() * 16.0);
+        let bytes = pack_s12([first as i16, second as i16], false);
+        (bytes, clipped_first as usize + clipped_second as usize)
+    }
+
+    fn to_s12_packed_be(&self, second: &Self) -> ([u8; 3], usize) {
+        let (first, clipped_first) =
+            clamp_int::<f32, i16>((self * f32::MAX_I16 / 16.0).round_ties_even() * 16.0);
+        let (second, clipped_second) =
+            clamp_int::<f32, i16>((second * f32::MAX_I16 / 16.0).round_ties_even() * 16.0);
+        let bytes = pack_s12([first as i16, second as i16], true);
+        (bytes, clipped_first as usize + clipped_second as usize)
+    }
+
+    fn to_s64_le(&self) -> ([u8; 8], bool) {
+        let val = (self * f32::MAX_I64).round_ties_even();
+        let (val, clipped) = clamp_i64(val);
+        ((val as i64).to_le_bytes(), clipped)
+    }
+
+    fn to_s64_be(&self) -> ([u8; 8], bool) {
+        let val = (self * f32::MAX_I64).round_ties_even();
+        let (val, clipped) = clamp_i64(val);
+        ((val as i64).to_be_bytes(), clipped)
+    }
+
+    #[inline]
+    fn to_s32_le(&self) -> ([u8; 4], bool) {
+        let val = (self * f32::MAX_I32).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        ((val as i32).to_le_bytes(), clipped)
+    }
+
+    #[inline]
+    fn to_s32_be(&self) -> ([u8; 4], bool) {
+        let val = (self * f32::MAX_I32).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        ((val as i32).to_be_bytes(), clipped)
+    }
+
+    #[inline]
+    fn to_s24_3_le(&self) -> ([u8; 3], bool) {
+        let val = (self * f32::MAX_I24).round_ties_even() * (f32::MAX_I32 / f32::MAX_I24);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = (val as i32).to_le_bytes();
+        ([bytes[1], bytes[2], bytes[3]], clipped)
+    }
+
+    #[inline]
+    fn to_s24_3_be(&self) -> ([u8; 3], bool) {
+        let val = (self * f32::MAX_I24).round_ties_even() * (f32::MAX_I32 / f32::MAX_I24);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = (val as i32).to_be_bytes();
+        ([bytes[0], bytes[1], bytes[2]], clipped)
+    }
+
+    #[inline]
+    fn to_s24_4_le(&self) -> ([u8; 4], bool) {
+        let val = (self * f32::MAX_I24).round_ties_even() * (f32::MAX_I32 / f32::MAX_I24);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = (val as i32).to_le_bytes();
+        ([bytes[1], bytes[2], bytes[3], 0], clipped)
+    }
+
+    #[inline]
+    fn to_s24_4_be(&self) -> ([u8; 4], bool) {
+        let val = (self * f32::MAX_I24).round_ties_even() * (f32::MAX_I32 / f32::MAX_I24);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = (val as i32).to_be_bytes();
+        ([0, bytes[0], bytes[1], bytes[2]], clipped)
+    }
+
+    #[inline]
+    fn to_s24_4_msb_le(&self) -> ([u8; 4], bool) {
+        let val = (self * f32::MAX_I24).round_ties_even() * (f32::MAX_I32 / f32::MAX_I24);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = (val as i32).to_le_bytes();
+        ([0, bytes[1], bytes[2], bytes[3]], clipped)
+    }
+
+    #[inline]
+    fn to_s24_4_msb_be(&self) -> ([u8; 4], bool) {
+        let val = (self * f32::MAX_I24).round_ties_even() * (f32::MAX_I32 / f32::MAX_I24);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = (val as i32).to_be_bytes();
+        ([bytes[0], bytes[1], bytes[2], 0], clipped)
+    }
+
+    fn to_s20_3_le(&self) -> ([u8; 3], bool) {
+        let val = (self * f32::MAX_I20).round_ties_even() * (f32::MAX_I32 / f32::MAX_I20);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = ((val as i32) >> 12).to_le_bytes();
+        ([bytes[0], bytes[1], bytes[2]], clipped)
+    }
+
+    fn to_s20_3_be(&self) -> ([u8; 3], bool) {
+        let val = (self * f32::MAX_I20).round_ties_even() * (f32::MAX_I32 / f32::MAX_I20);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = ((val as i32) >> 12).to_be_bytes();
+        ([bytes[1], bytes[2], bytes[3]], clipped)
+    }
+
+    fn to_s18_3_le(&self) -> ([u8; 3], bool) {
+        let val = (self * f32::MAX_I18).round_ties_even() * (f32::MAX_I32 / f32::MAX_I18);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = ((val as i32) >> 14).to_le_bytes();
+        ([bytes[0], bytes[1], bytes[2]], clipped)
+    }
+
+    fn to_s18_3_be(&self) -> ([u8; 3], bool) {
+        let val = (self * f32::MAX_I18).round_ties_even() * (f32::MAX_I32 / f32::MAX_I18);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = ((val as i32) >> 14).to_be_bytes();
+        ([bytes[1], bytes[2], bytes[3]], clipped)
+    }
+
+    fn to_u32_le(&self) -> ([u8; 4], bool) {
+        let val = (self * f32::MAX_I32).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        (((val as i32) as u32 ^ 0x8000_0000).to_le_bytes(), clipped)
+    }
+
+    fn to_u32_be(&self) -> ([u8; 4], bool) {
+        let val = (self * f32::MAX_I32).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        (((val as i32) as u32 ^ 0x8000_0000).to_be_bytes(), clipped)
+    }
+
+    fn to_u24_3_le(&self) -> ([u8; 3], bool) {
+        let val = (self * f32::MAX_I24).round_ties_even() * (f32::MAX_I32 / f32::MAX_I24);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = (val as i32).to_le_bytes();
+        ([bytes[1], bytes[2], bytes[3] ^ 0x80], clipped)
+    }
+
+    fn to_u24_3_be(&self) -> ([u8; 3], bool) {
+        let val = (self * f32::MAX_I24).round_ties_even() * (f32::MAX_I32 / f32::MAX_I24);
+        let (val, clipped) = clamp_int::<f32, i32>(val);
+        let bytes = (val as i32).to_be_bytes();
+        ([bytes[0] ^ 0x80, bytes[1], bytes[2]], clipped)
+    }
+
+    fn to_u16_le(&self) -> ([u8; 2], bool) {
+        let val = (self * f32::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i16>(val);
+        (((val as i16) as u16 ^ 0x8000).to_le_bytes(), clipped)
+    }
+
+    fn to_u16_be(&self) -> ([u8; 2], bool) {
+        let val = (self * f32::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i16>(val);
+        (((val as i16) as u16 ^ 0x8000).to_be_bytes(), clipped)
+    }
+
+    fn to_u8(&self) -> ([u8; 1], bool) {
+        let val = (self * f32::MAX_I8).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i8>(val);
+        ([(val as i8) as u8 ^ 0x80], clipped)
+    }
+
+    fn to_alaw(&self) -> ([u8; 1], bool) {
+        let val = (self * f32::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i16>(val);
+        ([linear_to_alaw(val as i16)], clipped)
+    }
+
+    fn to_mulaw(&self) -> ([u8; 1], bool) {
+        let val = (self * f32::MAX_I16).round_ties_even();
+        let (val, clipped) = clamp_int::<f32, i16>(val);
+        ([linear_to_mulaw(val as i16)], clipped)
+    }
+
+    fn to_fixed(&self, format: &FixedPoint) -> ([u8; 8], bool) {
+        let val = (self * format.scale::<f32>()).round_ties_even();
+        // the largest integer of the wider formats can't be represented exactly as a float,
+        // clamp to the i64 range first and then to the used bits as an integer
+        let (val, clipped_i64) = clamp_i64(val);
+        let (val, clipped) = format.clamp(val as i64);
+        (format.encode(val), clipped || clipped_i64)
+    }
+
+    fn to_f64_le(&self) -> ([u8; 8], bool) {
+        let val = f64::from(*self);
+        let (val, clipped) = clamp_float(val);
+        (val.to_le_bytes(), clipped)
+    }
+
+    fn to_f64_be(&self) -> ([u8; 8], bool) {
+        let val = f64::from(*self);
+        let (val, clipped) = clamp_float(val);
+        (val.to_be_bytes(), clipped)
+    }
+
+    fn to_f32_le(&self) -> ([u8; 4], bool) {
+        let (val, clipped) = clamp_float(*self);
+        (val.to_le_bytes(), clipped)
+    }
+
+    fn to_f32_be(&self) -> ([u8; 4], bool) {
+        let (val, clipped) = clamp_float(*self);
+        (val.to_be_bytes(), clipped)
+    }
+
+    fn to_f16_le(&self) -> ([u8; 2], bool) {
+        let (val, clipped) = clamp_float(*self);
+        (f32_to_f16_bits(val).to_le_bytes(), clipped)
+    }
+
+    fn to_f16_be(&self) -> ([u8; 2], bool) {
+        let (val, clipped) = clamp_float(*self);
+        (f32_to_f16_bits(val).to_be_bytes(), clipped)
+    }
+
+    fn to_bf16_le(&self) -> ([u8; 2], bool) {
+        let (val, clipped) = clamp_float(*self);
+        (f32_to_bf16_bits(val).to_le_bytes(), clipped)
+    }
+
+    fn to_bf16_be(&self) -> ([u8; 2], bool) {
+        let (val, clipped) = clamp_float(*self);
+        (f32_to_bf16_bits(val).to_be_bytes(), clipped)
+    }
+
+    fn from_s64_le(bytes: [u8; 8]) -> Self {
+        let intvalue = i64::from_le_bytes(bytes);
+        intvalue as f32 / f32::MAX_I64
+    }
+
+    fn from_s64_be(bytes: [u8; 8]) -> Self {
+        let intvalue = i64::from_be_bytes(bytes);
+        intvalue as f32 / f32::MAX_I64
+    }
+
+    #[inline]
+    fn from_s32_le(bytes: [u8; 4]) -> Self {
+        let intvalue = i32::from_le_bytes(bytes);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    #[inline]
+    fn from_s32_be(bytes: [u8; 4]) -> Self {
+        let intvalue = i32::from_be_bytes(bytes);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    #[inline]
+    fn from_s16_le(bytes: [u8; 2]) -> Self {
+        let intvalue = i16::from_le_bytes(bytes);
+        f32::from(intvalue) / f32::MAX_I16
+    }
+
+    #[inline]
+    fn from_s16_be(bytes: [u8; 2]) -> Self {
+        let intvalue = i16::from_be_bytes(bytes);
+        f32::from(intvalue) / f32::MAX_I16
+    }
+
+    fn from_s8(bytes: [u8; 1]) -> Self {
+        let intvalue = i8::from_le_bytes(bytes);
+        f32::from(intvalue) / f32::MAX_I8
+    }
+
+    fn from_s12_packed_le(bytes: [u8; 3]) -> [Self; 2] {
+        let [first, second] = unpack_s12(bytes, false);
+        [
+            f32::from(first) / f32::MAX_I16,
+            f32::from(second) / f32::MAX_I16,
+        ]
+    }
+
+    fn from_s12_packed_be(bytes: [u8; 3]) -> [Self; 2] {
+        let [first, second] = unpack_s12(bytes, true);
+        [
+            f32::from(first) / f32::MAX_I16,
+            f32::from(second) / f32::MAX_I16,
+        ]
+    }
+
+    #[inline]
+    fn from_s24_3_le(bytes: [u8; 3]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2]];
+        let intvalue = i32::from_le_bytes(padded);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_3_be(bytes: [u8; 3]) -> Self {
+        let padded = [bytes[0], bytes[1], bytes[2], 0];
+        let intvalue = i32::from_be_bytes(padded);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_4_le(bytes: [u8; 4]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2]];
+        let intvalue = i32::from_le_bytes(padded);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_4_be(bytes: [u8; 4]) -> Self {
+        let padded = [bytes[1], bytes[2], bytes[3], 0];
+        let intvalue = i32::from_be_bytes(padded);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_4_msb_le(bytes: [u8; 4]) -> Self {
+        let padded = [0, bytes[1], bytes[2], bytes[3]];
+        let intvalue = i32::from_le_bytes(padded);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    #[inline]
+    fn from_s24_4_msb_be(bytes: [u8; 4]) -> Self {
+        let padded = [bytes[0], bytes[1], bytes[2], 0];
+        let intvalue = i32::from_be_bytes(padded);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    fn from_s20_3_le(bytes: [u8; 3]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2]];
+        let intvalue = (i32::from_le_bytes(padded) << 4) >> 12;
+        intvalue as f32 / f32::MAX_I20
+    }
+
+    fn from_s20_3_be(bytes: [u8; 3]) -> Self {
+        let padded = [bytes[0], bytes[1], bytes[2], 0];
+        let intvalue = (i32::from_be_bytes(padded) << 4) >> 12;
+        intvalue as f32 / f32::MAX_I20
+    }
+
+    fn from_s18_3_le(bytes: [u8; 3]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2]];
+        let intvalue = (i32::from_le_bytes(padded) << 6) >> 14;
+        intvalue as f32 / f32::MAX_I18
+    }
+
+    fn from_s18_3_be(bytes: [u8; 3]) -> Self {
+        let padded = [bytes[0], bytes[1], bytes[2], 0];
+        let intvalue = (i32::from_be_bytes(padded) << 6) >> 14;
+        intvalue as f32 / f32::MAX_I18
+    }
+
+    fn from_u32_le(bytes: [u8; 4]) -> Self {
+        let intvalue = (u32::from_le_bytes(bytes) ^ 0x8000_0000) as i32;
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    fn from_u32_be(bytes: [u8; 4]) -> Self {
+        let intvalue = (u32::from_be_bytes(bytes) ^ 0x8000_0000) as i32;
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    fn from_u24_3_le(bytes: [u8; 3]) -> Self {
+        let padded = [0, bytes[0], bytes[1], bytes[2] ^ 0x80];
+        let intvalue = i32::from_le_bytes(padded);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    fn from_u24_3_be(bytes: [u8; 3]) -> Self {
+        let padded = [bytes[0] ^ 0x80, bytes[1], bytes[2], 0];
+        let intvalue = i32::from_be_bytes(padded);
+        intvalue as f32 / f32::MAX_I32
+    }
+
+    fn from_u16_le(bytes: [u8; 2]) -> Self {
+        let intvalue = (u16::from_le_bytes(bytes) ^ 0x8000) as i16;
+        f32::from(intvalue) / f32::MAX_I16
+    }
+
+    fn from_u16_be(bytes: [u8; 2]) -> Self {
+        let intvalue = (u16::from_be_bytes(bytes) ^ 0x8000) as i16;
+        f32::from(intvalue) / f32::MAX_I16
+    }
+
+    fn from_u8(bytes: [u8; 1]) -> Self {
+        let intvalue = (bytes[0] ^ 0x80) as i8;
+        f32::from(intvalue) / f32::MAX_I8
+    }
+
+    fn from_alaw(bytes: [u8; 1]) -> Self {
+        let intvalue = alaw_to_linear(bytes[0]);
+        f32::from(intvalue) / f32::MAX_I16
+    }
+
+    fn from_mulaw(bytes: [u8; 1]) -> Self {
+        let intvalue = mulaw_to_linear(bytes[0]);
+        f32::from(intvalue) / f32::MAX_I16
+    }
+
+    fn from_fixed(bytes: [u8; 8], format: &FixedPoint) -> Self {
+        let intvalue = format.decode(bytes);
+        intvalue as f32 / format.scale::<f32>()
+    }
+
+    fn from_f32_le(bytes: [u8; 4]) -> Self {
+        f32::from_le_bytes(bytes)
+    }
+
+    fn from_f32_be(bytes: [u8; 4]) -> Self {
+        f32::from_be_bytes(bytes)
+    }
+
+    fn from_f64_le(bytes: [u8; 8]) -> Self {
+        f64::from_le_bytes(bytes) as f32
+    }
+
+    fn from_f64_be(bytes: [u8; 8]) -> Self {
+        f64::from_be_bytes(bytes) as f32
+    }
+
+    fn from_f16_le(bytes: [u8; 2]) -> Self {
+        f16_bits_to_f32(u16::from_le_bytes(bytes))
+    }
+
+    fn from_f16_be(bytes: [u8; 2]) -> Self {
+        f16_bits_to_f32(u16::from_be_bytes(bytes))
+    }
+
+    fn from_bf16_le(bytes: [u8; 2]) -> Self {
+        bf16_bits_to_f32(u16::from_le_bytes(bytes))
+    }
+
+    fn from_bf16_be(bytes: [u8; 2]) -> Self {
+        bf16_bits_to_f32(u16::from_be_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::excessive_precision)]
+mod tests {
+    use crate::dither::{Dither, DitherKind};
+    use crate::ClipPolicy;
+    use crate::Error;
+    use crate::FixedPoint;
+    use crate::Quantization;
+    use crate::ReadOptions;
+    use crate::Sample;
+    use crate::SampleFormat;
+    use crate::SampleReader;
+    use crate::SampleWriter;
+    use crate::Scaling;
+    use crate::WriteOptions;
+    use crate::{bf16_bits_to_f32, f16_bits_to_f32, f32_to_bf16_bits, f32_to_f16_bits};
+    use std::convert::TryInto;
+
+    // -------------------
+    //  single values f64
+    // -------------------
+
+    #[test]
+    fn check_f64_to_s64le() {
+        let val: f64 = 0.256789;
+        assert_eq!(
+            val.to_s64_le(),
+            ([0, 82, 124, 124, 66, 118, 222, 32], false)
+        );
+        let val: f64 = -0.256789;
+        assert_eq!(
+            val.to_s64_le(),
+            ([0, 174, 131, 131, 189, 137, 33, 223], false)
+        );
+        let val: f64 = 1.1;
+        assert_eq!(
+            val.to_s64_le(),
+            ([255, 255, 255, 255, 255, 255, 255, 127], true)
+        );
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s64_le(), ([0, 0, 0, 0, 0, 0, 0, 128], true));
+    }
+
+    #[test]
+    fn check_f64_to_s64le_full_scale() {
+        // +1.0 is one step above the largest value, and is clipped
+        let val: f64 = 1.0;
+        assert_eq!(
+            val.to_s64_le(),
+            ([255, 255, 255, 255, 255, 255, 255, 127], true)
+        );
+        let val: f32 = 1.0;
+        assert_eq!(
+            val.to_s64_le(),
+            ([255, 255, 255, 255, 255, 255, 255, 127], true)
+        );
+        let val: f64 = -1.0;
+        assert_eq!(val.to_s64_le(), ([0, 0, 0, 0, 0, 0, 0, 128], false));
+    }
+
+    #[test]
+    fn check_f64_from_s64le() {
+        let data = [0, 0, 0, 0, 0, 0, 64, 32];
+        assert_eq!(f64::from_s64_le(data), 0.251953125);
+        let data = [0, 0, 0, 0, 0, 0, 0, 128];
+        assert_eq!(f64::from_s64_le(data), -1.0);
+        // the lowest bits are lost when converting to f64
+        let data = [1, 0, 0, 0, 0, 0, 64, 32];
+        assert_eq!(f64::from_s64_le(data), 0.251953125);
+    }
+
+    #[test]
+    fn check_f64_to_s64be() {
+        let val: f64 = 0.256789;
+        assert_eq!(
+            val.to_s64_be(),
+            ([32, 222, 118, 66, 124, 124, 82, 0], false)
+        );
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s64_be(), ([128, 0, 0, 0, 0, 0, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f64_from_s64be() {
+        let data = [223, 64, 0, 0, 0, 0, 0, 0];
+        assert_eq!(f64::from_s64_be(data), -0.255859375);
+    }
+
+    #[test]
+    fn check_f64_to_s32le() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s32_le(), ([66, 118, 222, 32], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s32_le(), ([190, 137, 33, 223], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s32_le(), ([255, 255, 255, 127], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s32_le(), ([0, 0, 0, 128], true));
+    }
+
+    #[test]
+    fn check_f64_from_s32le() {
+        let data = [0, 0, 64, 32];
+        assert_eq!(f64::from_s32_le(data), 0.251953125);
+        let data = [0, 0, 64, 223];
+        assert_eq!(f64::from_s32_le(data), -0.255859375);
+        let data = [0, 0, 0, 128];
+        assert_eq!(f64::from_s32_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_to_s32be() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s32_be(), ([32, 222, 118, 66], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s32_be(), ([223, 33, 137, 190], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s32_be(), ([127, 255, 255, 255], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s32_be(), ([128, 0, 0, 0], true));
+    }
+
+
+    #[test]
+    fn check_f64_from_s32be() {
+        let data = [32, 64, 0, 0];
+        assert_eq!(f64::from_s32_be(data), 0.251953125);
+        let data = [223, 64, 0, 0];
+        assert_eq!(f64::from_s32_be(data), -0.255859375);
+        let data = [128, 0, 0, 0];
+        assert_eq!(f64::from_s32_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_to_s243le() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s24_3_le(), ([118, 222, 32], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s24_3_le(), ([138, 33, 223], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s24_3_le(), ([255, 255, 127], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s24_3_le(), ([0, 0, 128], true));
+    }
+
+    #[test]
+    fn check_f64_to_s243be() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s24_3_be(), ([32, 222, 118], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s24_3_be(), ([223, 33, 138], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s24_3_be(), ([127, 255, 255], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s24_3_be(), ([128, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f64_from_s243le() {
+        let data = [0, 64, 32];
+        assert_eq!(f64::from_s24_3_le(data), 0.251953125);
+        let data = [0, 64, 223];
+        assert_eq!(f64::from_s24_3_le(data), -0.255859375);
+        let data = [0, 0, 128];
+        assert_eq!(f64::from_s24_3_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_from_s243be() {
+        let data = [32, 64, 0];
+        assert_eq!(f64::from_s24_3_be(data), 0.251953125);
+        let data = [223, 64, 0];
+        assert_eq!(f64::from_s24_3_be(data), -0.255859375);
+        let data = [128, 0, 0];
+        assert_eq!(f64::from_s24_3_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_from_s244le() {
+        let data = [0, 64, 32, 0];
+        assert_eq!(f64::from_s24_4_le(data), 0.251953125);
+        let data = [0, 64, 223, 0];
+        assert_eq!(f64::from_s24_4_le(data), -0.255859375);
+        let data = [0, 0, 128, 0];
+        assert_eq!(f64::from_s24_4_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_from_s244be() {
+        let data = [0, 32, 64, 0];
+        assert_eq!(f64::from_s24_4_be(data), 0.251953125);
+        let data = [0, 223, 64, 0];
+        assert_eq!(f64::from_s24_4_be(data), -0.255859375);
+        let data = [0, 128, 0, 0];
+        assert_eq!(f64::from_s24_4_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_to_s244le() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s24_4_le(), ([118, 222, 32, 0], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s24_4_le(), ([138, 33, 223, 0], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s24_4_le(), ([255, 255, 127, 0], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s24_4_le(), ([0, 0, 128, 0], true));
+    }
+
+    #[test]
+    fn check_f64_to_s244be() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s24_4_be(), ([0, 32, 222, 118], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s24_4_be(), ([0, 223, 33, 138], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s24_4_be(), ([0, 127, 255, 255], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s24_4_be(), ([0, 128, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f64_from_s244msble() {
+        let data = [0, 0, 64, 32];
+        assert_eq!(f64::from_s24_4_msb_le(data), 0.251953125);
+        let data = [0, 0, 64, 223];
+        assert_eq!(f64::from_s24_4_msb_le(data), -0.255859375);
+        // the padding byte is ignored
+        let data = [255, 0, 0, 128];
+        assert_eq!(f64::from_s24_4_msb_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_from_s244msbbe() {
+        let data = [32, 64, 0, 0];
+        assert_eq!(f64::from_s24_4_msb_be(data), 0.251953125);
+        let data = [223, 64, 0, 0];
+        assert_eq!(f64::from_s24_4_msb_be(data), -0.255859375);
+        // the padding byte is ignored
+        let data = [128, 0, 0, 255];
+        assert_eq!(f64::from_s24_4_msb_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_to_s244msble() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s24_4_msb_le(), ([0, 118, 222, 32], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s24_4_msb_le(), ([0, 138, 33, 223], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s24_4_msb_le(), ([0, 255, 255, 127], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s24_4_msb_le(), ([0, 0, 0, 128], true));
+    }
+
+    #[test]
+    fn check_f64_to_s244msbbe() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s24_4_msb_be(), ([32, 222, 118, 0], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s24_4_msb_be(), ([223, 33, 138, 0], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s24_4_msb_be(), ([127, 255, 255, 0], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s24_4_msb_be(), ([128, 0, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f64_to_s203le() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s20_3_le(), ([231, 13, 2], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s20_3_le(), ([25, 242, 253], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s20_3_le(), ([255, 255, 7], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s20_3_le(), ([0, 0, 248], true));
+    }
+
+    #[test]
+    fn check_f64_to_s203be() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s20_3_be(), ([2, 13, 231], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s20_3_be(), ([253, 242, 25], false));
+    }
+
+    #[test]
+    fn check_f64_from_s203le() {
+        let data = [0, 0, 2];
+        assert_eq!(f64::from_s20_3_le(data), 0.25);
+        let data = [0, 0, 8];
+        assert_eq!(f64::from_s20_3_le(data), -1.0);
+        // the padding bits are ignored
+        let data = [0, 0, 242];
+        assert_eq!(f64::from_s20_3_le(data), 0.25);
+        let data = [0, 0, 254];
+        assert_eq!(f64::from_s20_3_le(data), -0.25);
+    }
+
+    #[test]
+    fn check_f64_from_s203be() {
+        let data = [2, 0, 0];
+        assert_eq!(f64::from_s20_3_be(data), 0.25);
+        let data = [8, 0, 0];
+        assert_eq!(f64::from_s20_3_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_to_s183le() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s18_3_le(), ([122, 131, 0], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s18_3_le(), ([134, 124, 255], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s18_3_le(), ([255, 255, 1], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s18_3_le(), ([0, 0, 254], true));
+    }
+
+    #[test]
+    fn check_f64_from_s183be() {
+        let data = [0, 128, 0];
+        assert_eq!(f64::from_s18_3_be(data), 0.25);
+        let data = [2, 0, 0];
+        assert_eq!(f64::from_s18_3_be(data), -1.0);
+        // the padding bits are ignored
+        let data = [252, 128, 0];
+        assert_eq!(f64::from_s18_3_be(data), 0.25);
+    }
+
+    #[test]
+    fn check_f64_to_s16le() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s16_le(), ([222, 32], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s16_le(), ([34, 223], false));
+    }
+
+    #[test]
+    fn check_f64_to_s16be() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s16_be(), ([32, 222], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s16_be(), ([223, 34], false));
+    }
+
+    #[test]
+    fn check_f64_to_s8() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s8(), ([33], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_s8(), ([223], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s8(), ([127], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_s8(), ([128], true));
+    }
+
+    #[test]
+    fn check_f64_from_s8() {
+        assert_eq!(f64::from_s8([32]), 0.25);
+        assert_eq!(f64::from_s8([224]), -0.25);
+        assert_eq!(f64::from_s8([128]), -1.0);
+    }
+
+    #[test]
+    fn check_f64_to_s12packedle() {
+        let val: f64 = 0.25;
+        assert_eq!(val.to_s12_packed_le(&-0.5), ([0x00, 0x02, 0xC0], 0));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s12_packed_le(&-1.1), ([0xFF, 0x07, 0x80], 2));
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_s12_packed_le(&-0.256789), ([0x0E, 0x22, 0xDF], 0));
+    }
+
+    #[test]
+    fn check_f64_to_s12packedbe() {
+        let val: f64 = 0.25;
+        assert_eq!(val.to_s12_packed_be(&-0.5), ([0x20, 0x0C, 0x00], 0));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_s12_packed_be(&0.0), ([0x7F, 0xF0, 0x00], 1));
+    }
+
+    #[test]
+    fn check_f64_from_s12packedle() {
+        assert_eq!(f64::from_s12_packed_le([0x00, 0x02, 0xC0]), [0.25, -0.5]);
+        assert_eq!(f64::from_s12_packed_le([0x00, 0x08, 0x80]), [-1.0, -1.0]);
+    }
+
+    #[test]
+    fn check_f64_from_s12packedbe() {
+        assert_eq!(f64::from_s12_packed_be([0x20, 0x0C, 0x00]), [0.25, -0.5]);
+        assert_eq!(f64::from_s12_packed_be([0x80, 0x08, 0x00]), [-1.0, -1.0]);
+    }
+
+    #[test]
+    fn check_f64_to_f32le() {
+        let val: f64 = 0.256789;
+        let exp = 0.256789_f32.to_le_bytes();
+        assert_eq!(val.to_f32_le(), (exp, false));
+        let val: f64 = -0.256789;
+        let exp = (-0.256789_f32).to_le_bytes();
+        assert_eq!(val.to_f32_le(), (exp, false));
+        let val: f64 = 1.1;
+        let exp = 1.0_f32.to_le_bytes();
+        assert_eq!(val.to_f32_le(), (exp, true));
+        let val: f64 = -1.1;
+        let exp = (-1.0_f32).to_le_bytes();
+        assert_eq!(val.to_f32_le(), (exp, true));
+    }
+
+    #[test]
+    fn check_f64_to_f32be() {
+        let val: f64 = 0.256789;
+        let exp = 0.256789_f32.to_be_bytes();
+        assert_eq!(val.to_f32_be(), (exp, false));
+        let val: f64 = -0.256789;
+        let exp = (-0.256789_f32).to_be_bytes();
+        assert_eq!(val.to_f32_be(), (exp, false));
+        let val: f64 = 1.1;
+        let exp = 1.0_f32.to_be_bytes();
+        assert_eq!(val.to_f32_be(), (exp, true));
+        let val: f64 = -1.1;
+        let exp = (-1.0_f32).to_be_bytes();
+        assert_eq!(val.to_f32_be(), (exp, true));
+    }
+
+    #[test]
+    fn check_f64_to_f64le() {
+        let val: f64 = 0.256789;
+        let exp = 0.256789_f64.to_le_bytes();
+        assert_eq!(val.to_f64_le(), (exp, false));
+        let val: f64 = -0.256789;
+        let exp = (-0.256789_f64).to_le_bytes();
+        assert_eq!(val.to_f64_le(), (exp, false));
+        let val: f64 = 1.1;
+        let exp = 1.0_f64.to_le_bytes();
+        assert_eq!(val.to_f64_le(), (exp, true));
+        let val: f64 = -1.1;
+        let exp = (-1.0_f64).to_le_bytes();
+        assert_eq!(val.to_f64_le(), (exp, true));
+    }
+
+    #[test]
+    fn check_f64_to_f64be() {
+        let val: f64 = 0.256789;
+        let exp = 0.256789_f64.to_be_bytes();
+        assert_eq!(val.to_f64_be(), (exp, false));
+        let val: f64 = -0.256789;
+        let exp = (-0.256789_f64).to_be_bytes();
+        assert_eq!(val.to_f64_be(), (exp, false));
+        let val: f64 = 1.1;
+        let exp = 1.0_f64.to_be_bytes();
+        assert_eq!(val.to_f64_be(), (exp, true));
+        let val: f64 = -1.1;
+        let exp = (-1.0_f64).to_be_bytes();
+        assert_eq!(val.to_f64_be(), (exp, true));
+    }
+
+    #[test]
+    fn check_f64_to_u32le() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_u32_le(), ([66, 118, 222, 160], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_u32_le(), ([190, 137, 33, 95], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_u32_le(), ([255, 255, 255, 255], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_u32_le(), ([0, 0, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f64_from_u32le() {
+        let data = [0, 0, 64, 160];
+        assert_eq!(f64::from_u32_le(data), 0.251953125);
+        let data = [0, 0, 64, 95];
+        assert_eq!(f64::from_u32_le(data), -0.255859375);
+        let data = [0, 0, 0, 0];
+        assert_eq!(f64::from_u32_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_to_u32be() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_u32_be(), ([160, 222, 118, 66], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_u32_be(), ([95, 33, 137, 190], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_u32_be(), ([255, 255, 255, 255], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_u32_be(), ([0, 0, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f64_from_u32be() {
+        let data = [160, 64, 0, 0];
+        assert_eq!(f64::from_u32_be(data), 0.251953125);
+        let data = [95, 64, 0, 0];
+        assert_eq!(f64::from_u32_be(data), -0.255859375);
+        let data = [128, 0, 0, 0];
+        assert_eq!(f64::from_u32_be(data), 0.0);
+    }
+
+    #[test]
+    fn check_f64_to_u243le() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_u24_3_le(), ([118, 222, 160], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_u24_3_le(), ([138, 33, 95], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_u24_3_le(), ([255, 255, 255], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_u24_3_le(), ([0, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f64_to_u243be() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_u24_3_be(), ([160, 222, 118], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_u24_3_be(), ([95, 33, 138], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_u24_3_be(), ([255, 255, 255], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_u24_3_be(), ([0, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f64_from_u243le() {
+        let data = [0, 64, 160];
+        assert_eq!(f64::from_u24_3_le(data), 0.251953125);
+        let data = [0, 64, 95];
+        assert_eq!(f64::from_u24_3_le(data), -0.255859375);
+        let data = [0, 0, 0];
+        assert_eq!(f64::from_u24_3_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_from_u243be() {
+        let data = [160, 64, 0];
+        assert_eq!(f64::from_u24_3_be(data), 0.251953125);
+        let data = [95, 64, 0];
+        assert_eq!(f64::from_u24_3_be(data), -0.255859375);
+        let data = [128, 0, 0];
+        assert_eq!(f64::from_u24_3_be(data), 0.0);
+    }
+
+    #[test]
+    fn check_f64_to_u16le() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_u16_le(), ([222, 160], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_u16_le(), ([34, 95], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_u16_le(), ([255, 255], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_u16_le(), ([0, 0], true));
+    }
+
+    #[test]
+    fn check_f64_to_u16be() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_u16_be(), ([160, 222], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_u16_be(), ([95, 34], false));
+    }
+
+    #[test]
+    fn check_f64_from_u16le() {
+        let data = [0, 160];
+        assert_eq!(f64::from_u16_le(data), 0.25);
+        let data = [0, 128];
+        assert_eq!(f64::from_u16_le(data), 0.0);
+        let data = [0, 0];
+        assert_eq!(f64::from_u16_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_from_u16be() {
+        let data = [160, 0];
+        assert_eq!(f64::from_u16_be(data), 0.25);
+        let data = [128, 0];
+        assert_eq!(f64::from_u16_be(data), 0.0);
+        let data = [0, 0];
+        assert_eq!(f64::from_u16_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f64_to_u8() {
+        let val: f64 = 0.256789;
+        assert_eq!(val.to_u8(), ([161], false));
+        let val: f64 = -0.256789;
+        assert_eq!(val.to_u8(), ([95], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_u8(), ([255], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_u8(), ([0], true));
+    }
+
+    #[test]
+    fn check_f64_from_u8() {
+        assert_eq!(f64::from_u8([160]), 0.25);
+        assert_eq!(f64::from_u8([128]), 0.0);
+        assert_eq!(f64::from_u8([0]), -1.0);
+    }
+
+    #[test]
+    fn check_f64_to_alaw() {
+        let val: f64 = 0.0;
+        assert_eq!(val.to_alaw(), ([0xD5], false));
+        let val: f64 = 0.5;
+        assert_eq!(val.to_alaw(), ([0xA5], false));
+        let val: f64 = -0.5;
+        assert_eq!(val.to_alaw(), ([0x3A], false));
+        let val: f64 = -0.515625;
+        assert_eq!(val.to_alaw(), ([0x25], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_alaw(), ([0xAA], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_alaw(), ([0x2A], true));
+    }
+
+    #[test]
+    fn check_f64_from_alaw() {
+        assert_eq!(f64::from_alaw([0xD5]), 8.0 / 32768.0);
+        assert_eq!(f64::from_alaw([0xA5]), 0.515625);
+        assert_eq!(f64::from_alaw([0x25]), -0.515625);
+        assert_eq!(f64::from_alaw([0xAA]), 32256.0 / 32768.0);
+        assert_eq!(f64::from_alaw([0x2A]), -32256.0 / 32768.0);
+    }
+
+    #[test]
+    fn check_f64_to_mulaw() {
+        let val: f64 = 0.0;
+        assert_eq!(val.to_mulaw(), ([0xFF], false));
+        let val: f64 = 0.5;
+        assert_eq!(val.to_mulaw(), ([0x8F], false));
+        let val: f64 = -0.5;
+        assert_eq!(val.to_mulaw(), ([0x0F], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_mulaw(), ([0x80], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_mulaw(), ([0x00], true));
+    }
+
+    #[test]
+    fn check_f64_from_mulaw() {
+        assert_eq!(f64::from_mulaw([0xFF]), 0.0);
+        assert_eq!(f64::from_mulaw([0x8F]), 16764.0 / 32768.0);
+        assert_eq!(f64::from_mulaw([0x0F]), -16764.0 / 32768.0);
+        assert_eq!(f64::from_mulaw([0x80]), 32124.0 / 32768.0);
+        assert_eq!(f64::from_mulaw([0x00]), -32124.0 / 32768.0);
+    }
+
+    #[test]
+    fn check_f64_to_f16le() {
+        let val: f64 = 0.5;
+        assert_eq!(val.to_f16_le(), ([0x00, 0x38], false));
+        let val: f64 = -0.25;
+        assert_eq!(val.to_f16_le(), ([0x00, 0xB4], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_f16_le(), ([0x00, 0x3C], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_f16_le(), ([0x00, 0xBC], true));
+    }
+
+    #[test]
+    fn check_f64_from_f16be() {
+        assert_eq!(f64::from_f16_be([0x38, 0x00]), 0.5);
+        assert_eq!(f64::from_f16_be([0xB4, 0x00]), -0.25);
+        assert_eq!(f64::from_f16_be([0x34, 0x01]), 0.250244140625);
+    }
+
+    #[test]
+    fn check_f64_to_bf16be() {
+        let val: f64 = 0.5;
+        assert_eq!(val.to_bf16_be(), ([0x3F, 0x00], false));
+        let val: f64 = -0.25;
+        assert_eq!(val.to_bf16_be(), ([0xBE, 0x80], false));
+        let val: f64 = 1.1;
+        assert_eq!(val.to_bf16_be(), ([0x3F, 0x80], true));
+        let val: f64 = -1.1;
+        assert_eq!(val.to_bf16_be(), ([0xBF, 0x80], true));
+    }
+
+    #[test]
+    fn check_f64_from_bf16le() {
+        assert_eq!(f64::from_bf16_le([0x00, 0x3F]), 0.5);
+        assert_eq!(f64::from_bf16_le([0x80, 0xBE]), -0.25);
+        assert_eq!(f64::from_bf16_le([0x81, 0x3E]), 0.251953125);
+    }
+
+    #[test]
+    fn check_f64_to_fixed() {
+        let format = FixedPoint::le(8, 23, 4).unwrap();
+        let val: f64 = 2.5;
+        assert_eq!(val.to_fixed(&format).0[..4], [0, 0, 64, 1]);
+        assert!(!val.to_fixed(&format).1);
+        let val: f64 = -2.5;
+        assert_eq!(val.to_fixed(&format), ([0, 0, 192, 254, 0, 0, 0, 0], false));
+        // the headroom is limited by the integer bits
+        let val: f64 = 200.0;
+        assert_eq!(
+            val.to_fixed(&format),
+            ([255, 255, 255, 63, 0, 0, 0, 0], true)
+        );
+        let val: f64 = -200.0;
+        assert_eq!(val.to_fixed(&format), ([0, 0, 0, 192, 0, 0, 0, 0], true));
+        let format = FixedPoint::be(9, 23, 4).unwrap();
+        let val: f64 = 200.0;
+        assert_eq!(val.to_fixed(&format), ([100, 0, 0, 0, 0, 0, 0, 0], false));
+        let val: f64 = 300.0;
+        assert_eq!(
+            val.to_fixed(&format),
+            ([127, 255, 255, 255, 0, 0, 0, 0], true)
+        );
+    }
+
+    #[test]
+    fn check_f64_from_fixed() {
+        let format = FixedPoint::le(8, 23, 4).unwrap();
+        let data = [0, 0, 64, 1, 0, 0, 0, 0];
+        assert_eq!(f64::from_fixed(data, &format), 2.5);
+        let data = [0, 0, 192, 254, 0, 0, 0, 0];
+        assert_eq!(f64::from_fixed(data, &format), -2.5);
+        // the unused upper bit is ignored
+        let data = [0, 0, 64, 129, 0, 0, 0, 0];
+        assert_eq!(f64::from_fixed(data, &format), 2.5);
+        let format = FixedPoint::be(1, 31, 4).unwrap();
+        let data = [128, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(f64::from_fixed(data, &format), -1.0);
+        let data = [32, 64, 0, 0, 0, 0, 0, 0];
+        assert_eq!(f64::from_fixed(data, &format), 0.251953125);
+    }
+
+    // -------------------
+    //  single values f32
+    // -------------------
+    #[test]
+    fn check_f32_to_s64le() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s64_le(), ([0, 0, 0, 0, 64, 118, 222, 32], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s64_le(), ([0, 0, 0, 0, 192, 137, 33, 223], false));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s64_le(), ([0, 0, 0, 0, 0, 0, 0, 128], true));
+    }
+
+    #[test]
+    fn check_f32_from_s64be() {
+        let data = [32, 64, 0, 0, 0, 0, 0, 0];
+        assert_eq!(f32::from_s64_be(data), 0.251953125);
+        let data = [128, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(f32::from_s64_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f32_to_s32le() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s32_le(), ([64, 118, 222, 32], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s32_le(), ([192, 137, 33, 223], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_s32_le(), ([255, 255, 255, 127], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s32_le(), ([0, 0, 0, 128], true));
+    }
+
+    #[test]
+    fn check_f32_from_s32le() {
+        let data = [0, 0, 64, 32];
+        assert_eq!(f32::from_s32_le(data), 0.251953125);
+        let data = [0, 0, 64, 223];
+        assert_eq!(f32::from_s32_le(data), -0.255859375);
+        let data = [0, 0, 0, 128];
+        assert_eq!(f32::from_s32_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f32_to_s32be() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s32_be(), ([32, 222, 118, 64], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s32_be(), ([223, 33, 137, 192], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_s32_be(), ([127, 255, 255, 255], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s32_be(), ([128, 0, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f32_from_s32be() {
+        let data = [32, 64, 0, 0];
+        assert_eq!(f32::from_s32_be(data), 0.251953125);
+        let data = [223, 64, 0, 0];
+        assert_eq!(f32::from_s32_be(data), -0.255859375);
+        let data = [128, 0, 0, 0];
+        assert_eq!(f32::from_s32_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f32_to_s243le() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s24_3_le(), ([118, 222, 32], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s24_3_le(), ([138, 33, 223], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_s24_3_le(), ([255, 255, 127], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s24_3_le(), ([0, 0, 128], true));
+    }
+
+    #[test]
+    fn check_f32_to_s243be() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s24_3_be(), ([32, 222, 118], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s24_3_be(), ([223, 33, 138], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_s24_3_be(), ([127, 255, 255], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s24_3_be(), ([128, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f32_from_s243le() {
+        let data = [0, 64, 32];
+        assert_eq!(f32::from_s24_3_le(data), 0.251953125);
+        let data = [0, 64, 223];
+        assert_eq!(f32::from_s24_3_le(data), -0.255859375);
+        let data = [0, 0, 128];
+        assert_eq!(f32::from_s24_3_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f32_from_s243be() {
+        let data = [32, 64, 0];
+        assert_eq!(f32::from_s24_3_be(data), 0.251953125);
+        let data = [223, 64, 0];
+        assert_eq!(f32::from_s24_3_be(data), -0.255859375);
+        let data = [128, 0, 0];
+        assert_eq!(f32::from_s24_3_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f32_from_s244le() {
+        let data = [0, 64, 32, 0];
+        assert_eq!(f32::from_s24_4_le(data), 0.251953125);
+        let data = [0, 64, 223, 0];
+        assert_eq!(f32::from_s24_4_le(data), -0.255859375);
+        let data = [0, 0, 128, 0];
+        assert_eq!(f32::from_s24_4_le(data), -1.0);
+    }
+
+    #[test]
+    fn check_f32_from_s244be() {
+        let data = [0, 32, 64, 0];
+        assert_eq!(f32::from_s24_4_be(data), 0.251953125);
+        let data = [0, 223, 64, 0];
+        assert_eq!(f32::from_s24_4_be(data), -0.255859375);
+        let data = [0, 128, 0, 0];
+        assert_eq!(f32::from_s24_4_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f32_to_s244le() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s24_4_le(), ([118, 222, 32, 0], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s24_4_le(), ([138, 33, 223, 0], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_s24_4_le(), ([255, 255, 127, 0], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s24_4_le(), ([0, 0, 128, 0], true));
+    }
 
-    fn to_s16_le(&self) -> ([u8; 2], bool) {
-        let val = self * f32::MAX_I16;
-        let (val, clipped) = clamp_int::<f32, i16>(val);
-        ((val as i16).to_le_bytes(), clipped)
+    #[test]
+    fn check_f32_to_s244be() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s24_4_be(), ([0, 32, 222, 118], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s24_4_be(), ([0, 223, 33, 138], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_s24_4_be(), ([0, 127, 255, 255], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s24_4_be(), ([0, 128, 0, 0], true));
     }
 
-    fn to_s16_be(&self) -> ([u8; 2], bool) {
-        let val = self * f32::MAX_I16;
-        let (val, clipped) = clamp_int::<f32, i16>(val);
-        ((val as i16).to_be_bytes(), clipped)
+    #[test]
+    fn check_f32_from_s244msble() {
+        let data = [0, 0, 64, 32];
+        assert_eq!(f32::from_s24_4_msb_le(data), 0.251953125);
+        let data = [255, 0, 0, 128];
+        assert_eq!(f32::from_s24_4_msb_le(data), -1.0);
     }
 
-    fn to_s32_le(&self) -> ([u8; 4], bool) {
-        let val = self * f32::MAX_I32;
-        let (val, clipped) = clamp_int::<f32, i32>(val);
-        ((val as i32).to_le_bytes(), clipped)
+    #[test]
+    fn check_f32_to_s244msbbe() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s24_4_msb_be(), ([32, 222, 118, 0], false));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s24_4_msb_be(), ([128, 0, 0, 0], true));
     }
 
-    fn to_s32_be(&self) -> ([u8; 4], bool) {
-        let val = self * f32::MAX_I32;
-        let (val, clipped) = clamp_int::<f32, i32>(val);
-        ((val as i32).to_be_bytes(), clipped)
+    #[test]
+    fn check_f32_to_s203le() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s20_3_le(), ([231, 13, 2], false));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s20_3_le(), ([0, 0, 248], true));
     }
 
-    fn to_s24_3_le(&self) -> ([u8; 3], bool) {
-        let val = self * f32::MAX_I32;
-        let (val, clipped) = clamp_int::<f32, i32>(val);
-        let bytes = (val as i32).to_le_bytes();
-        ([bytes[1], bytes[2], bytes[3]], clipped)
+    #[test]
+    fn check_f32_from_s183le() {
+        let data = [0, 128, 0];
+        assert_eq!(f32::from_s18_3_le(data), 0.25);
+        let data = [0, 0, 2];
+        assert_eq!(f32::from_s18_3_le(data), -1.0);
     }
 
-    fn to_s24_3_be(&self) -> ([u8; 3], bool) {
-        let val = self * f32::MAX_I32;
-        let (val, clipped) = clamp_int::<f32, i32>(val);
-        let bytes = (val as i32).to_be_bytes();
-        ([bytes[0], bytes[1], bytes[2]], clipped)
+    #[test]
+    fn check_f32_to_s16le() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s16_le(), ([222, 32], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s16_le(), ([34, 223], false));
     }
 
-    fn to_s24_4_le(&self) -> ([u8; 4], bool) {
-        let val = self * f32::MAX_I32;
-        let (val, clipped) = clamp_int::<f32, i32>(val);
-        let bytes = (val as i32).to_le_bytes();
-        ([bytes[1], bytes[2], bytes[3], 0], clipped)
+    #[test]
+    fn check_f32_to_s16be() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s16_be(), ([32, 222], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s16_be(), ([223, 34], false));
+    }    
+
+    #[test]
+    fn check_f32_to_s8() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_s8(), ([33], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_s8(), ([223], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_s8(), ([127], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s8(), ([128], true));
     }
 
-    fn to_s24_4_be(&self) -> ([u8; 4], bool) {
-        let val = self * f32::MAX_I32;
-        let (val, clipped) = clamp_int::<f32, i32>(val);
-        let bytes = (val as i32).to_be_bytes();
-        ([0, bytes[0], bytes[1], bytes[2]], clipped)
+    #[test]
+    fn check_f32_from_s8() {
+        assert_eq!(f32::from_s8([32]), 0.25);
+        assert_eq!(f32::from_s8([224]), -0.25);
+        assert_eq!(f32::from_s8([128]), -1.0);
     }
 
-    fn to_f64_le(&self) -> ([u8; 8], bool) {
-        let val = f64::from(*self);
-        let (val, clipped) = clamp_float(val);
-        (val.to_le_bytes(), clipped)
+    #[test]
+    fn check_f32_to_s12packedle() {
+        let val: f32 = 0.25;
+        assert_eq!(val.to_s12_packed_le(&-0.5), ([0x00, 0x02, 0xC0], 0));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_s12_packed_le(&1.1), ([0x00, 0xF8, 0x7F], 2));
     }
 
-    fn to_f64_be(&self) -> ([u8; 8], bool) {
-        let val = f64::from(*self);
-        let (val, clipped) = clamp_float(val);
-        (val.to_be_bytes(), clipped)
+    #[test]
+    fn check_f32_from_s12packedbe() {
+        assert_eq!(f32::from_s12_packed_be([0x20, 0x0C, 0x00]), [0.25, -0.5]);
     }
 
-    fn to_f32_le(&self) -> ([u8; 4], bool) {
-        let (val, clipped) = clamp_float(*self);
-        (val.to_le_bytes(), clipped)
+    #[test]
+    fn check_f32_to_f32le() {
+        let val: f32 = 0.256789;
+        let exp = 0.256789_f32.to_le_bytes();
+        assert_eq!(val.to_f32_le(), (exp, false));
+        let val: f32 = -0.256789;
+        let exp = (-0.256789_f32).to_le_bytes();
+        assert_eq!(val.to_f32_le(), (exp, false));
+        let val: f32 = 1.1;
+        let exp = 1.0_f32.to_le_bytes();
+        assert_eq!(val.to_f32_le(), (exp, true));
+        let val: f32 = -1.1;
+        let exp = (-1.0_f32).to_le_bytes();
+        assert_eq!(val.to_f32_le(), (exp, true));
     }
 
-    fn to_f32_be(&self) -> ([u8; 4], bool) {
-        let (val, clipped) = clamp_float(*self);
-        (val.to_be_bytes(), clipped)
+    #[test]
+    fn check_f32_to_f32be() {
+        let val: f32 = 0.256789;
+        let exp = 0.256789_f32.to_be_bytes();
+        assert_eq!(val.to_f32_be(), (exp, false));
+        let val: f32 = -0.256789;
+        let exp = (-0.256789_f32).to_be_bytes();
+        assert_eq!(val.to_f32_be(), (exp, false));
+        let val: f32 = 1.1;
+        let exp = 1.0_f32.to_be_bytes();
+        assert_eq!(val.to_f32_be(), (exp, true));
+        let val: f32 = -1.1;
+        let exp = (-1.0_f32).to_be_bytes();
+        assert_eq!(val.to_f32_be(), (exp, true));
     }
 
-    fn from_s32_le(bytes: [u8; 4]) -> Self {
-        let intvalue = i32::from_le_bytes(bytes);
-        intvalue as f32 / f32::MAX_I32
+    #[test]
+    fn check_f32_to_f64le() {
+        let val: f32 = 0.256789;
+        let exp = (0.256789_f32 as f64).to_le_bytes();
+        assert_eq!(val.to_f64_le(), (exp, false));
+        let val: f32 = -0.256789;
+        let exp = (-0.256789_f32 as f64).to_le_bytes();
+        assert_eq!(val.to_f64_le(), (exp, false));
+        let val: f32 = 1.1;
+        let exp = 1.0_f64.to_le_bytes();
+        assert_eq!(val.to_f64_le(), (exp, true));
+        let val: f32 = -1.1;
+        let exp = (-1.0_f64).to_le_bytes();
+        assert_eq!(val.to_f64_le(), (exp, true));
     }
 
-    fn from_s32_be(bytes: [u8; 4]) -> Self {
-        let intvalue = i32::from_be_bytes(bytes);
-        intvalue as f32 / f32::MAX_I32
+    #[test]
+    fn check_f32_to_f64be() {
+        let val: f32 = 0.256789;
+        let exp = (0.256789_f32 as f64).to_be_bytes();
+        assert_eq!(val.to_f64_be(), (exp, false));
+        let val: f32 = -0.256789;
+        let exp = (-0.256789_f32 as f64).to_be_bytes();
+        assert_eq!(val.to_f64_be(), (exp, false));
+        let val: f32 = 1.1;
+        let exp = 1.0_f64.to_be_bytes();
+        assert_eq!(val.to_f64_be(), (exp, true));
+        let val: f32 = -1.1;
+        let exp = (-1.0_f64).to_be_bytes();
+        assert_eq!(val.to_f64_be(), (exp, true));
     }
 
-    fn from_s16_le(bytes: [u8; 2]) -> Self {
-        let intvalue = i16::from_le_bytes(bytes);
-        f32::from(intvalue) / f32::MAX_I16
+    #[test]
+    fn check_f32_to_u32le() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_u32_le(), ([64, 118, 222, 160], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_u32_le(), ([192, 137, 33, 95], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_u32_le(), ([255, 255, 255, 255], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_u32_le(), ([0, 0, 0, 0], true));
     }
 
-    fn from_s16_be(bytes: [u8; 2]) -> Self {
-        let intvalue = i16::from_be_bytes(bytes);
-        f32::from(intvalue) / f32::MAX_I16
+    #[test]
+    fn check_f32_from_u32le() {
+        let data = [0, 0, 64, 160];
+        assert_eq!(f32::from_u32_le(data), 0.251953125);
+        let data = [0, 0, 64, 95];
+        assert_eq!(f32::from_u32_le(data), -0.255859375);
+        let data = [0, 0, 0, 0];
+        assert_eq!(f32::from_u32_le(data), -1.0);
     }
 
-    fn from_s24_3_le(bytes: [u8; 3]) -> Self {
-        let padded = [0, bytes[0], bytes[1], bytes[2]];
-        let intvalue = i32::from_le_bytes(padded);
-        intvalue as f32 / f32::MAX_I32
+    #[test]
+    fn check_f32_to_u243le() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_u24_3_le(), ([118, 222, 160], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_u24_3_le(), ([138, 33, 95], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_u24_3_le(), ([255, 255, 255], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_u24_3_le(), ([0, 0, 0], true));
     }
 
-    fn from_s24_3_be(bytes: [u8; 3]) -> Self {
-        let padded = [bytes[0], bytes[1], bytes[2], 0];
-        let intvalue = i32::from_be_bytes(padded);
-        intvalue as f32 / f32::MAX_I32
+    #[test]
+    fn check_f32_from_u243be() {
+        let data = [160, 64, 0];
+        assert_eq!(f32::from_u24_3_be(data), 0.251953125);
+        let data = [95, 64, 0];
+        assert_eq!(f32::from_u24_3_be(data), -0.255859375);
+        let data = [0, 0, 0];
+        assert_eq!(f32::from_u24_3_be(data), -1.0);
+    }
+
+    #[test]
+    fn check_f32_to_u16le() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_u16_le(), ([222, 160], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_u16_le(), ([34, 95], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_u16_le(), ([255, 255], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_u16_le(), ([0, 0], true));
+    }
+
+    #[test]
+    fn check_f32_to_u8() {
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_u8(), ([161], false));
+        let val: f32 = -0.256789;
+        assert_eq!(val.to_u8(), ([95], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_u8(), ([255], true));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_u8(), ([0], true));
+    }
+
+    #[test]
+    fn check_f32_from_u8() {
+        assert_eq!(f32::from_u8([160]), 0.25);
+        assert_eq!(f32::from_u8([128]), 0.0);
+        assert_eq!(f32::from_u8([0]), -1.0);
+    }
+
+    #[test]
+    fn check_f32_to_alaw() {
+        let val: f32 = 0.5;
+        assert_eq!(val.to_alaw(), ([0xA5], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_alaw(), ([0xAA], true));
+    }
+
+    #[test]
+    fn check_f32_to_mulaw() {
+        let val: f32 = 0.5;
+        assert_eq!(val.to_mulaw(), ([0x8F], false));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_mulaw(), ([0x00], true));
+    }
+
+    #[test]
+    fn check_f32_to_f16be() {
+        let val: f32 = 0.5;
+        assert_eq!(val.to_f16_be(), ([0x38, 0x00], false));
+        let val: f32 = 1.1;
+        assert_eq!(val.to_f16_be(), ([0x3C, 0x00], true));
+    }
+
+    #[test]
+    fn check_f32_to_bf16le() {
+        let val: f32 = -0.25;
+        assert_eq!(val.to_bf16_le(), ([0x80, 0xBE], false));
+        let val: f32 = -1.1;
+        assert_eq!(val.to_bf16_le(), ([0x80, 0xBF], true));
+    }
+
+    // ----------------
+    //  half precision
+    // ----------------
+
+    #[test]
+    fn check_f16_rounding() {
+        // ties round to even
+        assert_eq!(f32_to_f16_bits(1.0 + 1.0 / 2048.0), 0x3C00);
+        assert_eq!(f32_to_f16_bits(1.0 + 3.0 / 2048.0), 0x3C02);
+        assert_eq!(f32_to_f16_bits(1.0 + 1.1 / 2048.0), 0x3C01);
+        // subnormals
+        assert_eq!(f32_to_f16_bits(1.0 / 16777216.0), 0x0001);
+        assert_eq!(f32_to_f16_bits(0.5 / 16777216.0), 0x0000);
+        assert_eq!(f32_to_f16_bits(-1.5 / 16777216.0), 0x8002);
+        assert_eq!(f32_to_f16_bits(1023.5 / 16777216.0), 0x0400);
+        assert_eq!(f16_bits_to_f32(0x0001), 1.0 / 16777216.0);
+        assert_eq!(f16_bits_to_f32(0x83FF), -1023.0 / 16777216.0);
+    }
+
+    #[test]
+    fn check_f16_overflow_and_special() {
+        assert_eq!(f32_to_f16_bits(65504.0), 0x7BFF);
+        assert_eq!(f32_to_f16_bits(65519.0), 0x7BFF);
+        assert_eq!(f32_to_f16_bits(65520.0), 0x7C00);
+        assert_eq!(f32_to_f16_bits(-1.0e6), 0xFC00);
+        assert_eq!(f32_to_f16_bits(f32::INFINITY), 0x7C00);
+        assert!(f16_bits_to_f32(f32_to_f16_bits(f32::NAN)).is_nan());
+        assert_eq!(f16_bits_to_f32(0x7BFF), 65504.0);
+        assert_eq!(f16_bits_to_f32(0xFC00), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn check_bf16_rounding_and_special() {
+        assert_eq!(f32_to_bf16_bits(1.0 + 1.0 / 256.0), 0x3F80);
+        assert_eq!(f32_to_bf16_bits(1.0 + 3.0 / 256.0), 0x3F82);
+        assert_eq!(f32_to_bf16_bits(f32::MAX), 0x7F80);
+        assert_eq!(f32_to_bf16_bits(-f32::MAX), 0xFF80);
+        assert!(bf16_bits_to_f32(f32_to_bf16_bits(f32::NAN)).is_nan());
+        assert_eq!(bf16_bits_to_f32(0x3F80), 1.0);
+    }
+
+    #[test]
+    fn check_f32_to_fixed() {
+        let format = FixedPoint::be(1, 15, 2).unwrap();
+        let val: f32 = 0.256789;
+        assert_eq!(val.to_fixed(&format).0[..2], [32, 222]);
+        let val: f32 = -1.1;
+        assert_eq!(val.to_fixed(&format), ([128, 0, 0, 0, 0, 0, 0, 0], true));
+    }
+
+    #[test]
+    fn check_f32_from_fixed() {
+        let format = FixedPoint::le(9, 23, 4).unwrap();
+        let data = [0, 0, 64, 1, 0, 0, 0, 0];
+        assert_eq!(f32::from_fixed(data, &format), 2.5);
+        let data = [0, 0, 0, 128, 0, 0, 0, 0];
+        assert_eq!(f32::from_fixed(data, &format), -256.0);
+    }
+
+    #[test]
+    fn check_fixed_point_limits() {
+        assert!(FixedPoint::le(0, 15, 2).is_err());
+        assert!(FixedPoint::le(1, 15, 0).is_err());
+        assert!(FixedPoint::le(1, 15, 9).is_err());
+        assert!(FixedPoint::le(9, 24, 4).is_err());
+        assert!(FixedPoint::le(1, u32::MAX, 8).is_err());
+        assert!(FixedPoint::be(u32::MAX, 1, 8).is_err());
+        assert!(FixedPoint::le(1, 63, 8).is_ok());
+        let format = FixedPoint::be(1, 63, 8).unwrap();
+        let val: f64 = -1.0;
+        assert_eq!(val.to_fixed(&format), ([128, 0, 0, 0, 0, 0, 0, 0], false));
+        let val: f64 = 1.0;
+        assert_eq!(
+            val.to_fixed(&format),
+            ([127, 255, 255, 255, 255, 255, 255, 255], true)
+        );
+    }
+
+    #[test]
+    fn check_fixed_point_positive_full_scale() {
+        // the largest integer of these formats rounds up when converted to a float,
+        // positive full scale must still be clipped to it and not wrap around.
+        let format = FixedPoint::le(1, 31, 4).unwrap();
+        let val: f32 = 1.0;
+        assert_eq!(
+            val.to_fixed(&format),
+            ([255, 255, 255, 127, 0, 0, 0, 0], true)
+        );
+        let format = FixedPoint::le(8, 23, 4).unwrap();
+        let val: f32 = 128.0;
+        assert_eq!(
+            val.to_fixed(&format),
+            ([255, 255, 255, 63, 0, 0, 0, 0], true)
+        );
+        let format = FixedPoint::le(1, 62, 8).unwrap();
+        let val: f64 = 1.0;
+        assert_eq!(
+            val.to_fixed(&format),
+            ([255, 255, 255, 255, 255, 255, 255, 63], true)
+        );
+        let val: f64 = -1.0;
+        assert_eq!(val.to_fixed(&format), ([0, 0, 0, 0, 0, 0, 0, 192], false));
+    }
+
+    // -----------------
+    //  read/write many
+    // -----------------
+
+    #[test]
+    fn write_read_to_slice_s16le() {
+        // write data, then read it back into a slice of the same length.
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples(&values, &mut data, &SampleFormat::S16LE).unwrap();
+        let mut values2 = vec![0.0; 7];
+        let mut slice: &[u8] = &data;
+        f64::read_samples(&mut slice, &mut values2, &SampleFormat::S16LE).unwrap();
+        assert_eq!(values, values2);
+    }
+
+    #[test]
+    fn write_read_to_slice_s16be() {
+        // write data, then read it back into a slice of the same length.
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples(&values, &mut data, &SampleFormat::S16BE).unwrap();
+        let mut values2 = vec![0.0; 7];
+        let mut slice: &[u8] = &data;
+        f64::read_samples(&mut slice, &mut values2, &SampleFormat::S16BE).unwrap();
+        assert_eq!(values, values2);
+    }
+
+    #[test]
+    fn write_read_all_s32le() {
+        // write data, then read all of it back into a dynamically allocated vec.
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples(&values, &mut data, &SampleFormat::S32LE).unwrap();
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f64::read_all_samples(&mut slice, &mut values2, &SampleFormat::S32LE).unwrap();
+        assert_eq!(values, values2);
     }
 
-    fn from_s24_4_le(bytes: [u8; 4]) -> Self {
-        let padded = [0, bytes[0], bytes[1], bytes[2]];
-        let intvalue = i32::from_le_bytes(padded);
-        intvalue as f32 / f32::MAX_I32
+    #[test]
+    fn write_read_all_s32be() {
+        // write data, then read all of it back into a dynamically allocated vec.
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples(&values, &mut data, &SampleFormat::S32BE).unwrap();
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f64::read_all_samples(&mut slice, &mut values2, &SampleFormat::S32BE).unwrap();
+        assert_eq!(values, values2);
     }
 
-    fn from_s24_4_be(bytes: [u8; 4]) -> Self {
-        let padded = [bytes[1], bytes[2], bytes[3], 0];
-        let intvalue = i32::from_be_bytes(padded);
-        intvalue as f32 / f32::MAX_I32
+    #[test]
+    fn write_read_all_s8() {
+        // write data, then read all of it back into a dynamically allocated vec.
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        f32::write_samples(&values, &mut data, &SampleFormat::S8).unwrap();
+        assert_eq!(data, vec![192, 224, 240, 0, 16, 32, 64]);
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f32::read_all_samples(&mut slice, &mut values2, &SampleFormat::S8).unwrap();
+        assert_eq!(values, values2);
     }
 
-    fn from_f32_le(bytes: [u8; 4]) -> Self {
-        f32::from_le_bytes(bytes)
+    #[test]
+    fn write_read_all_s20le3() {
+        // the 20 bit format must not be scaled like 24 bit.
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples(&values, &mut data, &SampleFormat::S20LE3).unwrap();
+        assert_eq!(data[15..18], [0, 0, 2]);
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f64::read_all_samples(&mut slice, &mut values2, &SampleFormat::S20LE3).unwrap();
+        assert_eq!(values, values2);
     }
 
-    fn from_f32_be(bytes: [u8; 4]) -> Self {
-        f32::from_be_bytes(bytes)
+    #[test]
+    fn write_read_to_slice_u8() {
+        // write data, then read it back into a slice of the same length.
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        let clipped = f64::write_samples(&values, &mut data, &SampleFormat::U8).unwrap();
+        assert_eq!(clipped, 0);
+        assert_eq!(data, vec![64, 96, 112, 128, 144, 160, 192]);
+        let mut values2 = vec![0.0; 7];
+        let mut slice: &[u8] = &data;
+        f64::read_samples(&mut slice, &mut values2, &SampleFormat::U8).unwrap();
+        assert_eq!(values, values2);
     }
 
-    fn from_f64_le(bytes: [u8; 8]) -> Self {
-        f64::from_le_bytes(bytes) as f32
+    #[test]
+    fn write_read_all_u24be3() {
+        // write data, then read all of it back into a dynamically allocated vec.
+        let values = vec![-1.5, -0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5, 1.5];
+        let mut data: Vec<u8> = Vec::new();
+        let clipped = f64::write_samples(&values, &mut data, &SampleFormat::U24BE3).unwrap();
+        assert_eq!(clipped, 2);
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f64::read_all_samples(&mut slice, &mut values2, &SampleFormat::U24BE3).unwrap();
+        assert_eq!(values[1..8], values2[1..8]);
+        assert_eq!(values2[0], -1.0);
+        assert_eq!(values2[8], 8388607.0 / 8388608.0);
     }
 
-    fn from_f64_be(bytes: [u8; 8]) -> Self {
-        f64::from_be_bytes(bytes) as f32
+    #[test]
+    fn alaw_mulaw_roundtrip_all_codes() {
+        // every 8-bit code should survive a decode-encode round trip.
+        let codes: Vec<u8> = (0..=255).collect();
+        for format in [SampleFormat::ALaw, SampleFormat::MuLaw].iter() {
+            let mut values = Vec::new();
+            let mut slice: &[u8] = &codes;
+            f32::read_all_samples(&mut slice, &mut values, format).unwrap();
+            let mut data: Vec<u8> = Vec::new();
+            let clipped = f32::write_samples(&values, &mut data, format).unwrap();
+            assert_eq!(clipped, 0);
+            for (code, written) in codes.iter().zip(data.iter()) {
+                // mu-law has two codes for zero, these both encode as positive zero.
+                if matches!(format, SampleFormat::MuLaw) && *code == 0x7F {
+                    assert_eq!(*written, 0xFF);
+                } else {
+                    assert_eq!(code, written);
+                }
+            }
+        }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::Sample;
-    use crate::SampleFormat;
-    use crate::SampleReader;
-    use crate::SampleWriter;
-
-    // -------------------
-    //  single values f64
-    // -------------------
 
     #[test]
-    fn check_f64_to_s32le() {
-        let val: f64 = 0.256789;
-        assert_eq!(val.to_s32_le(), ([66, 118, 222, 32], false));
-        let val: f64 = -0.256789;
-        assert_eq!(val.to_s32_le(), ([190, 137, 33, 223], false));
-        let val: f64 = 1.1;
-        assert_eq!(val.to_s32_le(), ([255, 255, 255, 127], true));
-        let val: f64 = -1.1;
-        assert_eq!(val.to_s32_le(), ([0, 0, 0, 128], true));
+    fn write_read_all_f16le() {
+        // write data, then read all of it back into a dynamically allocated vec.
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        f32::write_samples(&values, &mut data, &SampleFormat::F16LE).unwrap();
+        assert_eq!(data.len(), 14);
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f32::read_all_samples(&mut slice, &mut values2, &SampleFormat::F16LE).unwrap();
+        assert_eq!(values, values2);
     }
 
     #[test]
-    fn check_f64_from_s32le() {
-        let data = [0, 0, 64, 32];
-        assert_eq!(f64::from_s32_le(data), 0.251953125);
-        let data = [0, 0, 64, 223];
-        assert_eq!(f64::from_s32_le(data), -0.255859375);
-        let data = [0, 0, 0, 128];
-        assert_eq!(f64::from_s32_le(data), -1.0);
+    fn write_read_all_s64be() {
+        // write data, then read all of it back into a dynamically allocated vec.
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples(&values, &mut data, &SampleFormat::S64BE).unwrap();
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f64::read_all_samples(&mut slice, &mut values2, &SampleFormat::S64BE).unwrap();
+        assert_eq!(values, values2);
     }
 
     #[test]
-    fn check_f64_to_s32be() {
-        let val: f64 = 0.256789;
-        assert_eq!(val.to_s32_be(), ([32, 222, 118, 66], false));
-        let val: f64 = -0.256789;
-        assert_eq!(val.to_s32_be(), ([223, 33, 137, 190], false));
-        let val: f64 = 1.1;
-        assert_eq!(val.to_s32_be(), ([127, 255, 255, 255], true));
-        let val: f64 = -1.1;
-        assert_eq!(val.to_s32_be(), ([128, 0, 0, 0], true));
+    fn write_read_all_fixed_point() {
+        // values outside -1.0 to +1.0 fit in the headroom of Q8.23.
+        let values = vec![-100.0, -2.5, -0.125, 0.0, 0.125, 2.5, 100.0];
+        let format = SampleFormat::FixedPoint(FixedPoint::be(8, 23, 4).unwrap());
+        let mut data: Vec<u8> = Vec::new();
+        let clipped = f64::write_samples(&values, &mut data, &format).unwrap();
+        assert_eq!(clipped, 0);
+        assert_eq!(data.len(), 28);
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        f64::read_all_samples(&mut slice, &mut values2, &format).unwrap();
+        assert_eq!(values, values2);
     }
 
+    #[test]
+    fn q1_15_matches_s16le() {
+        let values = vec![-1.0, -0.256789, -0.125, 0.0, 0.125, 0.256789, 0.99];
+        let mut data_s16: Vec<u8> = Vec::new();
+        f32::write_samples(&values, &mut data_s16, &SampleFormat::S16LE).unwrap();
+        let format = SampleFormat::FixedPoint(FixedPoint::le(1, 15, 2).unwrap());
+        let mut data_q15: Vec<u8> = Vec::new();
+        f32::write_samples(&values, &mut data_q15, &format).unwrap();
+        assert_eq!(data_s16, data_q15);
+        let mut values2 = vec![0.0; 7];
+        let mut slice: &[u8] = &data_q15;
+        f32::read_samples(&mut slice, &mut values2, &format).unwrap();
+        let mut values3 = vec![0.0; 7];
+        let mut slice: &[u8] = &data_s16;
+        f32::read_samples(&mut slice, &mut values3, &SampleFormat::S16LE).unwrap();
+        assert_eq!(values2, values3);
+    }
 
     #[test]
-    fn check_f64_from_s32be() {
-        let data = [32, 64, 0, 0];
-        assert_eq!(f64::from_s32_be(data), 0.251953125);
-        let data = [223, 64, 0, 0];
-        assert_eq!(f64::from_s32_be(data), -0.255859375);
-        let data = [128, 0, 0, 0];
-        assert_eq!(f64::from_s32_be(data), -1.0);
+    fn write_read_all_s12packedle_odd() {
+        // an odd trailing sample is written as 2 bytes, and is included when reading back.
+        let values = vec![0.25, -0.5, 0.125];
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples(&values, &mut data, &SampleFormat::S12PackedLE).unwrap();
+        assert_eq!(data, vec![0x00, 0x02, 0xC0, 0x00, 0x01]);
+        let mut values2 = Vec::new();
+        let mut slice: &[u8] = &data;
+        let nbr_read =
+            f64::read_all_samples(&mut slice, &mut values2, &SampleFormat::S12PackedLE).unwrap();
+        assert_eq!(nbr_read, 3);
+        assert_eq!(values, values2);
     }
 
     #[test]
-    fn check_f64_to_s243le() {
-        let val: f64 = 0.256789;
-        assert_eq!(val.to_s24_3_le(), ([118, 222, 32], false));
-        let val: f64 = -0.256789;
-        assert_eq!(val.to_s24_3_le(), ([137, 33, 223], false));
-        let val: f64 = 1.1;
-        assert_eq!(val.to_s24_3_le(), ([255, 255, 127], true));
-        let val: f64 = -1.1;
-        assert_eq!(val.to_s24_3_le(), ([0, 0, 128], true));
+    fn read_to_slice_s12packedbe_odd() {
+        let values = vec![0.25, -0.5, 0.125, -0.125, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        f32::write_samples(&values, &mut data, &SampleFormat::S12PackedBE).unwrap();
+        assert_eq!(data.len(), 8);
+        // an odd length slice is only filled with whole pairs.
+        let mut values2 = vec![0.75; 3];
+        let mut slice: &[u8] = &data;
+        let nbr_read =
+            f32::read_samples(&mut slice, &mut values2, &SampleFormat::S12PackedBE).unwrap();
+        assert_eq!(nbr_read, 2);
+        assert_eq!(values2, vec![0.25, -0.5, 0.75]);
+        // the odd trailing sample is counted.
+        let mut values3 = vec![0.75; 4];
+        let nbr_read =
+            f32::read_samples(&mut slice, &mut values3, &SampleFormat::S12PackedBE).unwrap();
+        assert_eq!(nbr_read, 3);
+        assert_eq!(values3, vec![0.125, -0.125, 0.5, 0.75]);
     }
 
     #[test]
-    fn check_f64_to_s243be() {
-        let val: f64 = 0.256789;
-        assert_eq!(val.to_s24_3_be(), ([32, 222, 118], false));
-        let val: f64 = -0.256789;
-        assert_eq!(val.to_s24_3_be(), ([223, 33, 137], false));
-        let val: f64 = 1.1;
-        assert_eq!(val.to_s24_3_be(), ([127, 255, 255], true));
-        let val: f64 = -1.1;
-        assert_eq!(val.to_s24_3_be(), ([128, 0, 0], true));
+    fn write_read_frames_s16le() {
+        let values = vec![-0.5, 0.5, -0.25, 0.25, 1.5, 0.0];
+        let mut data: Vec<u8> = Vec::new();
+        let nbr_clipped = f64::write_frames(&values, 2, &mut data, &SampleFormat::S16LE).unwrap();
+        assert_eq!(nbr_clipped, 1);
+        assert_eq!(data.len(), 12);
+        // only whole frames are read into the slice.
+        let mut values2 = vec![0.75; 5];
+        let mut slice: &[u8] = &data;
+        let nbr_frames =
+            f64::read_frames(&mut slice, &mut values2, 2, &SampleFormat::S16LE).unwrap();
+        assert_eq!(nbr_frames, 2);
+        assert_eq!(values2, vec![-0.5, 0.5, -0.25, 0.25, 0.75]);
+        let mut values3 = Vec::new();
+        let nbr_frames =
+            f64::read_all_frames(&mut slice, &mut values3, 2, &SampleFormat::S16LE).unwrap();
+        assert_eq!(nbr_frames, 1);
+        assert_eq!(values3.len(), 2);
     }
 
     #[test]
-    fn check_f64_from_s243le() {
-        let data = [0, 64, 32];
-        assert_eq!(f64::from_s24_3_le(data), 0.251953125);
-        let data = [0, 64, 223];
-        assert_eq!(f64::from_s24_3_le(data), -0.255859375);
-        let data = [0, 0, 128];
-        assert_eq!(f64::from_s24_3_le(data), -1.0);
+    fn write_frames_clipped_in_chunks() {
+        let mut values = vec![0.25; 2 * 3000];
+        values[20] = 1.5;
+        values[21] = -1.5;
+        values[4001] = 2.0;
+        let mut data: Vec<u8> = Vec::new();
+        let nbr_clipped = f32::write_frames(&values, 2, &mut data, &SampleFormat::S24LE3).unwrap();
+        assert_eq!(nbr_clipped, 3);
+        assert_eq!(data.len(), 3 * 2 * 3000);
+        assert_eq!(data[3 * 4001..3 * 4002], [0xFF, 0xFF, 0x7F]);
     }
 
     #[test]
-    fn check_f64_from_s243be() {
-        let data = [32, 64, 0];
-        assert_eq!(f64::from_s24_3_be(data), 0.251953125);
-        let data = [223, 64, 0];
-        assert_eq!(f64::from_s24_3_be(data), -0.255859375);
-        let data = [128, 0, 0];
-        assert_eq!(f64::from_s24_3_be(data), -1.0);
+    fn read_frames_partial_frame() {
+        // 3 frames of 2 channels of S24LE3 is 18 bytes, drop the last byte.
+        let data = vec![0_u8; 17];
+        let mut values = vec![0.0; 6];
+        let mut slice: &[u8] = &data;
+        assert!(f32::read_frames(&mut slice, &mut values, 2, &SampleFormat::S24LE3).is_err());
+        let mut values = Vec::new();
+        let mut slice: &[u8] = &data;
+        assert!(f32::read_all_frames(&mut slice, &mut values, 2, &SampleFormat::S24LE3).is_err());
     }
 
     #[test]
-    fn check_f64_from_s244le() {
-        let data = [0, 64, 32, 0];
-        assert_eq!(f64::from_s24_4_le(data), 0.251953125);
-        let data = [0, 64, 223, 0];
-        assert_eq!(f64::from_s24_4_le(data), -0.255859375);
-        let data = [0, 0, 128, 0];
-        assert_eq!(f64::from_s24_4_le(data), -1.0);
+    fn read_frames_partial_frame_keeps_whole_frames() {
+        let values = vec![-0.5, 0.5, -0.25, 0.25, 0.125, -0.125];
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_frames(&values, 2, &mut data, &SampleFormat::S24LE3).unwrap();
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        let mut values2 = vec![0.0; 8];
+        let mut slice: &[u8] = &data;
+        let err = f64::read_frames(&mut slice, &mut values2, 2, &SampleFormat::S24LE3).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TruncatedFrame {
+                frames: 3,
+                bytes: 4
+            }
+        ));
+        assert_eq!(values2[0..6], values[..]);
+        let mut values3 = vec![1.0];
+        let mut slice: &[u8] = &data;
+        let err =
+            f64::read_all_frames(&mut slice, &mut values3, 2, &SampleFormat::S24LE3).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TruncatedFrame {
+                frames: 3,
+                bytes: 4
+            }
+        ));
+        assert_eq!(values3[1..], values[..]);
     }
 
     #[test]
-    fn check_f64_from_s244be() {
-        let data = [0, 32, 64, 0];
-        assert_eq!(f64::from_s24_4_be(data), 0.251953125);
-        let data = [0, 223, 64, 0];
-        assert_eq!(f64::from_s24_4_be(data), -0.255859375);
-        let data = [0, 128, 0, 0];
-        assert_eq!(f64::from_s24_4_be(data), -1.0);
+    fn write_frames_invalid() {
+        let values = vec![0.0; 5];
+        let mut data: Vec<u8> = Vec::new();
+        assert!(f32::write_frames(&values, 2, &mut data, &SampleFormat::S16LE).is_err());
+        assert!(f32::write_frames(&values, 0, &mut data, &SampleFormat::S16LE).is_err());
+        // 12 bit frames must be a whole number of bytes.
+        assert!(f32::write_frames(&values, 5, &mut data, &SampleFormat::S12PackedLE).is_err());
+        assert!(data.is_empty());
     }
 
     #[test]
-    fn check_f64_to_s244le() {
-        let val: f64 = 0.256789;
-        assert_eq!(val.to_s24_4_le(), ([118, 222, 32, 0], false));
-        let val: f64 = -0.256789;
-        assert_eq!(val.to_s24_4_le(), ([137, 33, 223, 0], false));
-        let val: f64 = 1.1;
-        assert_eq!(val.to_s24_4_le(), ([255, 255, 127, 0], true));
-        let val: f64 = -1.1;
-        assert_eq!(val.to_s24_4_le(), ([0, 0, 128, 0], true));
+    fn write_read_planar_s24le3() {
+        let left = vec![-0.5, -0.25, 0.0, 0.25];
+        let right = vec![0.5, 0.125, -0.125, 1.5];
+        let mut data: Vec<u8> = Vec::new();
+        let clipped =
+            f64::write_planar(&[&left, &right], &mut data, &SampleFormat::S24LE3).unwrap();
+        assert_eq!(clipped, 1);
+        // the data is interleaved.
+        let mut interleaved = Vec::new();
+        let mut slice: &[u8] = &data;
+        f64::read_all_samples(&mut slice, &mut interleaved, &SampleFormat::S24LE3).unwrap();
+        assert_eq!(interleaved[0..4], [-0.5, 0.5, -0.25, 0.125]);
+
+        let mut planar = vec![Vec::new(), Vec::new()];
+        let mut slice: &[u8] = &data;
+        let nbr_frames =
+            f64::read_all_planar(&mut slice, &mut planar, &SampleFormat::S24LE3).unwrap();
+        assert_eq!(nbr_frames, 4);
+        assert_eq!(planar[0], left);
+        assert_eq!(planar[1][0..3], right[0..3]);
     }
 
     #[test]
-    fn check_f64_to_s244be() {
-        let val: f64 = 0.256789;
-        assert_eq!(val.to_s24_4_be(), ([0, 32, 222, 118], false));
-        let val: f64 = -0.256789;
-        assert_eq!(val.to_s24_4_be(), ([0, 223, 33, 137], false));
-        let val: f64 = 1.1;
-        assert_eq!(val.to_s24_4_be(), ([0, 127, 255, 255], true));
-        let val: f64 = -1.1;
-        assert_eq!(val.to_s24_4_be(), ([0, 128, 0, 0], true));
+    fn read_planar_to_slices() {
+        let values: Vec<f32> = (0..3000).map(|n| (n % 100) as f32 / 128.0).collect();
+        let mut data: Vec<u8> = Vec::new();
+        f32::write_samples(&values, &mut data, &SampleFormat::S16BE).unwrap();
+        let mut first = vec![0.0; 1400];
+        let mut second = vec![0.0; 1500];
+        let mut third = vec![0.0; 1500];
+        let mut buffers: [&mut [f32]; 3] = [&mut first, &mut second, &mut third];
+        let mut slice: &[u8] = &data;
+        let nbr_frames = f32::read_planar(&mut slice, &mut buffers, &SampleFormat::S16BE).unwrap();
+        // read until the shortest buffer is full.
+        assert_eq!(nbr_frames, 1000);
+        assert_eq!(first[999], values[2997]);
+        assert_eq!(second[0], values[1]);
+        assert_eq!(third[999], values[2999]);
+        assert_eq!(second[1000], 0.0);
     }
 
     #[test]
-    fn check_f64_to_s16le() {
-        let val: f64 = 0.256789;
-        assert_eq!(val.to_s16_le(), ([222, 32], false));
-        let val: f64 = -0.256789;
-        assert_eq!(val.to_s16_le(), ([34, 223], false));
+    fn write_planar_clipped_samples() {
+        // both planar writers count the clipped samples
+        let left = vec![0.0, 1.5, 0.25];
+        let right = vec![0.0, -1.5, 0.25];
+        let mut data: Vec<u8> = Vec::new();
+        let clipped = f32::write_planar(&[&left, &right], &mut data, &SampleFormat::S16LE).unwrap();
+        assert_eq!(clipped, 2);
+        let mut left_data: Vec<u8> = Vec::new();
+        let mut right_data: Vec<u8> = Vec::new();
+        let clipped = f32::write_planar_raw(
+            &[&left, &right],
+            &mut [&mut left_data, &mut right_data],
+            &SampleFormat::S16LE,
+        )
+        .unwrap();
+        assert_eq!(clipped, 2);
     }
 
     #[test]
-    fn check_f64_to_s16be() {
-        let val: f64 = 0.256789;
-        assert_eq!(val.to_s16_be(), ([32, 222], false));
-        let val: f64 = -0.256789;
-        assert_eq!(val.to_s16_be(), ([223, 34], false));
+    fn read_planar_partial_frame() {
+        let data = vec![0, 64, 0, 32, 0, 192, 0, 224, 0];
+        let mut left = vec![0.0; 4];
+        let mut right = vec![0.0; 4];
+        let mut buffers: [&mut [f64]; 2] = [&mut left, &mut right];
+        let mut slice: &[u8] = &data;
+        let err = f64::read_planar(&mut slice, &mut buffers, &SampleFormat::S16LE).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TruncatedFrame {
+                frames: 2,
+                bytes: 1
+            }
+        ));
+        assert_eq!(left[0..2], [0.5, -0.5]);
+        assert_eq!(right[0..2], [0.25, -0.25]);
+        let mut planar = vec![Vec::new(), Vec::new()];
+        let mut slice: &[u8] = &data;
+        let err = f64::read_all_planar(&mut slice, &mut planar, &SampleFormat::S16LE).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TruncatedFrame {
+                frames: 2,
+                bytes: 1
+            }
+        ));
+        assert_eq!(planar[0], vec![0.5, -0.5]);
+        assert_eq!(planar[1], vec![0.25, -0.25]);
     }
 
     #[test]
-    fn check_f64_to_f32le() {
-        let val: f64 = 0.256789;
-        let exp = (0.256789 as f32).to_le_bytes();
-        assert_eq!(val.to_f32_le(), (exp, false));
-        let val: f64 = -0.256789;
-        let exp = (-0.256789 as f32).to_le_bytes();
-        assert_eq!(val.to_f32_le(), (exp, false));
-        let val: f64 = 1.1;
-        let exp = (1.0 as f32).to_le_bytes();
-        assert_eq!(val.to_f32_le(), (exp, true));
-        let val: f64 = -1.1;
-        let exp = (-1.0 as f32).to_le_bytes();
-        assert_eq!(val.to_f32_le(), (exp, true));
+    fn write_planar_unequal_lengths() {
+        let left = vec![0.0; 4];
+        let right = vec![0.0; 3];
+        let mut data: Vec<u8> = Vec::new();
+        assert!(f32::write_planar(&[&left, &right], &mut data, &SampleFormat::S16LE).is_err());
     }
 
     #[test]
-    fn check_f64_to_f32be() {
-        let val: f64 = 0.256789;
-        let exp = (0.256789 as f32).to_be_bytes();
-        assert_eq!(val.to_f32_be(), (exp, false));
-        let val: f64 = -0.256789;
-        let exp = (-0.256789 as f32).to_be_bytes();
-        assert_eq!(val.to_f32_be(), (exp, false));
-        let val: f64 = 1.1;
-        let exp = (1.0 as f32).to_be_bytes();
-        assert_eq!(val.to_f32_be(), (exp, true));
-        let val: f64 = -1.1;
-        let exp = (-1.0 as f32).to_be_bytes();
-        assert_eq!(val.to_f32_be(), (exp, true));
+    fn write_read_planar_raw_f32le() {
+        let left = vec![-0.5, -0.25, 0.0, 0.25];
+        let right = vec![0.5, 0.125, -0.125, 1.5];
+        // write each channel as a block, and join the blocks to a single buffer.
+        let mut left_data: Vec<u8> = Vec::new();
+        let mut right_data: Vec<u8> = Vec::new();
+        let clipped = f32::write_planar_raw(
+            &[&left, &right],
+            &mut [&mut left_data, &mut right_data],
+            &SampleFormat::F32LE,
+        )
+        .unwrap();
+        assert_eq!(clipped, 1);
+        let data = [left_data, right_data].concat();
+        assert_eq!(data[16..20], 0.5_f32.to_le_bytes());
+
+        let mut left2 = vec![0.0; 4];
+        let mut right2 = vec![0.0; 4];
+        let mut blocks = data.chunks(16);
+        let mut left_block = blocks.next().unwrap();
+        let mut right_block = blocks.next().unwrap();
+        let nbr_frames = f32::read_planar_raw(
+            &mut [&mut left_block, &mut right_block],
+            &mut [&mut left2, &mut right2],
+            &SampleFormat::F32LE,
+        )
+        .unwrap();
+        assert_eq!(nbr_frames, 4);
+        assert_eq!(left2, left);
+        assert_eq!(right2[3], 1.0);
     }
 
     #[test]
-    fn check_f64_to_f64le() {
-        let val: f64 = 0.256789;
-        let exp = (0.256789 as f64).to_le_bytes();
-        assert_eq!(val.to_f64_le(), (exp, false));
-        let val: f64 = -0.256789;
-        let exp = (-0.256789 as f64).to_le_bytes();
-        assert_eq!(val.to_f64_le(), (exp, false));
-        let val: f64 = 1.1;
-        let exp = (1.0 as f64).to_le_bytes();
-        assert_eq!(val.to_f64_le(), (exp, true));
-        let val: f64 = -1.1;
-        let exp = (-1.0 as f64).to_le_bytes();
-        assert_eq!(val.to_f64_le(), (exp, true));
+    fn read_all_planar_raw_shortest() {
+        let mut left_data: &[u8] = &[0, 64, 0, 32, 0, 192];
+        let mut right_data: &[u8] = &[0, 64, 0, 32];
+        let mut planar = vec![Vec::new(), Vec::new()];
+        let nbr_frames = f64::read_all_planar_raw(
+            &mut [&mut left_data, &mut right_data],
+            &mut planar,
+            &SampleFormat::S16LE,
+        )
+        .unwrap();
+        assert_eq!(nbr_frames, 2);
+        assert_eq!(planar[0], vec![0.5, 0.25, -0.5]);
+        assert_eq!(planar[1], vec![0.5, 0.25]);
     }
 
     #[test]
-    fn check_f64_to_f64be() {
-        let val: f64 = 0.256789;
-        let exp = (0.256789 as f64).to_be_bytes();
-        assert_eq!(val.to_f64_be(), (exp, false));
-        let val: f64 = -0.256789;
-        let exp = (-0.256789 as f64).to_be_bytes();
-        assert_eq!(val.to_f64_be(), (exp, false));
-        let val: f64 = 1.1;
-        let exp = (1.0 as f64).to_be_bytes();
-        assert_eq!(val.to_f64_be(), (exp, true));
-        let val: f64 = -1.1;
-        let exp = (-1.0 as f64).to_be_bytes();
-        assert_eq!(val.to_f64_be(), (exp, true));
+    fn read_frames_mapped_select() {
+        // 3 frames of 8 channels, where each value is the channel number divided by 8.
+        let values: Vec<f32> = (0..24).map(|n| (n % 8) as f32 / 8.0).collect();
+        let mut data: Vec<u8> = Vec::new();
+        f32::write_samples(&values, &mut data, &SampleFormat::S16LE).unwrap();
+        let mut selected = vec![0.5; 7];
+        let mut slice: &[u8] = &data;
+        let nbr_frames = f32::read_frames_mapped(
+            &mut slice,
+            &mut selected,
+            8,
+            &[Some(2), Some(5)],
+            &SampleFormat::S16LE,
+        )
+        .unwrap();
+        assert_eq!(nbr_frames, 3);
+        assert_eq!(selected, vec![0.25, 0.625, 0.25, 0.625, 0.25, 0.625, 0.5]);
+
+        let mut remapped = Vec::new();
+        let mut slice: &[u8] = &data;
+        let nbr_frames = f32::read_all_frames_mapped(
+            &mut slice,
+            &mut remapped,
+            8,
+            &[Some(1), Some(1), None, Some(0)],
+            &SampleFormat::S16LE,
+        )
+        .unwrap();
+        assert_eq!(nbr_frames, 3);
+        assert_eq!(remapped[0..4], [0.125, 0.125, 0.0, 0.0]);
+        assert_eq!(remapped.len(), 12);
+
+        // the whole frames before a truncated frame are kept
+        let mut remapped = Vec::new();
+        let mut slice: &[u8] = &data[0..40];
+        let err = f32::read_all_frames_mapped(
+            &mut slice,
+            &mut remapped,
+            8,
+            &[Some(3)],
+            &SampleFormat::S16LE,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TruncatedFrame {
+                frames: 2,
+                bytes: 8
+            }
+        ));
+        assert_eq!(remapped, vec![0.375, 0.375]);
+        let mut selected = vec![0.5; 3];
+        let mut slice: &[u8] = &data[0..40];
+        let err = f32::read_frames_mapped(
+            &mut slice,
+            &mut selected,
+            8,
+            &[Some(3)],
+            &SampleFormat::S16LE,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TruncatedFrame {
+                frames: 2,
+                bytes: 8
+            }
+        ));
+        assert_eq!(selected, vec![0.375, 0.375, 0.5]);
     }
 
-    // -------------------
-    //  single values f32
-    // -------------------
     #[test]
-    fn check_f32_to_s32le() {
-        let val: f32 = 0.256789;
-        assert_eq!(val.to_s32_le(), ([64, 118, 222, 32], false));
-        let val: f32 = -0.256789;
-        assert_eq!(val.to_s32_le(), ([192, 137, 33, 223], false));
-        let val: f32 = 1.1;
-        assert_eq!(val.to_s32_le(), ([255, 255, 255, 127], true));
-        let val: f32 = -1.1;
-        assert_eq!(val.to_s32_le(), ([0, 0, 0, 128], true));
+    fn write_frames_mapped_u8() {
+        let values = vec![-0.5, 0.5, -0.25, 0.25];
+        let mut data: Vec<u8> = Vec::new();
+        let nbr_clipped = f64::write_frames_mapped(
+            &values,
+            2,
+            &[Some(1), None, Some(0)],
+            &mut data,
+            &SampleFormat::U8,
+        )
+        .unwrap();
+        assert_eq!(nbr_clipped, 0);
+        // unmapped channels are written as silence, which is 128 for U8.
+        assert_eq!(data, vec![192, 128, 64, 160, 128, 96]);
     }
 
     #[test]
-    fn check_f32_from_s32le() {
-        let data = [0, 0, 64, 32];
-        assert_eq!(f32::from_s32_le(data), 0.251953125);
-        let data = [0, 0, 64, 223];
-        assert_eq!(f32::from_s32_le(data), -0.255859375);
-        let data = [0, 0, 0, 128];
-        assert_eq!(f32::from_s32_le(data), -1.0);
+    fn channel_map_invalid() {
+        let values = vec![0.0; 4];
+        let mut data: Vec<u8> = Vec::new();
+        assert!(
+            f64::write_frames_mapped(&values, 2, &[Some(2)], &mut data, &SampleFormat::S16LE)
+                .is_err()
+        );
+        assert!(
+            f64::write_frames_mapped(&values, 2, &[], &mut data, &SampleFormat::S16LE).is_err()
+        );
+        let mut slice: &[u8] = &[0, 0, 0, 0];
+        let mut values = vec![0.0; 2];
+        assert!(f64::read_frames_mapped(
+            &mut slice,
+            &mut values,
+            2,
+            &[Some(0), Some(3)],
+            &SampleFormat::S16LE
+        )
+        .is_err());
     }
 
     #[test]
-    fn check_f32_to_s32be() {
-        let val: f32 = 0.256789;
-        assert_eq!(val.to_s32_be(), ([32, 222, 118, 64], false));
-        let val: f32 = -0.256789;
-        assert_eq!(val.to_s32_be(), ([223, 33, 137, 192], false));
-        let val: f32 = 1.1;
-        assert_eq!(val.to_s32_be(), ([127, 255, 255, 255], true));
-        let val: f32 = -1.1;
-        assert_eq!(val.to_s32_be(), ([128, 0, 0, 0], true));
+    fn write_read_slices_s24le3() {
+        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 1.5];
+        let mut data = vec![0_u8; 20];
+        let (nbr_converted, nbr_bytes, nbr_clipped) =
+            f64::write_samples_to_slice(&values, &mut data, &SampleFormat::S24LE3);
+        // only 6 samples fit in 20 bytes.
+        assert_eq!((nbr_converted, nbr_bytes, nbr_clipped), (6, 18, 0));
+        let mut values2 = vec![1.0; 8];
+        let (nbr_converted, leftover) =
+            f64::read_samples_from_slice(&data, &mut values2, &SampleFormat::S24LE3);
+        assert_eq!(nbr_converted, 6);
+        assert_eq!(leftover, &[0, 0]);
+        assert_eq!(values2[0..6], values[0..6]);
+        assert_eq!(values2[6..8], [1.0, 1.0]);
     }
 
     #[test]
-    fn check_f32_from_s32be() {
-        let data = [32, 64, 0, 0];
-        assert_eq!(f32::from_s32_be(data), 0.251953125);
-        let data = [223, 64, 0, 0];
-        assert_eq!(f32::from_s32_be(data), -0.255859375);
-        let data = [128, 0, 0, 0];
-        assert_eq!(f32::from_s32_be(data), -1.0);
+    fn read_slice_to_shorter_slice_fixed() {
+        let format = SampleFormat::FixedPoint(FixedPoint::be(2, 14, 2).unwrap());
+        let data = [0x20, 0x00, 0xE0, 0x00, 0x40, 0x00];
+        let mut values = vec![0.0; 2];
+        let (nbr_converted, leftover) = f32::read_samples_from_slice(&data, &mut values, &format);
+        assert_eq!(nbr_converted, 2);
+        assert_eq!(leftover, &[0x40, 0x00]);
+        assert_eq!(values, vec![0.5, -0.5]);
     }
 
     #[test]
-    fn check_f32_to_s243le() {
-        let val: f32 = 0.256789;
-        assert_eq!(val.to_s24_3_le(), ([118, 222, 32], false));
-        let val: f32 = -0.256789;
-        assert_eq!(val.to_s24_3_le(), ([137, 33, 223], false));
-        let val: f32 = 1.1;
-        assert_eq!(val.to_s24_3_le(), ([255, 255, 127], true));
-        let val: f32 = -1.1;
-        assert_eq!(val.to_s24_3_le(), ([0, 0, 128], true));
+    fn write_read_slices_s12packedbe() {
+        let values = vec![0.25, -0.5, 0.125];
+        let mut data = vec![0_u8; 5];
+        let (nbr_converted, nbr_bytes, _) =
+            f32::write_samples_to_slice(&values, &mut data, &SampleFormat::S12PackedBE);
+        assert_eq!((nbr_converted, nbr_bytes), (3, 5));
+        let mut values2 = vec![0.0; 4];
+        let (nbr_converted, leftover) =
+            f32::read_samples_from_slice(&data, &mut values2, &SampleFormat::S12PackedBE);
+        assert_eq!(nbr_converted, 3);
+        assert!(leftover.is_empty());
+        assert_eq!(values2[0..3], values[..]);
+        // a pair does not fit in 4 bytes after the first pair.
+        let mut data = vec![0_u8; 4];
+        let (nbr_converted, nbr_bytes, _) = f32::write_samples_to_slice(
+            &[0.25, -0.5, 0.125, 0.0],
+            &mut data,
+            &SampleFormat::S12PackedBE,
+        );
+        assert_eq!((nbr_converted, nbr_bytes), (2, 3));
     }
 
-    #[test]
-    fn check_f32_to_s243be() {
-        let val: f32 = 0.256789;
-        assert_eq!(val.to_s24_3_be(), ([32, 222, 118], false));
-        let val: f32 = -0.256789;
-        assert_eq!(val.to_s24_3_be(), ([223, 33, 137], false));
-        let val: f32 = 1.1;
-        assert_eq!(val.to_s24_3_be(), ([127, 255, 255], true));
-        let val: f32 = -1.1;
-        assert_eq!(val.to_s24_3_be(), ([128, 0, 0], true));
+    /// A source that counts the calls to read.
+    struct CountingReader<'a> {
+        data: &'a [u8],
+        nbr_calls: usize,
     }
 
-    #[test]
-    fn check_f32_from_s243le() {
-        let data = [0, 64, 32];
-        assert_eq!(f32::from_s24_3_le(data), 0.251953125);
-        let data = [0, 64, 223];
-        assert_eq!(f32::from_s24_3_le(data), -0.255859375);
-        let data = [0, 0, 128];
-        assert_eq!(f32::from_s24_3_le(data), -1.0);
+    impl<'a> std::io::Read for CountingReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.nbr_calls += 1;
+            self.data.read(buf)
+        }
+    }
+
+    /// A target that counts the calls to write.
+    struct CountingWriter {
+        data: Vec<u8>,
+        nbr_calls: usize,
+    }
+
+    impl std::io::Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.nbr_calls += 1;
+            self.data.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
     }
 
     #[test]
-    fn check_f32_from_s243be() {
-        let data = [32, 64, 0];
-        assert_eq!(f32::from_s24_3_be(data), 0.251953125);
-        let data = [223, 64, 0];
-        assert_eq!(f32::from_s24_3_be(data), -0.255859375);
-        let data = [128, 0, 0];
-        assert_eq!(f32::from_s24_3_be(data), -1.0);
+    fn write_read_chunked_s24le3() {
+        let values: Vec<f64> = (0..3000).map(|n| (n % 100) as f64 / 128.0).collect();
+        let mut writer = CountingWriter {
+            data: Vec::new(),
+            nbr_calls: 0,
+        };
+        f64::write_samples(&values, &mut writer, &SampleFormat::S24LE3).unwrap();
+        assert_eq!(writer.data.len(), 9000);
+        assert_eq!(writer.nbr_calls, 3);
+
+        let mut reader = CountingReader {
+            data: &writer.data,
+            nbr_calls: 0,
+        };
+        let mut values2 = vec![0.0; 2500];
+        let nbr_read = f64::read_samples(&mut reader, &mut values2, &SampleFormat::S24LE3).unwrap();
+        assert_eq!(nbr_read, 2500);
+        assert_eq!(reader.nbr_calls, 3);
+        assert_eq!(values2, values[0..2500]);
+        // no more bytes than needed are read
+        assert_eq!(reader.data.len(), 1500);
+
+        let mut values3 = Vec::new();
+        let nbr_read =
+            f64::read_all_samples(&mut reader, &mut values3, &SampleFormat::S24LE3).unwrap();
+        assert_eq!(nbr_read, 500);
+        assert_eq!(values3, values[2500..]);
     }
 
     #[test]
-    fn check_f32_from_s244le() {
-        let data = [0, 64, 32, 0];
-        assert_eq!(f32::from_s24_4_le(data), 0.251953125);
-        let data = [0, 64, 223, 0];
-        assert_eq!(f32::from_s24_4_le(data), -0.255859375);
-        let data = [0, 0, 128, 0];
-        assert_eq!(f32::from_s24_4_le(data), -1.0);
+    fn write_frames_chunked() {
+        let values = vec![0.25; 4000];
+        let mut writer = CountingWriter {
+            data: Vec::new(),
+            nbr_calls: 0,
+        };
+        f32::write_frames(&values, 4, &mut writer, &SampleFormat::S16LE).unwrap();
+        assert_eq!(writer.data.len(), 8000);
+        assert_eq!(writer.nbr_calls, 1);
+    }
+
+    fn write_quantised(values: &[f64], quantization: Quantization) -> Vec<i16> {
+        let options = WriteOptions {
+            quantization,
+            ..Default::default()
+        };
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples_with(values, &mut data, &SampleFormat::S16LE, &options).unwrap();
+        data.chunks(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect()
     }
 
     #[test]
-    fn check_f32_from_s244be() {
-        let data = [0, 32, 64, 0];
-        assert_eq!(f32::from_s24_4_be(data), 0.251953125);
-        let data = [0, 223, 64, 0];
-        assert_eq!(f32::from_s24_4_be(data), -0.255859375);
-        let data = [0, 128, 0, 0];
-        assert_eq!(f32::from_s24_4_be(data), -1.0);
+    fn write_quantization_modes_s16le() {
+        let values: Vec<f64> = [2.5, 1.5, 0.7, 0.3, -0.3, -0.7, -1.5, -2.5]
+            .iter()
+            .map(|v| v / 32768.0)
+            .collect();
+        assert_eq!(
+            write_quantised(&values, Quantization::RoundHalfEven),
+            vec![2, 2, 1, 0, 0, -1, -2, -2]
+        );
+        assert_eq!(
+            write_quantised(&values, Quantization::RoundHalfUp),
+            vec![3, 2, 1, 0, 0, -1, -1, -2]
+        );
+        assert_eq!(
+            write_quantised(&values, Quantization::Floor),
+            vec![2, 1, 0, 0, -1, -1, -2, -3]
+        );
+        assert_eq!(
+            write_quantised(&values, Quantization::Truncate),
+            vec![2, 1, 0, 0, 0, 0, -1, -2]
+        );
+        // the default is to round to nearest, with ties to even
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples(&values, &mut data, &SampleFormat::S16LE).unwrap();
+        let mut data2: Vec<u8> = Vec::new();
+        f64::write_samples_with(
+            &values,
+            &mut data2,
+            &SampleFormat::S16LE,
+            &WriteOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(data, data2);
+        assert_eq!(
+            data.chunks(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]))
+                .collect::<Vec<i16>>(),
+            vec![2, 2, 1, 0, 0, -1, -2, -2]
+        );
     }
 
     #[test]
-    fn check_f32_to_s244le() {
-        let val: f32 = 0.256789;
-        assert_eq!(val.to_s24_4_le(), ([118, 222, 32, 0], false));
-        let val: f32 = -0.256789;
-        assert_eq!(val.to_s24_4_le(), ([137, 33, 223, 0], false));
-        let val: f32 = 1.1;
-        assert_eq!(val.to_s24_4_le(), ([255, 255, 127, 0], true));
-        let val: f32 = -1.1;
-        assert_eq!(val.to_s24_4_le(), ([0, 0, 128, 0], true));
+    fn write_quantization_modes_s24le3() {
+        let lsb = 1.0 / 8388608.0;
+        let values = [1000.5 * lsb, 1001.5 * lsb, -1000.5 * lsb, -1001.5 * lsb];
+        let expected = [
+            (Quantization::RoundHalfEven, [1000, 1002, -1000, -1002]),
+            (Quantization::RoundHalfUp, [1001, 1002, -1000, -1001]),
+            (Quantization::Floor, [1000, 1001, -1001, -1002]),
+            (Quantization::Truncate, [1000, 1001, -1000, -1001]),
+        ];
+        for (quantization, ints) in expected.iter() {
+            let options = WriteOptions {
+                quantization: *quantization,
+                ..Default::default()
+            };
+            let mut data: Vec<u8> = Vec::new();
+            f32::write_samples_with(&values, &mut data, &SampleFormat::S24LE3, &options).unwrap();
+            let written: Vec<i32> = data
+                .chunks(3)
+                .map(|b| i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8)
+                .collect();
+            assert_eq!(written, ints, "{:?}", quantization);
+        }
     }
 
     #[test]
-    fn check_f32_to_s244be() {
-        let val: f32 = 0.256789;
-        assert_eq!(val.to_s24_4_be(), ([0, 32, 222, 118], false));
-        let val: f32 = -0.256789;
-        assert_eq!(val.to_s24_4_be(), ([0, 223, 33, 137], false));
-        let val: f32 = 1.1;
-        assert_eq!(val.to_s24_4_be(), ([0, 127, 255, 255], true));
-        let val: f32 = -1.1;
-        assert_eq!(val.to_s24_4_be(), ([0, 128, 0, 0], true));
+    fn write_read_scaling_s16le() {
+        let values = [1.0, 0.5, -0.5, -1.0];
+        let expected = [
+            (Scaling::PowerOfTwo, [32767, 16384, -16384, -32768], 1),
+            (Scaling::MaxInteger, [32767, 16384, -16384, -32767], 0),
+            (Scaling::Asymmetric, [32767, 16384, -16384, -32768], 0),
+        ];
+        for (scaling, ints, nbr_clipped) in expected.iter() {
+            let options = WriteOptions {
+                scaling: *scaling,
+                ..Default::default()
+            };
+            let mut data: Vec<u8> = Vec::new();
+            let clipped =
+                f64::write_samples_with(&values, &mut data, &SampleFormat::S16LE, &options)
+                    .unwrap();
+            let written: Vec<i16> = data
+                .chunks(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]))
+                .collect();
+            assert_eq!(written, ints, "{:?}", scaling);
+            assert_eq!(clipped, *nbr_clipped, "{:?}", scaling);
+        }
+
+        let data: Vec<u8> = [32767_i16, 16384, -32767, -32768]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let read = |scaling| {
+            let options = ReadOptions { scaling };
+            let mut values = Vec::new();
+            let mut slice: &[u8] = &data;
+            f64::read_all_samples_with(&mut slice, &mut values, &SampleFormat::S16LE, &options)
+                .unwrap();
+            values
+        };
+        assert_eq!(
+            read(Scaling::PowerOfTwo),
+            vec![32767.0 / 32768.0, 0.5, -32767.0 / 32768.0, -1.0]
+        );
+        assert_eq!(
+            read(Scaling::MaxInteger),
+            vec![1.0, 16384.0 / 32767.0, -1.0, -32768.0 / 32767.0]
+        );
+        assert_eq!(
+            read(Scaling::Asymmetric),
+            vec![1.0, 16384.0 / 32767.0, -32767.0 / 32768.0, -1.0]
+        );
     }
 
     #[test]
-    fn check_f32_to_s16le() {
-        let val: f32 = 0.256789;
-        assert_eq!(val.to_s16_le(), ([222, 32], false));
-        let val: f32 = -0.256789;
-        assert_eq!(val.to_s16_le(), ([34, 223], false));
+    fn read_scaling_single_rounding() {
+        // the values are the integers divided by the scale, rounded once to the sample type
+        fn check<T: Sample<T> + SampleReader<T> + num_traits::Float + std::fmt::Debug>(
+            ints: &[i64],
+            sformat: SampleFormat,
+            bytes: usize,
+            reference: fn(i64, Scaling) -> T,
+        ) {
+            let data: Vec<u8> = ints
+                .iter()
+                .flat_map(|i| (i << (64 - 8 * bytes)).to_be_bytes()[0..bytes].to_vec())
+                .collect();
+            for scaling in [Scaling::MaxInteger, Scaling::Asymmetric] {
+                let mut values = vec![T::zero(); ints.len()];
+                let (nbr_read, _) = T::read_samples_from_slice_with(
+                    &data,
+                    &mut values,
+                    &sformat,
+                    &ReadOptions { scaling },
+                );
+                assert_eq!(nbr_read, ints.len());
+                for (i, value) in ints.iter().zip(values.iter()) {
+                    assert_eq!(*value, reference(*i, scaling), "{} {:?}", i, scaling);
+                }
+            }
+        }
+        let s16: Vec<i64> = (-32768..32768).collect();
+        let s24: Vec<i64> = (-(1 << 23)..(1 << 23))
+            .step_by(7)
+            .chain([(1 << 23) - 1])
+            .collect();
+        let s32: Vec<i64> = (-(1 << 31)..(1 << 31))
+            .step_by(65521)
+            .chain(((1 << 31) - 1000..(1 << 31)).chain(-(1 << 31)..-(1 << 31) + 1000))
+            .chain((1 << 24) - 1000..(1 << 24) + 1000)
+            .collect();
+        check::<f32>(&s16, SampleFormat::S16BE, 2, |i, scaling| match scaling {
+            Scaling::Asymmetric if i <= 0 => i as f32 / 32768.0,
+            _ => i as f32 / 32767.0,
+        });
+        check::<f32>(&s24, SampleFormat::S24BE3, 3, |i, scaling| match scaling {
+            Scaling::Asymmetric if i <= 0 => i as f32 / 8388608.0,
+            _ => i as f32 / 8388607.0,
+        });
+        check::<f64>(&s32, SampleFormat::S32BE, 4, |i, scaling| match scaling {
+            Scaling::Asymmetric if i <= 0 => i as f64 / 2147483648.0,
+            _ => i as f64 / 2147483647.0,
+        });
     }
 
     #[test]
-    fn check_f32_to_s16be() {
-        let val: f32 = 0.256789;
-        assert_eq!(val.to_s16_be(), ([32, 222], false));
-        let val: f32 = -0.256789;
-        assert_eq!(val.to_s16_be(), ([223, 34], false));
-    }    
+    fn read_scaling_s32_as_f32() {
+        // f32 can't represent all 32 bit integers, or the scale 2^31-1,
+        // and the values are the same as with the default scaling
+        let ints: [i32; 5] = [i32::MIN, -123456789, 2147483456, 2147483583, i32::MAX];
+        let data: Vec<u8> = ints.iter().flat_map(|i| i.to_le_bytes()).collect();
+        for scaling in [Scaling::MaxInteger, Scaling::Asymmetric] {
+            let mut values = vec![0.0_f32; ints.len()];
+            let (nbr_read, _) = f32::read_samples_from_slice_with(
+                &data,
+                &mut values,
+                &SampleFormat::S32LE,
+                &ReadOptions { scaling },
+            );
+            assert_eq!(nbr_read, ints.len());
+            for (i, value) in ints.iter().zip(values.iter()) {
+                assert_eq!(*value, *i as f32 / 2147483648.0, "{} {:?}", i, scaling);
+            }
+        }
+        // dividing 2147483456 exactly by 2^31-1 would round to 1.0 - 2^-24 instead
+        assert_eq!(2147483456_f32 / 2147483648.0, 1.0 - 2.0_f32.powi(-23));
+        assert_eq!(
+            (2147483456_f64 / 2147483647.0) as f32,
+            1.0 - 2.0_f32.powi(-24)
+        );
+    }
 
     #[test]
-    fn check_f32_to_f32le() {
-        let val: f32 = 0.256789;
-        let exp = (0.256789 as f32).to_le_bytes();
-        assert_eq!(val.to_f32_le(), (exp, false));
-        let val: f32 = -0.256789;
-        let exp = (-0.256789 as f32).to_le_bytes();
-        assert_eq!(val.to_f32_le(), (exp, false));
-        let val: f32 = 1.1;
-        let exp = (1.0 as f32).to_le_bytes();
-        assert_eq!(val.to_f32_le(), (exp, true));
-        let val: f32 = -1.1;
-        let exp = (-1.0 as f32).to_le_bytes();
-        assert_eq!(val.to_f32_le(), (exp, true));
+    fn roundtrip_scaling_s24le3() {
+        // reading and writing with the same scaling gives back the same bytes
+        let data: Vec<u8> = (0..3000).map(|n| (n * 7919 % 251) as u8).collect();
+        for scaling in [
+            Scaling::PowerOfTwo,
+            Scaling::MaxInteger,
+            Scaling::Asymmetric,
+        ] {
+            let mut values: Vec<f32> = vec![0.0; 1000];
+            let (nbr_read, _) = f32::read_samples_from_slice_with(
+                &data,
+                &mut values,
+                &SampleFormat::S24LE3,
+                &ReadOptions { scaling },
+            );
+            assert_eq!(nbr_read, 1000);
+            let options = WriteOptions {
+                scaling,
+                ..Default::default()
+            };
+            let mut data2 = vec![0; 3000];
+            let (_, nbr_bytes, nbr_clipped) = f32::write_samples_to_slice_with(
+                &values,
+                &mut data2,
+                &SampleFormat::S24LE3,
+                &options,
+            )
+            .unwrap();
+            assert_eq!(nbr_bytes, 3000);
+            assert_eq!(nbr_clipped, 0);
+            assert_eq!(data2, data, "{:?}", scaling);
+        }
+    }
+
+    fn clip_options(clipping: ClipPolicy) -> WriteOptions {
+        WriteOptions {
+            clipping,
+            ..Default::default()
+        }
     }
 
     #[test]
-    fn check_f32_to_f32be() {
-        let val: f32 = 0.256789;
-        let exp = (0.256789 as f32).to_be_bytes();
-        assert_eq!(val.to_f32_be(), (exp, false));
-        let val: f32 = -0.256789;
-        let exp = (-0.256789 as f32).to_be_bytes();
-        assert_eq!(val.to_f32_be(), (exp, false));
-        let val: f32 = 1.1;
-        let exp = (1.0 as f32).to_be_bytes();
-        assert_eq!(val.to_f32_be(), (exp, true));
-        let val: f32 = -1.1;
-        let exp = (-1.0 as f32).to_be_bytes();
-        assert_eq!(val.to_f32_be(), (exp, true));
+    fn write_clip_fail() {
+        let mut values = vec![0.25; 3000];
+        values[2500] = 1.5;
+        values[2700] = -1.5;
+        let options = clip_options(ClipPolicy::Fail);
+        let mut data: Vec<u8> = Vec::new();
+        let err = f64::write_samples_with(&values, &mut data, &SampleFormat::S16LE, &options)
+            .unwrap_err();
+        assert!(matches!(err, Error::Clipped(2500)));
+        // the values before the clipped one are written
+        assert_eq!(data.len(), 5000);
+
+        let mut rawbytes = [0; 12];
+        let result = f32::write_samples_to_slice_with(
+            &[0.5, 1.0, 0.5],
+            &mut rawbytes,
+            &SampleFormat::F32LE,
+            &options,
+        );
+        assert!(matches!(result, Err(Error::Clipped(1))));
+        let result = f32::write_samples_to_slice_with(
+            &[0.5, -1.0, 0.5],
+            &mut rawbytes,
+            &SampleFormat::F32LE,
+            &options,
+        );
+        assert_eq!(result.unwrap(), (3, 12, 0));
     }
 
     #[test]
-    fn check_f32_to_f64le() {
-        let val: f32 = 0.256789;
-        let exp = ((0.256789 as f32) as f64).to_le_bytes();
-        assert_eq!(val.to_f64_le(), (exp, false));
-        let val: f32 = -0.256789;
-        let exp = ((-0.256789 as f32) as f64).to_le_bytes();
-        assert_eq!(val.to_f64_le(), (exp, false));
-        let val: f32 = 1.1;
-        let exp = (1.0 as f64).to_le_bytes();
-        assert_eq!(val.to_f64_le(), (exp, true));
-        let val: f32 = -1.1;
-        let exp = (-1.0 as f64).to_le_bytes();
-        assert_eq!(val.to_f64_le(), (exp, true));
+    fn write_clip_wrap() {
+        let lsb = 1.0 / 32768.0;
+        let values = [1.0, 1.0 + lsb, -1.0 - lsb, 0.5, -1.0];
+        let mut data: Vec<u8> = Vec::new();
+        let nbr_clipped = f64::write_samples_with(
+            &values,
+            &mut data,
+            &SampleFormat::S16LE,
+            &clip_options(ClipPolicy::Wrap),
+        )
+        .unwrap();
+        assert_eq!(nbr_clipped, 3);
+        let ints: Vec<i16> = data
+            .chunks(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        assert_eq!(ints, vec![-32768, -32767, 32767, 16384, -32768]);
+
+        // unsigned and fixed point formats wrap in the same way
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples_with(
+            &[1.5, -1.25],
+            &mut data,
+            &SampleFormat::U8,
+            &clip_options(ClipPolicy::Wrap),
+        )
+        .unwrap();
+        assert_eq!(data, vec![0x40, 0xE0]);
+        let q2_6 = SampleFormat::FixedPoint(FixedPoint::le(2, 6, 1).unwrap());
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples_with(
+            &[2.5, -3.0],
+            &mut data,
+            &q2_6,
+            &clip_options(ClipPolicy::Wrap),
+        )
+        .unwrap();
+        assert_eq!(data, vec![0xA0, 0x40]);
     }
 
     #[test]
-    fn check_f32_to_f64be() {
-        let val: f32 = 0.256789;
-        let exp = ((0.256789 as f32) as f64).to_be_bytes();
-        assert_eq!(val.to_f64_be(), (exp, false));
-        let val: f32 = -0.256789;
-        let exp = ((-0.256789 as f32) as f64).to_be_bytes();
-        assert_eq!(val.to_f64_be(), (exp, false));
-        let val: f32 = 1.1;
-        let exp = (1.0 as f64).to_be_bytes();
-        assert_eq!(val.to_f64_be(), (exp, true));
-        let val: f32 = -1.1;
-        let exp = (-1.0 as f64).to_be_bytes();
-        assert_eq!(val.to_f64_be(), (exp, true));
+    fn write_clip_s64le() {
+        let values = [1.0, -1.0, 0.5];
+        let mut data: Vec<u8> = Vec::new();
+        assert_eq!(
+            f64::write_samples(&values, &mut data, &SampleFormat::S64LE).unwrap(),
+            1
+        );
+        let ints: Vec<i64> = data
+            .chunks(8)
+            .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(ints, vec![i64::MAX, i64::MIN, 1 << 62]);
+
+        let mut data: Vec<u8> = Vec::new();
+        let nbr_wrapped = f64::write_samples_with(
+            &values,
+            &mut data,
+            &SampleFormat::S64LE,
+            &clip_options(ClipPolicy::Wrap),
+        )
+        .unwrap();
+        assert_eq!(nbr_wrapped, 1);
+        let ints: Vec<i64> = data
+            .chunks(8)
+            .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(ints, vec![i64::MIN, i64::MIN, 1 << 62]);
     }
 
-    // -----------------
-    //  read/write many
-    // -----------------
+    #[test]
+    fn write_clip_pass_through() {
+        let values = [1.5, -2.0, 0.5, 1.0];
+        let options = clip_options(ClipPolicy::PassThrough);
+        for format in [
+            SampleFormat::F32LE,
+            SampleFormat::F64BE,
+            SampleFormat::F16LE,
+            SampleFormat::BF16BE,
+        ] {
+            let mut data: Vec<u8> = Vec::new();
+            let nbr_clipped =
+                f64::write_samples_with(&values, &mut data, &format, &options).unwrap();
+            assert_eq!(nbr_clipped, 0);
+            let mut values2 = Vec::new();
+            let mut slice: &[u8] = &data;
+            f64::read_all_samples(&mut slice, &mut values2, &format).unwrap();
+            assert_eq!(values2, values, "{:?}", format);
+        }
+        // the integer formats are still clamped
+        let mut data: Vec<u8> = Vec::new();
+        let nbr_clipped =
+            f64::write_samples_with(&values, &mut data, &SampleFormat::S16LE, &options).unwrap();
+        assert_eq!(nbr_clipped, 3);
+        assert_eq!(data[0..2], [0xFF, 0x7F]);
+    }
 
     #[test]
-    fn write_read_to_slice_s16le() {
-        // write data, then read it back into a slice of the same length.
-        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+    fn roundtrip_s16le_no_bias() {
+        // values between the representable ones, of both signs
+        let values: Vec<f64> = (0..2000)
+            .map(|n| (n as f64 - 1000.0 + ((n % 10) as f64 + 0.5) / 10.0) / 32768.0)
+            .collect();
         let mut data: Vec<u8> = Vec::new();
         f64::write_samples(&values, &mut data, &SampleFormat::S16LE).unwrap();
-        let mut values2 = vec![0.0; 7];
+        let mut values2: Vec<f32> = Vec::new();
         let mut slice: &[u8] = &data;
-        f64::read_samples(&mut slice, &mut values2, &SampleFormat::S16LE).unwrap();
-        assert_eq!(values, values2);
+        f32::read_all_samples(&mut slice, &mut values2, &SampleFormat::S16LE).unwrap();
+        let errors: Vec<f64> = values
+            .iter()
+            .zip(values2.iter())
+            .map(|(a, b)| (*b as f64 - a) * 32768.0)
+            .collect();
+        assert!(errors.iter().all(|err| err.abs() <= 0.5));
+        let positive_bias = errors[1000..].iter().sum::<f64>() / 1000.0;
+        let negative_bias = errors[..1000].iter().sum::<f64>() / 1000.0;
+        assert!(positive_bias.abs() < 0.05);
+        assert!(negative_bias.abs() < 0.05);
     }
 
     #[test]
-    fn write_read_to_slice_s16be() {
-        // write data, then read it back into a slice of the same length.
-        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+    fn write_dithered_s16le() {
+        let values = vec![0.25 + 0.3 / 32768.0; 3000];
+        let mut dither = Dither::new(DitherKind::Triangular, 5);
         let mut data: Vec<u8> = Vec::new();
-        f64::write_samples(&values, &mut data, &SampleFormat::S16BE).unwrap();
-        let mut values2 = vec![0.0; 7];
-        let mut slice: &[u8] = &data;
-        f64::read_samples(&mut slice, &mut values2, &SampleFormat::S16BE).unwrap();
-        assert_eq!(values, values2);
+        f64::write_samples_dithered(&values, &mut data, &SampleFormat::S16LE, &mut dither).unwrap();
+        let ints: Vec<i16> = data
+            .chunks(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        assert_eq!(ints.len(), 3000);
+        assert!(ints.iter().all(|v| (8191..=8193).contains(v)));
+        assert!(ints.iter().any(|v| *v != ints[0]));
+
+        // the same seed gives the same output
+        let mut dither = Dither::new(DitherKind::Triangular, 5);
+        let mut data2: Vec<u8> = Vec::new();
+        f64::write_samples_dithered(&values, &mut data2, &SampleFormat::S16LE, &mut dither)
+            .unwrap();
+        assert_eq!(data, data2);
     }
 
     #[test]
-    fn write_read_all_s32le() {
-        // write data, then read all of it back into a dynamically allocated vec.
-        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+    fn write_dithered_float_unchanged() {
+        let values = vec![0.1, -0.2, 0.3];
+        let mut dither = Dither::new(DitherKind::Rectangular, 5);
         let mut data: Vec<u8> = Vec::new();
-        f64::write_samples(&values, &mut data, &SampleFormat::S32LE).unwrap();
-        let mut values2 = Vec::new();
+        f32::write_samples_dithered(&values, &mut data, &SampleFormat::F32LE, &mut dither).unwrap();
+        let mut expected: Vec<u8> = Vec::new();
+        f32::write_samples(&values, &mut expected, &SampleFormat::F32LE).unwrap();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn read_all_incomplete_sample() {
+        // a trailing incomplete sample is reported, and the complete samples are kept
+        let data = [0, 64, 0, 0, 0];
+        let mut values = Vec::new();
         let mut slice: &[u8] = &data;
-        f64::read_all_samples(&mut slice, &mut values2, &SampleFormat::S32LE).unwrap();
-        assert_eq!(values, values2);
+        let err =
+            f64::read_all_samples(&mut slice, &mut values, &SampleFormat::S24BE3).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TruncatedSample {
+                samples: 1,
+                bytes: 2
+            }
+        ));
+        assert_eq!(values, vec![0.001953125]);
+
+        let mut values = vec![0.0; 4];
+        let mut slice: &[u8] = &data;
+        let err = f64::read_samples(&mut slice, &mut values, &SampleFormat::S24BE3).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TruncatedSample {
+                samples: 1,
+                bytes: 2
+            }
+        ));
+        assert_eq!(values[0], 0.001953125);
     }
 
     #[test]
-    fn write_read_all_s32be() {
-        // write data, then read all of it back into a dynamically allocated vec.
-        let values = vec![-0.5, -0.25, -0.125, 0.0, 0.125, 0.25, 0.5];
+    fn read_with_leftover_s24le3() {
+        let values = vec![-0.5, -0.25, 0.25, 0.5];
         let mut data: Vec<u8> = Vec::new();
-        f64::write_samples(&values, &mut data, &SampleFormat::S32BE).unwrap();
-        let mut values2 = Vec::new();
+        f32::write_samples(&values, &mut data, &SampleFormat::S24LE3).unwrap();
+        // split the data in the middle of the third sample
+        let mut leftover = Vec::new();
+        let mut values2 = vec![0.0; 4];
+        let mut first: &[u8] = &data[0..7];
+        let nbr_read = f32::read_samples_with_leftover(
+            &mut first,
+            &mut values2,
+            &mut leftover,
+            &SampleFormat::S24LE3,
+        )
+        .unwrap();
+        assert_eq!(nbr_read, 2);
+        assert_eq!(leftover, data[6..7]);
+        // the leftover byte is carried into the next call
+        let mut second: &[u8] = &data[7..];
+        let nbr_read = f32::read_samples_with_leftover(
+            &mut second,
+            &mut values2[2..],
+            &mut leftover,
+            &SampleFormat::S24LE3,
+        )
+        .unwrap();
+        assert_eq!(nbr_read, 2);
+        assert!(leftover.is_empty());
+        assert_eq!(values2, values);
+    }
+
+    #[test]
+    fn read_all_with_leftover_s16be() {
+        let data = [0x00, 0x00, 0x20, 0x20];
+        let mut leftover = vec![0xC0];
+        let mut values = Vec::new();
         let mut slice: &[u8] = &data;
-        f64::read_all_samples(&mut slice, &mut values2, &SampleFormat::S32BE).unwrap();
-        assert_eq!(values, values2);
+        let nbr_read = f64::read_all_samples_with_leftover(
+            &mut slice,
+            &mut values,
+            &mut leftover,
+            &SampleFormat::S16BE,
+        )
+        .unwrap();
+        assert_eq!(nbr_read, 2);
+        assert_eq!(values, vec![-0.5, 0.0009765625]);
+        assert_eq!(leftover, vec![0x20]);
+    }
+
+    #[test]
+    fn read_with_leftover_no_room() {
+        // the previous leftover is kept when the slice has no room for a whole sample
+        let mut leftover = vec![1, 2];
+        let mut empty: &[u8] = &[];
+        let nbr_read = f32::read_samples_with_leftover(
+            &mut empty,
+            &mut [],
+            &mut leftover,
+            &SampleFormat::S24LE3,
+        )
+        .unwrap();
+        assert_eq!(nbr_read, 0);
+        assert_eq!(leftover, vec![1, 2]);
+        let mut values = vec![0.0; 1];
+        let nbr_read = f32::read_samples_with_leftover(
+            &mut empty,
+            &mut values,
+            &mut leftover,
+            &SampleFormat::S12PackedLE,
+        )
+        .unwrap();
+        assert_eq!(nbr_read, 0);
+        assert_eq!(leftover, vec![1, 2]);
+        // and the bytes that are not needed to fill the slice
+        let mut leftover = vec![0, 0, 64, 0, 0, 32, 7];
+        let mut source: &[u8] = &[8, 9];
+        let nbr_read = f32::read_samples_with_leftover(
+            &mut source,
+            &mut values,
+            &mut leftover,
+            &SampleFormat::S24LE3,
+        )
+        .unwrap();
+        assert_eq!(nbr_read, 1);
+        assert_eq!(values, vec![0.5]);
+        assert_eq!(leftover, vec![0, 0, 32, 7]);
+        assert_eq!(source, &[8, 9]);
+        // reading all samples always uses the previous bytes
+        let mut leftover = vec![1, 2];
+        let mut values = Vec::new();
+        let nbr_read = f32::read_all_samples_with_leftover(
+            &mut empty,
+            &mut values,
+            &mut leftover,
+            &SampleFormat::S24LE3,
+        )
+        .unwrap();
+        assert_eq!(nbr_read, 0);
+        assert_eq!(leftover, vec![1, 2]);
+    }
+
+    #[test]
+    fn read_with_leftover_s12packed_split() {
+        // a stream split at any byte gives the same values, also inside a pair
+        let data = [0, 2, 192, 0, 1, 240];
+        let mut expected = Vec::new();
+        f32::read_all_samples(&mut &data[..], &mut expected, &SampleFormat::S12PackedLE).unwrap();
+        assert_eq!(expected.len(), 4);
+        for split in 0..=data.len() {
+            let mut leftover = Vec::new();
+            let mut values = vec![0.0; 4];
+            let mut nbr_read = 0;
+            for mut piece in [&data[..split], &data[split..]] {
+                nbr_read += f32::read_samples_with_leftover(
+                    &mut piece,
+                    &mut values[nbr_read..],
+                    &mut leftover,
+                    &SampleFormat::S12PackedLE,
+                )
+                .unwrap();
+            }
+            assert_eq!(nbr_read, 4, "split at {}", split);
+            assert!(leftover.is_empty());
+            assert_eq!(values, expected, "split at {}", split);
+
+            let mut values = Vec::new();
+            for mut piece in [&data[..split], &data[split..]] {
+                f32::read_all_samples_with_leftover(
+                    &mut piece,
+                    &mut values,
+                    &mut leftover,
+                    &SampleFormat::S12PackedLE,
+                )
+                .unwrap();
+            }
+            assert!(leftover.is_empty());
+            assert_eq!(values, expected, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn error_variants() {
+        fn assert_send_sync<E: Send + Sync + std::error::Error>(_err: &E) {}
+        let data = vec![0_u8; 17];
+        let mut values = Vec::new();
+        let mut slice: &[u8] = &data;
+        let err =
+            f32::read_all_frames(&mut slice, &mut values, 2, &SampleFormat::S24LE3).unwrap_err();
+        assert_send_sync(&err);
+        assert!(matches!(
+            err,
+            Error::TruncatedFrame {
+                frames: 2,
+                bytes: 5
+            }
+        ));
+        let mut data: Vec<u8> = Vec::new();
+        let err =
+            f32::write_frames(&[0.0; 5], 5, &mut data, &SampleFormat::S12PackedLE).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedFormat(_)));
+        let err = f32::write_frames(&[0.0; 5], 0, &mut data, &SampleFormat::S16LE).unwrap_err();
+        assert!(matches!(err, Error::InvalidParameter(_)));
+        assert!(matches!(
+            FixedPoint::le(0, 8, 1),
+            Err(Error::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn error_io() {
+        struct FailingWriter;
+        impl std::io::Write for FailingWriter {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("failed"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let err = f64::write_samples(&[0.5], &mut FailingWriter, &SampleFormat::S16LE).unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+        assert!(std::error::Error::source(&err).is_some());
     }
 
     #[test]
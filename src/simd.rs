@@ -0,0 +1,1446 @@
+//! Vectorised conversion kernels.
+//!
+//! The kernels convert between f32 or f64 sample values and the S16, S24 (3 bytes) and S32 formats,
+//! as well as the float format of the same size, in both byte orders.
+//! The instruction set is detected at runtime.
+//! On x86_64 the kernels use AVX2 when the CPU supports it, and SSE2 otherwise.
+//! On little endian aarch64 they use NEON.
+//! Only whole blocks of samples are converted,
+//! and the remaining samples, as well as all other formats and targets, use the scalar conversions of [crate::Sample].
+//!
+//! The kernels give bit-identical results to the scalar code, including the rounding,
+//! the handling of NaN and infinite values, and the reported number of clipped values.
+//! When converting to an integer format, the scaled values are limited to the range of the format before rounding,
+//! with limits chosen so that the rounded values are the same as for the scalar code.
+//! The rounding relies on the default rounding mode, round to nearest with ties to even.
+
+use crate::SampleFormat;
+
+/// A function that converts whole blocks of raw bytes to sample values, and returns the number of converted samples.
+pub(crate) type ReadKernel<T> = fn(&[u8], &mut [T], &SampleFormat) -> usize;
+
+/// A function that converts whole blocks of sample values to raw bytes,
+/// and returns the number of converted samples and the number of clipped samples.
+pub(crate) type WriteKernel<T> = fn(&[T], &mut [u8], &SampleFormat) -> (usize, usize);
+
+/// Call the kernel of an instruction set for a format.
+/// The integer kernels take the number of bytes per sample and the byte order as const parameters,
+/// and the float kernels take the byte order.
+/// Formats without a kernel convert nothing.
+macro_rules! select_kernel {
+    ($isa:ident, $ints:ident, $floats:ident, $float_le:pat, $float_be:pat, $src:expr, $dst:expr, $format:expr) => {
+        match $format {
+            SampleFormat::S16LE => $isa::$ints::<2, false>($src, $dst),
+            SampleFormat::S16BE => $isa::$ints::<2, true>($src, $dst),
+            SampleFormat::S24LE3 => $isa::$ints::<3, false>($src, $dst),
+            SampleFormat::S24BE3 => $isa::$ints::<3, true>($src, $dst),
+            SampleFormat::S32LE => $isa::$ints::<4, false>($src, $dst),
+            SampleFormat::S32BE => $isa::$ints::<4, true>($src, $dst),
+            $float_le => $isa::$floats::<false>($src, $dst),
+            $float_be => $isa::$floats::<true>($src, $dst),
+            _ => Default::default(),
+        }
+    };
+}
+
+/// Convert with the kernels of the best instruction set that the CPU supports.
+macro_rules! dispatch {
+    ($ints:ident, $floats:ident, $float_le:pat, $float_be:pat, $src:ident, $dst:ident, $format:ident) => {{
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                // Safety: the required target feature is available.
+                return unsafe {
+                    select_kernel!(avx2, $ints, $floats, $float_le, $float_be, $src, $dst, $format)
+                };
+            }
+            if is_x86_feature_detected!("sse2") {
+                // Safety: the required target feature is available.
+                return unsafe {
+                    select_kernel!(sse2, $ints, $floats, $float_le, $float_be, $src, $dst, $format)
+                };
+            }
+        }
+        #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                // Safety: the required target feature is available.
+                return unsafe {
+                    select_kernel!(neon, $ints, $floats, $float_le, $float_be, $src, $dst, $format)
+                };
+            }
+        }
+        #[cfg(not(any(
+            target_arch = "x86_64",
+            all(target_arch = "aarch64", target_endian = "little")
+        )))]
+        let _ = ($src, $dst, $format);
+        Default::default()
+    }};
+}
+
+/// Convert raw bytes to f32 sample values with a vectorised kernel, if there is one for the format on this CPU.
+/// The number of converted samples is returned.
+pub(crate) fn bytes_to_f32(rawbytes: &[u8], values: &mut [f32], format: &SampleFormat) -> usize {
+    dispatch!(
+        int_to_f32,
+        float_to_f32,
+        SampleFormat::F32LE,
+        SampleFormat::F32BE,
+        rawbytes,
+        values,
+        format
+    )
+}
+
+/// Convert f32 sample values to raw bytes with a vectorised kernel, if there is one for the format on this CPU.
+/// The number of converted samples and the number of clipped samples are returned.
+pub(crate) fn f32_to_bytes(
+    values: &[f32],
+    rawbytes: &mut [u8],
+    format: &SampleFormat,
+) -> (usize, usize) {
+    dispatch!(
+        f32_to_int,
+        f32_to_float,
+        SampleFormat::F32LE,
+        SampleFormat::F32BE,
+        values,
+        rawbytes,
+        format
+    )
+}
+
+/// Convert raw bytes to f64 sample values with a vectorised kernel, if there is one for the format on this CPU.
+/// The number of converted samples is returned.
+pub(crate) fn bytes_to_f64(rawbytes: &[u8], values: &mut [f64], format: &SampleFormat) -> usize {
+    dispatch!(
+        int_to_f64,
+        float_to_f64,
+        SampleFormat::F64LE,
+        SampleFormat::F64BE,
+        rawbytes,
+        values,
+        format
+    )
+}
+
+/// Convert f64 sample values to raw bytes with a vectorised kernel, if there is one for the format on this CPU.
+/// The number of converted samples and the number of clipped samples are returned.
+pub(crate) fn f64_to_bytes(
+    values: &[f64],
+    rawbytes: &mut [u8],
+    format: &SampleFormat,
+) -> (usize, usize) {
+    dispatch!(
+        f64_to_int,
+        f64_to_float,
+        SampleFormat::F64LE,
+        SampleFormat::F64BE,
+        values,
+        rawbytes,
+        format
+    )
+}
+
+/// The scaling and limits used when converting values to an integer format.
+/// The limits apply to the scaled values, before rounding.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_endian = "little")
+))]
+#[derive(Clone, Copy)]
+struct Limits<T> {
+    /// The factor between a sample value and the integer.
+    scale: T,
+    /// Values below this are clipped.
+    clip_below: T,
+    /// Values from this and up are clipped.
+    clip_from: T,
+    /// The smallest value, before rounding.
+    low: T,
+    /// The largest value, before rounding.
+    high: T,
+}
+
+/// Get the limits for converting f32 values to an integer format with the given number of bytes.
+/// The scalar 24 bit conversion clamps the value multiplied by 256 to the range of an i32,
+/// and i32::MAX rounds up to 2^31 as f32.
+/// This means that 2^23 is written as the largest value without being counted as clipped,
+/// and that the same goes for 2^31 in the 32 bit format.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_endian = "little")
+))]
+#[inline]
+fn f32_limits(width: usize) -> Limits<f32> {
+    match width {
+        2 => Limits {
+            scale: 32768.0,
+            clip_below: -32768.5,
+            clip_from: 32767.5,
+            low: -32768.0,
+            high: 32767.0,
+        },
+        3 => Limits {
+            scale: 8388608.0,
+            clip_below: -8388608.0,
+            clip_from: 8388609.0,
+            low: -8388608.0,
+            high: 8388607.0,
+        },
+        _ => Limits {
+            scale: 2147483648.0,
+            clip_below: -2147483648.0,
+            clip_from: 2147483904.0,
+            low: -2147483648.0,
+            high: 2147483648.0,
+        },
+    }
+}
+
+/// Get the limits for converting f64 values to an integer format with the given number of bytes.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_endian = "little")
+))]
+#[inline]
+fn f64_limits(width: usize) -> Limits<f64> {
+    match width {
+        2 => Limits {
+            scale: 32768.0,
+            clip_below: -32768.5,
+            clip_from: 32767.5,
+            low: -32768.0,
+            high: 32767.0,
+        },
+        3 => Limits {
+            scale: 8388608.0,
+            clip_below: -8388608.5,
+            clip_from: 8388607.5,
+            low: -8388608.0,
+            high: 8388607.0,
+        },
+        _ => Limits {
+            scale: 2147483648.0,
+            clip_below: -2147483648.5,
+            clip_from: 2147483647.5,
+            low: -2147483648.0,
+            high: 2147483647.0,
+        },
+    }
+}
+
+/// Get the factor to multiply the loaded integers with, when reading a format with the given number of bytes.
+/// The 24 bit values are loaded to the upper 3 bytes of an i32.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_endian = "little")
+))]
+#[inline]
+fn read_scale(width: usize) -> f64 {
+    match width {
+        2 => 1.0 / 32768.0,
+        _ => 1.0 / 2147483648.0,
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use super::{f32_limits, f64_limits, read_scale, Limits};
+    use std::arch::x86_64::*;
+
+    /// Byte shuffle that reverses the byte order of each 16 bit value.
+    unsafe fn swap16() -> __m128i {
+        _mm_setr_epi8(1, 0, 3, 2, 5, 4, 7, 6, 9, 8, 11, 10, 13, 12, 15, 14)
+    }
+
+    /// Byte shuffle that reverses the byte order of each 32 bit value.
+    unsafe fn swap32() -> __m256i {
+        _mm256_setr_epi8(
+            3, 2, 1, 0, 7, 6, 5, 4, 11, 10, 9, 8, 15, 14, 13, 12, 3, 2, 1, 0, 7, 6, 5, 4, 11, 10,
+            9, 8, 15, 14, 13, 12,
+        )
+    }
+
+    /// Byte shuffle that reverses the byte order of each 64 bit value.
+    unsafe fn swap64() -> __m256i {
+        _mm256_setr_epi8(
+            7, 6, 5, 4, 3, 2, 1, 0, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, 15, 14,
+            13, 12, 11, 10, 9, 8,
+        )
+    }
+
+    /// Byte shuffle that moves 4 packed 24 bit values of each lane to the upper 3 bytes of 32 bit values.
+    /// The upper lane is loaded 4 bytes early, to avoid reading past the end of the block.
+    unsafe fn unpack24(big_endian: bool) -> __m256i {
+        if big_endian {
+            _mm256_setr_epi8(
+                -1, 2, 1, 0, -1, 5, 4, 3, -1, 8, 7, 6, -1, 11, 10, 9, -1, 6, 5, 4, -1, 9, 8, 7, -1,
+                12, 11, 10, -1, 15, 14, 13,
+            )
+        } else {
+            _mm256_setr_epi8(
+                -1, 0, 1, 2, -1, 3, 4, 5, -1, 6, 7, 8, -1, 9, 10, 11, -1, 4, 5, 6, -1, 7, 8, 9, -1,
+                10, 11, 12, -1, 13, 14, 15,
+            )
+        }
+    }
+
+    /// Byte shuffle that packs the lower 3 bytes of the 32 bit values of each lane to the first 12 bytes.
+    unsafe fn pack24(big_endian: bool) -> __m256i {
+        if big_endian {
+            _mm256_setr_epi8(
+                2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1, 2, 1, 0, 6, 5, 4, 10, 9, 8,
+                14, 13, 12, -1, -1, -1, -1,
+            )
+        } else {
+            _mm256_setr_epi8(
+                0, 1, 2, 4, 5, 6, 8, 9, 10, 12, 13, 14, -1, -1, -1, -1, 0, 1, 2, 4, 5, 6, 8, 9, 10,
+                12, 13, 14, -1, -1, -1, -1,
+            )
+        }
+    }
+
+    /// Load 8 integer samples as i32, with the 24 bit values in the upper 3 bytes.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn load<const WIDTH: usize, const BE: bool>(src: *const u8) -> __m256i {
+        match WIDTH {
+            2 => {
+                let mut ints = _mm_loadu_si128(src as *const __m128i);
+                if BE {
+                    ints = _mm_shuffle_epi8(ints, swap16());
+                }
+                _mm256_cvtepi16_epi32(ints)
+            }
+            3 => {
+                let low = _mm_loadu_si128(src as *const __m128i);
+                let high = _mm_loadu_si128(src.add(8) as *const __m128i);
+                _mm256_shuffle_epi8(_mm256_set_m128i(high, low), unpack24(BE))
+            }
+            _ => {
+                let ints = _mm256_loadu_si256(src as *const __m256i);
+                if BE {
+                    _mm256_shuffle_epi8(ints, swap32())
+                } else {
+                    ints
+                }
+            }
+        }
+    }
+
+    /// Store 8 integer samples, from i32 values that are within the range of the format.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn store<const WIDTH: usize, const BE: bool>(dst: *mut u8, ints: __m256i) {
+        match WIDTH {
+            2 => {
+                let packed = _mm256_permute4x64_epi64::<0b1000>(_mm256_packs_epi32(ints, ints));
+                let mut bytes = _mm256_castsi256_si128(packed);
+                if BE {
+                    bytes = _mm_shuffle_epi8(bytes, swap16());
+                }
+                _mm_storeu_si128(dst as *mut __m128i, bytes);
+            }
+            3 => {
+                let bytes = _mm256_shuffle_epi8(ints, pack24(BE));
+                // write 12 bytes from each lane, without writing past the end of the block
+                _mm_storeu_si128(dst as *mut __m128i, _mm256_castsi256_si128(bytes));
+                let high = _mm256_extracti128_si256::<1>(bytes);
+                _mm_storel_epi64(dst.add(12) as *mut __m128i, high);
+                (dst.add(20) as *mut i32).write_unaligned(_mm_extract_epi32::<2>(high));
+            }
+            _ => {
+                let ints = if BE {
+                    _mm256_shuffle_epi8(ints, swap32())
+                } else {
+                    ints
+                };
+                _mm256_storeu_si256(dst as *mut __m256i, ints);
+            }
+        }
+    }
+
+    /// Scale 8 f32 values and convert them to integers, in the same way as the scalar `to_*` methods.
+    /// NaN becomes zero, and 2^31 becomes the largest i32, like a saturating cast.
+    /// The integers and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn quantise_f32(values: *const f32, limits: Limits<f32>) -> (__m256i, usize) {
+        let scaled = _mm256_mul_ps(_mm256_loadu_ps(values), _mm256_set1_ps(limits.scale));
+        let clipped = _mm256_or_ps(
+            _mm256_cmp_ps::<_CMP_GE_OQ>(scaled, _mm256_set1_ps(limits.clip_from)),
+            _mm256_cmp_ps::<_CMP_LT_OQ>(scaled, _mm256_set1_ps(limits.clip_below)),
+        );
+        let not_nan = _mm256_cmp_ps::<_CMP_ORD_Q>(scaled, scaled);
+        let limited = _mm256_min_ps(
+            _mm256_max_ps(_mm256_and_ps(scaled, not_nan), _mm256_set1_ps(limits.low)),
+            _mm256_set1_ps(limits.high),
+        );
+        // 2^31 converts to i32::MIN, flip all bits to get i32::MAX
+        let overflow = _mm256_cmp_ps::<_CMP_GE_OQ>(limited, _mm256_set1_ps(2147483648.0));
+        let ints = _mm256_xor_si256(_mm256_cvtps_epi32(limited), _mm256_castps_si256(overflow));
+        (ints, _mm256_movemask_ps(clipped).count_ones() as usize)
+    }
+
+    /// Scale 4 f64 values and convert them to integers, in the same way as the scalar `to_*` methods.
+    /// NaN becomes zero.
+    /// The integers and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn quantise_f64(values: *const f64, limits: Limits<f64>) -> (__m128i, usize) {
+        let scaled = _mm256_mul_pd(_mm256_loadu_pd(values), _mm256_set1_pd(limits.scale));
+        let clipped = _mm256_or_pd(
+            _mm256_cmp_pd::<_CMP_GE_OQ>(scaled, _mm256_set1_pd(limits.clip_from)),
+            _mm256_cmp_pd::<_CMP_LT_OQ>(scaled, _mm256_set1_pd(limits.clip_below)),
+        );
+        let not_nan = _mm256_cmp_pd::<_CMP_ORD_Q>(scaled, scaled);
+        let limited = _mm256_min_pd(
+            _mm256_max_pd(_mm256_and_pd(scaled, not_nan), _mm256_set1_pd(limits.low)),
+            _mm256_set1_pd(limits.high),
+        );
+        (
+            _mm256_cvtpd_epi32(limited),
+            _mm256_movemask_pd(clipped).count_ones() as usize,
+        )
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn int_to_f32<const WIDTH: usize, const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f32],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / (8 * WIDTH)).min(values.len() / 8);
+        let scale = _mm256_set1_ps(read_scale(WIDTH) as f32);
+        for block in 0..nbr_blocks {
+            let ints = load::<WIDTH, BE>(rawbytes.as_ptr().add(8 * WIDTH * block));
+            _mm256_storeu_ps(
+                values.as_mut_ptr().add(8 * block),
+                _mm256_mul_ps(_mm256_cvtepi32_ps(ints), scale),
+            );
+        }
+        8 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn f32_to_int<const WIDTH: usize, const BE: bool>(
+        values: &[f32],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / (8 * WIDTH)).min(values.len() / 8);
+        let limits = f32_limits(WIDTH);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let (ints, clipped) = quantise_f32(values.as_ptr().add(8 * block), limits);
+            nbr_clipped += clipped;
+            store::<WIDTH, BE>(rawbytes.as_mut_ptr().add(8 * WIDTH * block), ints);
+        }
+        (8 * nbr_blocks, nbr_clipped)
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn int_to_f64<const WIDTH: usize, const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f64],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / (8 * WIDTH)).min(values.len() / 8);
+        let scale = _mm256_set1_pd(read_scale(WIDTH));
+        for block in 0..nbr_blocks {
+            let ints = load::<WIDTH, BE>(rawbytes.as_ptr().add(8 * WIDTH * block));
+            let low = _mm256_cvtepi32_pd(_mm256_castsi256_si128(ints));
+            let high = _mm256_cvtepi32_pd(_mm256_extracti128_si256::<1>(ints));
+            let dst = values.as_mut_ptr().add(8 * block);
+            _mm256_storeu_pd(dst, _mm256_mul_pd(low, scale));
+            _mm256_storeu_pd(dst.add(4), _mm256_mul_pd(high, scale));
+        }
+        8 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn f64_to_int<const WIDTH: usize, const BE: bool>(
+        values: &[f64],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / (8 * WIDTH)).min(values.len() / 8);
+        let limits = f64_limits(WIDTH);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let src = values.as_ptr().add(8 * block);
+            let (low, clipped_low) = quantise_f64(src, limits);
+            let (high, clipped_high) = quantise_f64(src.add(4), limits);
+            nbr_clipped += clipped_low + clipped_high;
+            store::<WIDTH, BE>(
+                rawbytes.as_mut_ptr().add(8 * WIDTH * block),
+                _mm256_set_m128i(high, low),
+            );
+        }
+        (8 * nbr_blocks, nbr_clipped)
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn float_to_f32<const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f32],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / 32).min(values.len() / 8);
+        for block in 0..nbr_blocks {
+            let mut bits = _mm256_loadu_si256(rawbytes.as_ptr().add(32 * block) as *const __m256i);
+            if BE {
+                bits = _mm256_shuffle_epi8(bits, swap32());
+            }
+            _mm256_storeu_ps(
+                values.as_mut_ptr().add(8 * block),
+                _mm256_castsi256_ps(bits),
+            );
+        }
+        8 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn f32_to_float<const BE: bool>(
+        values: &[f32],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / 32).min(values.len() / 8);
+        let one = _mm256_set1_ps(1.0);
+        let minus_one = _mm256_set1_ps(-1.0);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            // clamp to -1.0 .. +1.0 like the scalar conversion, where +1.0 is also counted as clipped
+            let value = _mm256_loadu_ps(values.as_ptr().add(8 * block));
+            let above = _mm256_cmp_ps::<_CMP_GE_OQ>(value, one);
+            let below = _mm256_cmp_ps::<_CMP_LT_OQ>(value, minus_one);
+            let clamped = _mm256_blendv_ps(_mm256_blendv_ps(value, minus_one, below), one, above);
+            nbr_clipped += _mm256_movemask_ps(_mm256_or_ps(above, below)).count_ones() as usize;
+            let mut bits = _mm256_castps_si256(clamped);
+            if BE {
+                bits = _mm256_shuffle_epi8(bits, swap32());
+            }
+            _mm256_storeu_si256(rawbytes.as_mut_ptr().add(32 * block) as *mut __m256i, bits);
+        }
+        (8 * nbr_blocks, nbr_clipped)
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn float_to_f64<const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f64],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / 32).min(values.len() / 4);
+        for block in 0..nbr_blocks {
+            let mut bits = _mm256_loadu_si256(rawbytes.as_ptr().add(32 * block) as *const __m256i);
+            if BE {
+                bits = _mm256_shuffle_epi8(bits, swap64());
+            }
+            _mm256_storeu_pd(
+                values.as_mut_ptr().add(4 * block),
+                _mm256_castsi256_pd(bits),
+            );
+        }
+        4 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn f64_to_float<const BE: bool>(
+        values: &[f64],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / 32).min(values.len() / 4);
+        let one = _mm256_set1_pd(1.0);
+        let minus_one = _mm256_set1_pd(-1.0);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            // clamp to -1.0 .. +1.0 like the scalar conversion, where +1.0 is also counted as clipped
+            let value = _mm256_loadu_pd(values.as_ptr().add(4 * block));
+            let above = _mm256_cmp_pd::<_CMP_GE_OQ>(value, one);
+            let below = _mm256_cmp_pd::<_CMP_LT_OQ>(value, minus_one);
+            let clamped = _mm256_blendv_pd(_mm256_blendv_pd(value, minus_one, below), one, above);
+            nbr_clipped += _mm256_movemask_pd(_mm256_or_pd(above, below)).count_ones() as usize;
+            let mut bits = _mm256_castpd_si256(clamped);
+            if BE {
+                bits = _mm256_shuffle_epi8(bits, swap64());
+            }
+            _mm256_storeu_si256(rawbytes.as_mut_ptr().add(32 * block) as *mut __m256i, bits);
+        }
+        (4 * nbr_blocks, nbr_clipped)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+    use super::{f32_limits, f64_limits, read_scale, Limits};
+    use std::arch::x86_64::*;
+
+    /// Reverse the byte order of each 16 bit value.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn swap16(value: __m128i) -> __m128i {
+        _mm_or_si128(_mm_slli_epi16::<8>(value), _mm_srli_epi16::<8>(value))
+    }
+
+    /// Reverse the byte order of each 32 bit value.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn swap32(value: __m128i) -> __m128i {
+        let words = _mm_shufflelo_epi16::<0b10_11_00_01>(value);
+        swap16(_mm_shufflehi_epi16::<0b10_11_00_01>(words))
+    }
+
+    /// Reverse the byte order of each 64 bit value.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn swap64(value: __m128i) -> __m128i {
+        let words = _mm_shufflelo_epi16::<0b00_01_10_11>(value);
+        swap16(_mm_shufflehi_epi16::<0b00_01_10_11>(words))
+    }
+
+    /// Load 4 integer samples as i32, with the 24 bit values in the upper 3 bytes.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn load<const WIDTH: usize, const BE: bool>(src: *const u8) -> __m128i {
+        match WIDTH {
+            2 => {
+                let mut ints = _mm_loadl_epi64(src as *const __m128i);
+                if BE {
+                    ints = swap16(ints);
+                }
+                // sign extend, by placing each value in the upper half of a 32 bit value and shifting it down
+                _mm_srai_epi32::<16>(_mm_unpacklo_epi16(ints, ints))
+            }
+            3 => {
+                // load bytes 0 to 7 and 4 to 11, to avoid reading past the end of the block
+                let bytes = _mm_unpacklo_epi64(
+                    _mm_loadl_epi64(src as *const __m128i),
+                    _mm_loadl_epi64(src.add(4) as *const __m128i),
+                );
+                // move each sample to the lower 3 bytes of a 32 bit value
+                let first = _mm_unpacklo_epi32(bytes, _mm_srli_si128::<3>(bytes));
+                let second =
+                    _mm_unpacklo_epi32(_mm_srli_si128::<10>(bytes), _mm_srli_si128::<13>(bytes));
+                let ints = _mm_unpacklo_epi64(first, second);
+                if BE {
+                    _mm_and_si128(swap32(ints), _mm_set1_epi32(0xFFFF_FF00_u32 as i32))
+                } else {
+                    _mm_slli_epi32::<8>(ints)
+                }
+            }
+            _ => {
+                let ints = _mm_loadu_si128(src as *const __m128i);
+                if BE {
+                    swap32(ints)
+                } else {
+                    ints
+                }
+            }
+        }
+    }
+
+    /// Store 4 integer samples, from i32 values that are within the range of the format.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn store<const WIDTH: usize, const BE: bool>(dst: *mut u8, ints: __m128i) {
+        match WIDTH {
+            2 => {
+                let mut bytes = _mm_packs_epi32(ints, ints);
+                if BE {
+                    bytes = swap16(bytes);
+                }
+                _mm_storel_epi64(dst as *mut __m128i, bytes);
+            }
+            3 => {
+                // place the bytes of each sample in the lower 3 bytes of the 32 bit value, in the order of the format
+                let ints = if BE {
+                    _mm_srli_epi32::<8>(swap32(ints))
+                } else {
+                    ints
+                };
+                // pack the two samples of each 64 bit value to its lower 6 bytes
+                let pairs = _mm_or_si128(
+                    _mm_and_si128(ints, _mm_set1_epi64x(0x0000_0000_00FF_FFFF)),
+                    _mm_and_si128(
+                        _mm_srli_epi64::<8>(ints),
+                        _mm_set1_epi64x(0x0000_FFFF_FF00_0000),
+                    ),
+                );
+                // and move the upper 6 bytes down, next to the lower 6 bytes
+                let bytes = _mm_or_si128(
+                    _mm_and_si128(pairs, _mm_set_epi64x(0, 0x0000_FFFF_FFFF_FFFF)),
+                    _mm_and_si128(
+                        _mm_srli_si128::<2>(pairs),
+                        _mm_set_epi64x(0x0000_0000_FFFF_FFFF, 0xFFFF_0000_0000_0000_u64 as i64),
+                    ),
+                );
+                // write 12 bytes, without writing past the end of the block
+                _mm_storel_epi64(dst as *mut __m128i, bytes);
+                let last = _mm_cvtsi128_si32(_mm_srli_si128::<8>(bytes));
+                (dst.add(8) as *mut i32).write_unaligned(last);
+            }
+            _ => {
+                let ints = if BE { swap32(ints) } else { ints };
+                _mm_storeu_si128(dst as *mut __m128i, ints);
+            }
+        }
+    }
+
+    /// Scale 4 f32 values and convert them to integers, in the same way as the scalar `to_*` methods.
+    /// NaN becomes zero, and 2^31 becomes the largest i32, like a saturating cast.
+    /// The integers and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn quantise_f32(values: *const f32, limits: Limits<f32>) -> (__m128i, usize) {
+        let scaled = _mm_mul_ps(_mm_loadu_ps(values), _mm_set1_ps(limits.scale));
+        let clipped = _mm_or_ps(
+            _mm_cmpge_ps(scaled, _mm_set1_ps(limits.clip_from)),
+            _mm_cmplt_ps(scaled, _mm_set1_ps(limits.clip_below)),
+        );
+        let not_nan = _mm_cmpord_ps(scaled, scaled);
+        let limited = _mm_min_ps(
+            _mm_max_ps(_mm_and_ps(scaled, not_nan), _mm_set1_ps(limits.low)),
+            _mm_set1_ps(limits.high),
+        );
+        // 2^31 converts to i32::MIN, flip all bits to get i32::MAX
+        let overflow = _mm_cmpge_ps(limited, _mm_set1_ps(2147483648.0));
+        let ints = _mm_xor_si128(_mm_cvtps_epi32(limited), _mm_castps_si128(overflow));
+        (ints, _mm_movemask_ps(clipped).count_ones() as usize)
+    }
+
+    /// Scale 2 f64 values and convert them to integers in the lower half,
+    /// in the same way as the scalar `to_*` methods.
+    /// NaN becomes zero.
+    /// The integers and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn quantise_f64(values: *const f64, limits: Limits<f64>) -> (__m128i, usize) {
+        let scaled = _mm_mul_pd(_mm_loadu_pd(values), _mm_set1_pd(limits.scale));
+        let clipped = _mm_or_pd(
+            _mm_cmpge_pd(scaled, _mm_set1_pd(limits.clip_from)),
+            _mm_cmplt_pd(scaled, _mm_set1_pd(limits.clip_below)),
+        );
+        let not_nan = _mm_cmpord_pd(scaled, scaled);
+        let limited = _mm_min_pd(
+            _mm_max_pd(_mm_and_pd(scaled, not_nan), _mm_set1_pd(limits.low)),
+            _mm_set1_pd(limits.high),
+        );
+        (
+            _mm_cvtpd_epi32(limited),
+            _mm_movemask_pd(clipped).count_ones() as usize,
+        )
+    }
+
+    /// Clamp 4 values to -1.0 .. +1.0 like the scalar conversion, where +1.0 is also counted as clipped.
+    /// The clamped values and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn clamp_f32(value: __m128) -> (__m128, usize) {
+        let one = _mm_set1_ps(1.0);
+        let minus_one = _mm_set1_ps(-1.0);
+        let above = _mm_cmpge_ps(value, one);
+        let below = _mm_cmplt_ps(value, minus_one);
+        let clipped = _mm_or_ps(above, below);
+        let clamped = _mm_or_ps(
+            _mm_andnot_ps(clipped, value),
+            _mm_or_ps(_mm_and_ps(above, one), _mm_and_ps(below, minus_one)),
+        );
+        (clamped, _mm_movemask_ps(clipped).count_ones() as usize)
+    }
+
+    /// Clamp 2 values to -1.0 .. +1.0 like the scalar conversion, where +1.0 is also counted as clipped.
+    /// The clamped values and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    unsafe fn clamp_f64(value: __m128d) -> (__m128d, usize) {
+        let one = _mm_set1_pd(1.0);
+        let minus_one = _mm_set1_pd(-1.0);
+        let above = _mm_cmpge_pd(value, one);
+        let below = _mm_cmplt_pd(value, minus_one);
+        let clipped = _mm_or_pd(above, below);
+        let clamped = _mm_or_pd(
+            _mm_andnot_pd(clipped, value),
+            _mm_or_pd(_mm_and_pd(above, one), _mm_and_pd(below, minus_one)),
+        );
+        (clamped, _mm_movemask_pd(clipped).count_ones() as usize)
+    }
+
+    /// # Safety
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn int_to_f32<const WIDTH: usize, const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f32],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / (4 * WIDTH)).min(values.len() / 4);
+        let scale = _mm_set1_ps(read_scale(WIDTH) as f32);
+        for block in 0..nbr_blocks {
+            let ints = load::<WIDTH, BE>(rawbytes.as_ptr().add(4 * WIDTH * block));
+            _mm_storeu_ps(
+                values.as_mut_ptr().add(4 * block),
+                _mm_mul_ps(_mm_cvtepi32_ps(ints), scale),
+            );
+        }
+        4 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn f32_to_int<const WIDTH: usize, const BE: bool>(
+        values: &[f32],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / (4 * WIDTH)).min(values.len() / 4);
+        let limits = f32_limits(WIDTH);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let (ints, clipped) = quantise_f32(values.as_ptr().add(4 * block), limits);
+            nbr_clipped += clipped;
+            store::<WIDTH, BE>(rawbytes.as_mut_ptr().add(4 * WIDTH * block), ints);
+        }
+        (4 * nbr_blocks, nbr_clipped)
+    }
+
+    /// # Safety
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn int_to_f64<const WIDTH: usize, const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f64],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / (4 * WIDTH)).min(values.len() / 4);
+        let scale = _mm_set1_pd(read_scale(WIDTH));
+        for block in 0..nbr_blocks {
+            let ints = load::<WIDTH, BE>(rawbytes.as_ptr().add(4 * WIDTH * block));
+            let low = _mm_cvtepi32_pd(ints);
+            let high = _mm_cvtepi32_pd(_mm_srli_si128::<8>(ints));
+            let dst = values.as_mut_ptr().add(4 * block);
+            _mm_storeu_pd(dst, _mm_mul_pd(low, scale));
+            _mm_storeu_pd(dst.add(2), _mm_mul_pd(high, scale));
+        }
+        4 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn f64_to_int<const WIDTH: usize, const BE: bool>(
+        values: &[f64],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / (4 * WIDTH)).min(values.len() / 4);
+        let limits = f64_limits(WIDTH);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let src = values.as_ptr().add(4 * block);
+            let (low, clipped_low) = quantise_f64(src, limits);
+            let (high, clipped_high) = quantise_f64(src.add(2), limits);
+            nbr_clipped += clipped_low + clipped_high;
+            store::<WIDTH, BE>(
+                rawbytes.as_mut_ptr().add(4 * WIDTH * block),
+                _mm_unpacklo_epi64(low, high),
+            );
+        }
+        (4 * nbr_blocks, nbr_clipped)
+    }
+
+    /// # Safety
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn float_to_f32<const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f32],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / 16).min(values.len() / 4);
+        for block in 0..nbr_blocks {
+            let mut bits = _mm_loadu_si128(rawbytes.as_ptr().add(16 * block) as *const __m128i);
+            if BE {
+                bits = swap32(bits);
+            }
+            _mm_storeu_ps(values.as_mut_ptr().add(4 * block), _mm_castsi128_ps(bits));
+        }
+        4 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn f32_to_float<const BE: bool>(
+        values: &[f32],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / 16).min(values.len() / 4);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let (clamped, clipped) = clamp_f32(_mm_loadu_ps(values.as_ptr().add(4 * block)));
+            nbr_clipped += clipped;
+            let mut bits = _mm_castps_si128(clamped);
+            if BE {
+                bits = swap32(bits);
+            }
+            _mm_storeu_si128(rawbytes.as_mut_ptr().add(16 * block) as *mut __m128i, bits);
+        }
+        (4 * nbr_blocks, nbr_clipped)
+    }
+
+    /// # Safety
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn float_to_f64<const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f64],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / 16).min(values.len() / 2);
+        for block in 0..nbr_blocks {
+            let mut bits = _mm_loadu_si128(rawbytes.as_ptr().add(16 * block) as *const __m128i);
+            if BE {
+                bits = swap64(bits);
+            }
+            _mm_storeu_pd(values.as_mut_ptr().add(2 * block), _mm_castsi128_pd(bits));
+        }
+        2 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn f64_to_float<const BE: bool>(
+        values: &[f64],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / 16).min(values.len() / 2);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let (clamped, clipped) = clamp_f64(_mm_loadu_pd(values.as_ptr().add(2 * block)));
+            nbr_clipped += clipped;
+            let mut bits = _mm_castpd_si128(clamped);
+            if BE {
+                bits = swap64(bits);
+            }
+            _mm_storeu_si128(rawbytes.as_mut_ptr().add(16 * block) as *mut __m128i, bits);
+        }
+        (2 * nbr_blocks, nbr_clipped)
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+mod neon {
+    use super::{f32_limits, f64_limits, read_scale, Limits};
+    use std::arch::aarch64::*;
+
+    /// Table lookups that move 4 packed 24 bit values to the upper 3 bytes of 32 bit values.
+    /// The upper half is loaded 4 bytes early, to avoid reading past the end of the block.
+    const UNPACK24_LE: [u8; 16] = [255, 0, 1, 2, 255, 3, 4, 5, 255, 10, 11, 12, 255, 13, 14, 15];
+    const UNPACK24_BE: [u8; 16] = [255, 2, 1, 0, 255, 5, 4, 3, 255, 12, 11, 10, 255, 15, 14, 13];
+
+    /// Table lookups that pack the lower 3 bytes of 4 32 bit values to the first 12 bytes.
+    const PACK24_LE: [u8; 16] = [0, 1, 2, 4, 5, 6, 8, 9, 10, 12, 13, 14, 255, 255, 255, 255];
+    const PACK24_BE: [u8; 16] = [2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, 255, 255, 255, 255];
+
+    /// Load 4 integer samples as i32, with the 24 bit values in the upper 3 bytes.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn load<const WIDTH: usize, const BE: bool>(src: *const u8) -> int32x4_t {
+        match WIDTH {
+            2 => {
+                let mut bytes = vld1_u8(src);
+                if BE {
+                    bytes = vrev16_u8(bytes);
+                }
+                vmovl_s16(vreinterpret_s16_u8(bytes))
+            }
+            3 => {
+                let bytes = vcombine_u8(vld1_u8(src), vld1_u8(src.add(4)));
+                let table = if BE { UNPACK24_BE } else { UNPACK24_LE };
+                vreinterpretq_s32_u8(vqtbl1q_u8(bytes, vld1q_u8(table.as_ptr())))
+            }
+            _ => {
+                let mut bytes = vld1q_u8(src);
+                if BE {
+                    bytes = vrev32q_u8(bytes);
+                }
+                vreinterpretq_s32_u8(bytes)
+            }
+        }
+    }
+
+    /// Store 4 integer samples, from i32 values that are within the range of the format.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn store<const WIDTH: usize, const BE: bool>(dst: *mut u8, ints: int32x4_t) {
+        match WIDTH {
+            2 => {
+                let mut bytes = vreinterpret_u8_s16(vmovn_s32(ints));
+                if BE {
+                    bytes = vrev16_u8(bytes);
+                }
+                vst1_u8(dst, bytes);
+            }
+            3 => {
+                let table = if BE { PACK24_BE } else { PACK24_LE };
+                let bytes = vqtbl1q_u8(vreinterpretq_u8_s32(ints), vld1q_u8(table.as_ptr()));
+                // write 12 bytes, without writing past the end of the block
+                vst1_u8(dst, vget_low_u8(bytes));
+                let last = vgetq_lane_u32::<2>(vreinterpretq_u32_u8(bytes));
+                (dst.add(8) as *mut u32).write_unaligned(last);
+            }
+            _ => {
+                let mut bytes = vreinterpretq_u8_s32(ints);
+                if BE {
+                    bytes = vrev32q_u8(bytes);
+                }
+                vst1q_u8(dst, bytes);
+            }
+        }
+    }
+
+    /// Scale 4 f32 values and convert them to integers, in the same way as the scalar `to_*` methods.
+    /// NaN becomes zero, and 2^31 becomes the largest i32, since the conversion saturates.
+    /// The integers and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn quantise_f32(values: *const f32, limits: Limits<f32>) -> (int32x4_t, usize) {
+        let scaled = vmulq_n_f32(vld1q_f32(values), limits.scale);
+        let clipped = vorrq_u32(
+            vcgeq_f32(scaled, vdupq_n_f32(limits.clip_from)),
+            vcltq_f32(scaled, vdupq_n_f32(limits.clip_below)),
+        );
+        let limited = vminq_f32(
+            vmaxq_f32(scaled, vdupq_n_f32(limits.low)),
+            vdupq_n_f32(limits.high),
+        );
+        (
+            vcvtnq_s32_f32(limited),
+            vaddvq_u32(vshrq_n_u32::<31>(clipped)) as usize,
+        )
+    }
+
+    /// Scale 2 f64 values and convert them to integers, in the same way as the scalar `to_*` methods.
+    /// NaN becomes zero.
+    /// The integers and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn quantise_f64(values: *const f64, limits: Limits<f64>) -> (int32x2_t, usize) {
+        let scaled = vmulq_n_f64(vld1q_f64(values), limits.scale);
+        let clipped = vorrq_u64(
+            vcgeq_f64(scaled, vdupq_n_f64(limits.clip_from)),
+            vcltq_f64(scaled, vdupq_n_f64(limits.clip_below)),
+        );
+        let limited = vminq_f64(
+            vmaxq_f64(scaled, vdupq_n_f64(limits.low)),
+            vdupq_n_f64(limits.high),
+        );
+        (
+            vmovn_s64(vcvtnq_s64_f64(limited)),
+            vaddvq_u64(vshrq_n_u64::<63>(clipped)) as usize,
+        )
+    }
+
+    /// Clamp 4 values to -1.0 .. +1.0 like the scalar conversion, where +1.0 is also counted as clipped.
+    /// The clamped values and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn clamp_f32(value: float32x4_t) -> (float32x4_t, usize) {
+        let one = vdupq_n_f32(1.0);
+        let minus_one = vdupq_n_f32(-1.0);
+        let above = vcgeq_f32(value, one);
+        let below = vcltq_f32(value, minus_one);
+        let clamped = vbslq_f32(above, one, vbslq_f32(below, minus_one, value));
+        let clipped = vshrq_n_u32::<31>(vorrq_u32(above, below));
+        (clamped, vaddvq_u32(clipped) as usize)
+    }
+
+    /// Clamp 2 values to -1.0 .. +1.0 like the scalar conversion, where +1.0 is also counted as clipped.
+    /// The clamped values and the number of clipped values are returned.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn clamp_f64(value: float64x2_t) -> (float64x2_t, usize) {
+        let one = vdupq_n_f64(1.0);
+        let minus_one = vdupq_n_f64(-1.0);
+        let above = vcgeq_f64(value, one);
+        let below = vcltq_f64(value, minus_one);
+        let clamped = vbslq_f64(above, one, vbslq_f64(below, minus_one, value));
+        let clipped = vshrq_n_u64::<63>(vorrq_u64(above, below));
+        (clamped, vaddvq_u64(clipped) as usize)
+    }
+
+    /// # Safety
+    /// The CPU must support NEON.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn int_to_f32<const WIDTH: usize, const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f32],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / (4 * WIDTH)).min(values.len() / 4);
+        let scale = read_scale(WIDTH) as f32;
+        for block in 0..nbr_blocks {
+            let ints = load::<WIDTH, BE>(rawbytes.as_ptr().add(4 * WIDTH * block));
+            vst1q_f32(
+                values.as_mut_ptr().add(4 * block),
+                vmulq_n_f32(vcvtq_f32_s32(ints), scale),
+            );
+        }
+        4 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support NEON.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn f32_to_int<const WIDTH: usize, const BE: bool>(
+        values: &[f32],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / (4 * WIDTH)).min(values.len() / 4);
+        let limits = f32_limits(WIDTH);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let (ints, clipped) = quantise_f32(values.as_ptr().add(4 * block), limits);
+            nbr_clipped += clipped;
+            store::<WIDTH, BE>(rawbytes.as_mut_ptr().add(4 * WIDTH * block), ints);
+        }
+        (4 * nbr_blocks, nbr_clipped)
+    }
+
+    /// # Safety
+    /// The CPU must support NEON.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn int_to_f64<const WIDTH: usize, const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f64],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / (4 * WIDTH)).min(values.len() / 4);
+        let scale = read_scale(WIDTH);
+        for block in 0..nbr_blocks {
+            let ints = load::<WIDTH, BE>(rawbytes.as_ptr().add(4 * WIDTH * block));
+            let low = vcvtq_f64_s64(vmovl_s32(vget_low_s32(ints)));
+            let high = vcvtq_f64_s64(vmovl_high_s32(ints));
+            let dst = values.as_mut_ptr().add(4 * block);
+            vst1q_f64(dst, vmulq_n_f64(low, scale));
+            vst1q_f64(dst.add(2), vmulq_n_f64(high, scale));
+        }
+        4 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support NEON.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn f64_to_int<const WIDTH: usize, const BE: bool>(
+        values: &[f64],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / (4 * WIDTH)).min(values.len() / 4);
+        let limits = f64_limits(WIDTH);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let src = values.as_ptr().add(4 * block);
+            let (low, clipped_low) = quantise_f64(src, limits);
+            let (high, clipped_high) = quantise_f64(src.add(2), limits);
+            nbr_clipped += clipped_low + clipped_high;
+            store::<WIDTH, BE>(
+                rawbytes.as_mut_ptr().add(4 * WIDTH * block),
+                vcombine_s32(low, high),
+            );
+        }
+        (4 * nbr_blocks, nbr_clipped)
+    }
+
+    /// # Safety
+    /// The CPU must support NEON.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn float_to_f32<const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f32],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / 16).min(values.len() / 4);
+        for block in 0..nbr_blocks {
+            let mut bytes = vld1q_u8(rawbytes.as_ptr().add(16 * block));
+            if BE {
+                bytes = vrev32q_u8(bytes);
+            }
+            vst1q_f32(
+                values.as_mut_ptr().add(4 * block),
+                vreinterpretq_f32_u8(bytes),
+            );
+        }
+        4 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support NEON.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn f32_to_float<const BE: bool>(
+        values: &[f32],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / 16).min(values.len() / 4);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let (clamped, clipped) = clamp_f32(vld1q_f32(values.as_ptr().add(4 * block)));
+            nbr_clipped += clipped;
+            let mut bytes = vreinterpretq_u8_f32(clamped);
+            if BE {
+                bytes = vrev32q_u8(bytes);
+            }
+            vst1q_u8(rawbytes.as_mut_ptr().add(16 * block), bytes);
+        }
+        (4 * nbr_blocks, nbr_clipped)
+    }
+
+    /// # Safety
+    /// The CPU must support NEON.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn float_to_f64<const BE: bool>(
+        rawbytes: &[u8],
+        values: &mut [f64],
+    ) -> usize {
+        let nbr_blocks = (rawbytes.len() / 16).min(values.len() / 2);
+        for block in 0..nbr_blocks {
+            let mut bytes = vld1q_u8(rawbytes.as_ptr().add(16 * block));
+            if BE {
+                bytes = vrev64q_u8(bytes);
+            }
+            vst1q_f64(
+                values.as_mut_ptr().add(2 * block),
+                vreinterpretq_f64_u8(bytes),
+            );
+        }
+        2 * nbr_blocks
+    }
+
+    /// # Safety
+    /// The CPU must support NEON.
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn f64_to_float<const BE: bool>(
+        values: &[f64],
+        rawbytes: &mut [u8],
+    ) -> (usize, usize) {
+        let nbr_blocks = (rawbytes.len() / 16).min(values.len() / 2);
+        let mut nbr_clipped = 0;
+        for block in 0..nbr_blocks {
+            let (clamped, clipped) = clamp_f64(vld1q_f64(values.as_ptr().add(2 * block)));
+            nbr_clipped += clipped;
+            let mut bytes = vreinterpretq_u8_f64(clamped);
+            if BE {
+                bytes = vrev64q_u8(bytes);
+            }
+            vst1q_u8(rawbytes.as_mut_ptr().add(16 * block), bytes);
+        }
+        (2 * nbr_blocks, nbr_clipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::simd::{bytes_to_f32, f32_to_bytes};
+    use crate::{Sample, SampleFormat};
+
+    /// Values covering the whole range, including clipping, ties and the special values.
+    fn test_values() -> Vec<f64> {
+        let mut values: Vec<f64> = (-1100..=1100).map(|n| n as f64 / 1000.0 + 1.0e-7).collect();
+        for bits in [15, 23, 31] {
+            let lsb = 0.5_f64.powi(bits);
+            // values exactly between two integer values of each format
+            values.extend((-20..20).map(|n| (n as f64 + 0.5) * lsb));
+            // and values around the limits
+            values.extend((-8..8).map(|n| 1.0 + n as f64 * 0.25 * lsb));
+            values.extend((-8..8).map(|n| -1.0 + n as f64 * 0.25 * lsb));
+        }
+        values.extend_from_slice(&[
+            -1.0,
+            1.0,
+            -1.0 - 1.0e-7,
+            1.0 - 1.0e-7,
+            0.0,
+            -0.0,
+            f64::NAN,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::MAX,
+            f64::MIN,
+            f32::MAX as f64,
+            f32::MIN as f64,
+        ]);
+        values
+    }
+
+    /// Bytes covering the whole range, including the extreme values.
+    fn test_bytes(n: usize) -> Vec<u8> {
+        let mut bytes: Vec<u8> = (0..997 * n).map(|i| (i * 7919 % 251) as u8).collect();
+        bytes.extend(vec![0x80; 8 * n]);
+        bytes.extend(vec![0x7F; 8 * n]);
+        bytes.extend(vec![0xFF; 8 * n]);
+        bytes.extend(vec![0; 8 * n]);
+        bytes
+    }
+
+    /// Compare the kernels of one instruction set with the scalar conversions, for one format.
+    macro_rules! check_kernels {
+        ($isa:ident, $t:ident, $format:expr, $to:ident, $from:ident, $n:expr) => {{
+            let values: Vec<$t> = test_values().iter().map(|v| *v as $t).collect();
+            let mut expected = vec![0; $n * values.len()];
+            let mut expected_clipped = 0;
+            for (value, chunk) in values.iter().zip(expected.chunks_exact_mut($n)) {
+                let (bytes, clipped) = value.$to();
+                chunk.copy_from_slice(&bytes);
+                expected_clipped += clipped as usize;
+            }
+            let mut bytes = vec![0; $n * values.len()];
+            // Safety: the caller checks that the instruction set is available.
+            let (nbr_converted, nbr_clipped) =
+                unsafe { check_kernels!(@write $isa, $t, &values, &mut bytes, &$format) };
+            // all whole blocks are converted
+            assert!(values.len() - nbr_converted < 8, "{:?}", $format);
+            assert_eq!(
+                bytes[..$n * nbr_converted],
+                expected[..$n * nbr_converted],
+                "{:?}",
+                $format
+            );
+            let clipped_in_blocks = values[..nbr_converted]
+                .iter()
+                .filter(|value| value.$to().1)
+                .count();
+            assert_eq!(nbr_clipped, clipped_in_blocks, "{:?}", $format);
+            assert!(expected_clipped > 0);
+
+            let rawbytes = test_bytes($n);
+            let expected: Vec<$t> = rawbytes
+                .chunks_exact($n)
+                .map(|chunk| $t::$from(chunk.try_into().unwrap()))
+                .collect();
+            let mut values: Vec<$t> = vec![0.0; expected.len()];
+            // Safety: the caller checks that the instruction set is available.
+            let nbr_converted =
+                unsafe { check_kernels!(@read $isa, $t, &rawbytes, &mut values, &$format) };
+            assert!(expected.len() - nbr_converted < 8, "{:?}", $format);
+            let expected_bits: Vec<_> = expected[..nbr_converted]
+                .iter()
+                .map(|v| v.to_bits())
+                .collect();
+            let bits: Vec<_> = values[..nbr_converted]
+                .iter()
+                .map(|v| v.to_bits())
+                .collect();
+            assert_eq!(bits, expected_bits, "{:?}", $format);
+        }};
+        (@write $isa:ident, f32, $src:expr, $dst:expr, $format:expr) => {
+            select_kernel!($isa, f32_to_int, f32_to_float, SampleFormat::F32LE, SampleFormat::F32BE, $src, $dst, $format)
+        };
+        (@write $isa:ident, f64, $src:expr, $dst:expr, $format:expr) => {
+            select_kernel!($isa, f64_to_int, f64_to_float, SampleFormat::F64LE, SampleFormat::F64BE, $src, $dst, $format)
+        };
+        (@read $isa:ident, f32, $src:expr, $dst:expr, $format:expr) => {
+            select_kernel!($isa, int_to_f32, float_to_f32, SampleFormat::F32LE, SampleFormat::F32BE, $src, $dst, $format)
+        };
+        (@read $isa:ident, f64, $src:expr, $dst:expr, $format:expr) => {
+            select_kernel!($isa, int_to_f64, float_to_f64, SampleFormat::F64LE, SampleFormat::F64BE, $src, $dst, $format)
+        };
+    }
+
+    /// Compare all kernels of one instruction set with the scalar conversions.
+    macro_rules! check_all_kernels {
+        ($isa:ident) => {{
+            use std::convert::TryInto;
+            check_kernels!($isa, f32, SampleFormat::S16LE, to_s16_le, from_s16_le, 2);
+            check_kernels!($isa, f32, SampleFormat::S16BE, to_s16_be, from_s16_be, 2);
+            check_kernels!(
+                $isa,
+                f32,
+                SampleFormat::S24LE3,
+                to_s24_3_le,
+                from_s24_3_le,
+                3
+            );
+            check_kernels!(
+                $isa,
+                f32,
+                SampleFormat::S24BE3,
+                to_s24_3_be,
+                from_s24_3_be,
+                3
+            );
+            check_kernels!($isa, f32, SampleFormat::S32LE, to_s32_le, from_s32_le, 4);
+            check_kernels!($isa, f32, SampleFormat::S32BE, to_s32_be, from_s32_be, 4);
+            check_kernels!($isa, f32, SampleFormat::F32LE, to_f32_le, from_f32_le, 4);
+            check_kernels!($isa, f32, SampleFormat::F32BE, to_f32_be, from_f32_be, 4);
+            check_kernels!($isa, f64, SampleFormat::S16LE, to_s16_le, from_s16_le, 2);
+            check_kernels!($isa, f64, SampleFormat::S16BE, to_s16_be, from_s16_be, 2);
+            check_kernels!(
+                $isa,
+                f64,
+                SampleFormat::S24LE3,
+                to_s24_3_le,
+                from_s24_3_le,
+                3
+            );
+            check_kernels!(
+                $isa,
+                f64,
+                SampleFormat::S24BE3,
+                to_s24_3_be,
+                from_s24_3_be,
+                3
+            );
+            check_kernels!($isa, f64, SampleFormat::S32LE, to_s32_le, from_s32_le, 4);
+            check_kernels!($isa, f64, SampleFormat::S32BE, to_s32_be, from_s32_be, 4);
+            check_kernels!($isa, f64, SampleFormat::F64LE, to_f64_le, from_f64_le, 8);
+            check_kernels!($isa, f64, SampleFormat::F64BE, to_f64_be, from_f64_be, 8);
+        }};
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn check_kernels_avx2() {
+        use crate::simd::avx2;
+        if is_x86_feature_detected!("avx2") {
+            check_all_kernels!(avx2);
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn check_kernels_sse2() {
+        use crate::simd::sse2;
+        check_all_kernels!(sse2);
+    }
+
+    #[test]
+    #[cfg(all(target_arch = "aarch64", target_endian = "little"))]
+    fn check_kernels_neon() {
+        use crate::simd::neon;
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            check_all_kernels!(neon);
+        }
+    }
+
+    #[test]
+    fn check_kernels_partial_blocks() {
+        // only whole blocks are converted, and nothing is written past them
+        let values = [0.5_f32; 13];
+        let mut bytes = [0xAA; 3 * 13];
+        let (nbr_converted, _) = f32_to_bytes(&values, &mut bytes, &SampleFormat::S24LE3);
+        assert!(nbr_converted <= 12 && nbr_converted % 4 == 0);
+        assert!(bytes[3 * nbr_converted..].iter().all(|b| *b == 0xAA));
+        let mut values = [0.25_f32; 13];
+        let nbr_converted = bytes_to_f32(&[0; 3 * 3], &mut values, &SampleFormat::S24LE3);
+        assert_eq!(nbr_converted, 0);
+        assert_eq!(
+            f32_to_bytes(&values, &mut bytes, &SampleFormat::S24LE4),
+            (0, 0)
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use crate::dither::{Dither, NoiseShaping};
+use crate::{Error, Quantization, Sample, SampleFormat, SampleReader, SampleWriter, CHUNK_FRAMES};
+use num_traits::Float;
+use std::io::Write;
+use std::marker::PhantomData;
+
+/// A stateful reader for raw bytes that arrive in chunks of any size.
+///
+/// This is the stateful counterpart of [SampleReader::read_samples].
+/// The chunks of bytes are added with [StreamReader::push_bytes], and don't need to be aligned to sample or frame boundaries.
+/// The bytes are buffered until they form complete frames,
+/// which can then be read as interleaved f32 or f64 values.
+///
+/// ```rust
+/// use rawsample::{SampleFormat, StreamReader};
+/// let mut reader = StreamReader::<f32>::new(SampleFormat::S16LE, 2).unwrap();
+/// // one and a half frames
+/// reader.push_bytes(&[0, 64, 0, 192, 0, 32]);
+/// let mut samples = Vec::new();
+/// assert_eq!(reader.read_all_frames(&mut samples), 1);
+/// assert_eq!(samples, vec![0.5, -0.5]);
+/// // the rest of the second frame
+/// reader.push_bytes(&[0, 224]);
+/// assert_eq!(reader.read_all_frames(&mut samples), 1);
+/// assert_eq!(samples, vec![0.5, -0.5, 0.25, -0.25]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamReader<T> {
+    format: SampleFormat,
+    channels: usize,
+    frame_bytes: usize,
+    buffer: Vec<u8>,
+    _sample: PhantomData<T>,
+}
+
+impl<T: Sample<T> + SampleReader<T> + Copy + Default> StreamReader<T> {
+    /// Create a new StreamReader for a sample format and number of channels.
+    pub fn new(format: SampleFormat, channels: usize) -> Result<Self, Error> {
+        let frame_bytes = format.frame_bytes(channels)?;
+        Ok(StreamReader {
+            format,
+            channels,
+            frame_bytes,
+            buffer: Vec::new(),
+            _sample: PhantomData,
+        })
+    }
+
+    /// Get the sample format.
+    pub fn format(&self) -> SampleFormat {
+        self.format
+    }
+
+    /// Get the number of channels.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Add a chunk of raw bytes to the buffer.
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Get the number of complete frames in the buffer.
+    pub fn available_frames(&self) -> usize {
+        self.buffer.len() / self.frame_bytes
+    }
+
+    /// Get the bytes in the buffer that don't yet form a complete frame.
+    pub fn pending_bytes(&self) -> &[u8] {
+        let start = self.available_frames() * self.frame_bytes;
+        &self.buffer[start..]
+    }
+
+    /// Remove all buffered bytes, including any incomplete frame.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Convert complete frames from the buffer, and store the samples in a slice.
+    /// It converts as many frames as are available and fit in the slice,
+    /// and any remaining values at the end of the slice are left untouched.
+    /// The converted frames are removed from the buffer.
+    /// The number of frames read is returned.
+    pub fn read_frames(&mut self, samples: &mut [T]) -> usize {
+        let nbr_frames = self.available_frames().min(samples.len() / self.channels);
+        let nbr_bytes = nbr_frames * self.frame_bytes;
+        T::read_samples_from_slice(
+            &self.buffer[0..nbr_bytes],
+            &mut samples[0..nbr_frames * self.channels],
+            &self.format,
+        );
+        self.buffer.drain(0..nbr_bytes);
+        nbr_frames
+    }
+
+    /// Convert all complete frames from the buffer, and append the samples to a vec.
+    /// The converted frames are removed from the buffer, and any incomplete frame is kept.
+    /// The number of frames read is returned.
+    pub fn read_all_frames(&mut self, samples: &mut Vec<T>) -> usize {
+        let offset = samples.len();
+        samples.resize(
+            offset + self.available_frames() * self.channels,
+            T::default(),
+        );
+        self.read_frames(&mut samples[offset..])
+    }
+}
+
+/// A stateful writer for the integer formats, that applies noise shaping and dither.
+///
+/// This is the stateful counterpart of [SampleWriter::write_frames].
+/// The quantisation errors of the previous samples are kept for each channel,
+/// and fed back through the noise shaping filter.
+/// The state is kept between calls, which means that a stream can be written in pieces
+/// with the same result as if it was written all at once.
+/// The values are rounded to the nearest value of the output format, see [Quantization::RoundHalfEven].
+///
+/// ```rust
+/// use rawsample::dither::{Dither, DitherKind, NoiseShaping};
+/// use rawsample::{SampleFormat, StreamWriter};
+/// let dither = Dither::new(DitherKind::Triangular, 1234);
+/// let mut writer =
+///     StreamWriter::<f64>::new(SampleFormat::S16LE, 2, NoiseShaping::Lipshitz44, Some(dither))
+///         .unwrap();
+/// let mut rawbytes: Vec<u8> = Vec::new();
+/// writer.write_frames(&[0.1, -0.1, 0.2, -0.2], &mut rawbytes).unwrap();
+/// writer.write_frames(&[0.3, -0.3], &mut rawbytes).unwrap();
+/// assert_eq!(rawbytes.len(), 12);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamWriter<T> {
+    format: SampleFormat,
+    channels: usize,
+    lsb: f64,
+    shaping: NoiseShaping,
+    dither: Option<Dither>,
+    // the latest quantisation errors of each channel, with the newest first
+    errors: Vec<Vec<f64>>,
+    // the quantised values are kept as f64, to be written without rounding them again
+    quantised: Vec<f64>,
+    _sample: PhantomData<T>,
+}
+
+impl<T: Sample<T> + SampleWriter<T> + Float> StreamWriter<T> {
+    /// Create a new StreamWriter for an integer or fixed point sample format and number of channels,
+    /// with the given noise shaping, and optionally dither.
+    /// Returns an error for the float formats, and for the companded A-law and mu-law formats.
+    pub fn new(
+        format: SampleFormat,
+        channels: usize,
+        shaping: NoiseShaping,
+        dither: Option<Dither>,
+    ) -> Result<Self, Error> {
+        format.frame_bytes(channels)?;
+        let lsb = format.lsb().ok_or(Error::UnsupportedFormat(
+            "noise shaping requires a linear integer or fixed point format",
+        ))?;
+        Ok(StreamWriter {
+            format,
+            channels,
+            lsb,
+            shaping,
+            dither,
+            errors: vec![vec![0.0; shaping.coefficients().len()]; channels],
+            quantised: Vec::with_capacity(CHUNK_FRAMES * channels),
+            _sample: PhantomData,
+        })
+    }
+
+    /// Get the sample format.
+    pub fn format(&self) -> SampleFormat {
+        self.format
+    }
+
+    /// Get the number of channels.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Clear the stored quantisation errors, for example before writing an unrelated stream.
+    /// The state of the dither generator is kept.
+    pub fn reset(&mut self) {
+        for errors in self.errors.iter_mut() {
+            errors.iter_mut().for_each(|err| *err = 0.0);
+        }
+    }
+
+    /// Write interleaved frames of sample values from a slice to anything that implements the "Write" trait.
+    /// The length of the slice must be a whole number of frames.
+    /// The number of clipped samples is returned.
+    pub fn write_frames(&mut self, values: &[T], target: &mut dyn Write) -> Result<usize, Error> {
+        if values.len() % self.channels != 0 {
+            return Err(Error::InvalidParameter(
+                "the number of samples must be a whole number of frames",
+            ));
+        }
+        let coefficients = self.shaping.coefficients();
+        let mut nbr_clipped = 0;
+        for chunk in values.chunks(CHUNK_FRAMES * self.channels) {
+            self.quantised.clear();
+            for frame in chunk.chunks(self.channels) {
+                for (value, errors) in frame.iter().zip(self.errors.iter_mut()) {
+                    let feedback: f64 = coefficients
+                        .iter()
+                        .zip(errors.iter())
+                        .map(|(coeff, err)| coeff * err)
+                        .sum();
+                    let wanted = value.to_f64().unwrap_or(0.0) - feedback;
+                    let noise = match self.dither.as_mut() {
+                        Some(dither) => dither.next_noise(),
+                        None => 0.0,
+                    };
+                    let quantised =
+                        Quantization::RoundHalfEven.quantise(wanted + noise * self.lsb, self.lsb);
+                    if !errors.is_empty() {
+                        // limit the error, to keep the filter stable when the output clips
+                        let err = (quantised - wanted).clamp(-2.0 * self.lsb, 2.0 * self.lsb);
+                        errors.rotate_right(1);
+                        errors[0] = err;
+                    }
+                    self.quantised.push(quantised);
+                }
+            }
+            nbr_clipped += f64::write_frames(&self.quantised, self.channels, target, &self.format)?;
+        }
+        Ok(nbr_clipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dither::{Dither, DitherKind, NoiseShaping};
+    use crate::{SampleFormat, SampleReader, SampleWriter, StreamReader, StreamWriter};
+
+    /// A quiet stereo sine, with a different frequency in each channel.
+    fn quiet_sine(frames: usize) -> Vec<f64> {
+        let mut values = Vec::with_capacity(2 * frames);
+        for n in 0..frames {
+            for freq in [0.01, 0.013] {
+                values.push(0.001 * (2.0 * std::f64::consts::PI * freq * n as f64).sin());
+            }
+        }
+        values
+    }
+
+    /// Get the power of the quantisation error of one channel, after a simple low pass filter.
+    fn low_frequency_error(values: &[f64], rawbytes: &[u8], format: &SampleFormat) -> f64 {
+        let mut decoded = Vec::new();
+        let mut slice: &[u8] = rawbytes;
+        f64::read_all_samples(&mut slice, &mut decoded, format).unwrap();
+        let errors: Vec<f64> = decoded
+            .iter()
+            .zip(values.iter())
+            .step_by(2)
+            .map(|(out, value)| out - value)
+            .collect();
+        errors
+            .windows(64)
+            .map(|win| win.iter().sum::<f64>().powi(2))
+            .sum::<f64>()
+            / errors.len() as f64
+    }
+
+    #[test]
+    fn stream_writer_noise_shaping_s16le() {
+        let values = quiet_sine(10000);
+        let format = SampleFormat::S16LE;
+        let mut plain = StreamWriter::<f64>::new(
+            format,
+            2,
+            NoiseShaping::None,
+            Some(Dither::new(DitherKind::Triangular, 3)),
+        )
+        .unwrap();
+        let mut plain_data: Vec<u8> = Vec::new();
+        plain.write_frames(&values, &mut plain_data).unwrap();
+        let mut shaped = StreamWriter::<f64>::new(
+            format,
+            2,
+            NoiseShaping::ErrorFeedback,
+            Some(Dither::new(DitherKind::Triangular, 3)),
+        )
+        .unwrap();
+        let mut shaped_data: Vec<u8> = Vec::new();
+        shaped.write_frames(&values, &mut shaped_data).unwrap();
+        // the shaped noise has much less energy at low frequencies
+        let plain_error = low_frequency_error(&values, &plain_data, &format);
+        let shaped_error = low_frequency_error(&values, &shaped_data, &format);
+        assert!(shaped_error < 0.1 * plain_error);
+    }
+
+    #[test]
+    fn stream_writer_state_kept_between_calls() {
+        let values = quiet_sine(3000);
+        let format = SampleFormat::S24LE3;
+        let mut writer = StreamWriter::<f32>::new(
+            format,
+            2,
+            NoiseShaping::Lipshitz44,
+            Some(Dither::new(DitherKind::Triangular, 9)),
+        )
+        .unwrap();
+        let values: Vec<f32> = values.iter().map(|v| *v as f32).collect();
+        let mut whole: Vec<u8> = Vec::new();
+        writer.clone().write_frames(&values, &mut whole).unwrap();
+        let mut pieces: Vec<u8> = Vec::new();
+        for chunk in values.chunks(202) {
+            writer.write_frames(chunk, &mut pieces).unwrap();
+        }
+        assert_eq!(whole, pieces);
+    }
+
+    #[test]
+    fn stream_writer_f32_s32le_exact() {
+        // the quantised values are written directly, without rounding them to f32 first,
+        // which gives the same result as for f64 values
+        // a loud signal, where the values of S32LE need more bits than an f32 has
+        let values = quiet_sine(1000);
+        let values32: Vec<f32> = values.iter().map(|v| (500.0 * v) as f32).collect();
+        let values64: Vec<f64> = values32.iter().map(|v| *v as f64).collect();
+        let dither = Dither::new(DitherKind::Triangular, 5);
+        let format = SampleFormat::S32LE;
+        let mut data32: Vec<u8> = Vec::new();
+        StreamWriter::<f32>::new(
+            format,
+            2,
+            NoiseShaping::FittedLipshitz48,
+            Some(dither.clone()),
+        )
+        .unwrap()
+        .write_frames(&values32, &mut data32)
+        .unwrap();
+        let mut data64: Vec<u8> = Vec::new();
+        StreamWriter::<f64>::new(format, 2, NoiseShaping::FittedLipshitz48, Some(dither))
+            .unwrap()
+            .write_frames(&values64, &mut data64)
+            .unwrap();
+        assert_eq!(data32, data64);
+    }
+
+    #[test]
+    fn stream_writer_rounds_without_dither() {
+        let lsb = 1.0 / 128.0;
+        let values = [0.3 * lsb, 0.7 * lsb, -0.3 * lsb, -0.7 * lsb, 2.0];
+        let mut writer =
+            StreamWriter::<f64>::new(SampleFormat::S8, 1, NoiseShaping::None, None).unwrap();
+        let mut data: Vec<u8> = Vec::new();
+        assert_eq!(writer.write_frames(&values, &mut data).unwrap(), 1);
+        assert_eq!(data, vec![0, 1, 0, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn stream_writer_invalid_format() {
+        let writer = StreamWriter::<f64>::new(SampleFormat::F32LE, 2, NoiseShaping::None, None);
+        assert!(writer.is_err());
+        let writer = StreamWriter::<f64>::new(SampleFormat::ALaw, 2, NoiseShaping::None, None);
+        assert!(writer.is_err());
+        let writer = StreamWriter::<f64>::new(SampleFormat::S16LE, 0, NoiseShaping::None, None);
+        assert!(writer.is_err());
+        let mut writer =
+            StreamWriter::<f64>::new(SampleFormat::S16LE, 2, NoiseShaping::None, None).unwrap();
+        let mut data: Vec<u8> = Vec::new();
+        assert!(writer.write_frames(&[0.0; 3], &mut data).is_err());
+    }
+
+    #[test]
+    fn stream_odd_chunks_s24le3() {
+        let values: Vec<f64> = (0..300).map(|n| (n % 50) as f64 / 64.0 - 0.25).collect();
+        let mut data: Vec<u8> = Vec::new();
+        f64::write_samples(&values, &mut data, &SampleFormat::S24LE3).unwrap();
+        let mut reader = StreamReader::<f64>::new(SampleFormat::S24LE3, 3).unwrap();
+        let mut values2 = Vec::new();
+        for chunk in data.chunks(7) {
+            reader.push_bytes(chunk);
+            reader.read_all_frames(&mut values2);
+            assert!(reader.pending_bytes().len() < 9);
+        }
+        assert_eq!(values2, values);
+        assert_eq!(reader.available_frames(), 0);
+        assert!(reader.pending_bytes().is_empty());
+    }
+
+    #[test]
+    fn stream_to_slice_s12packedle() {
+        let mut reader = StreamReader::<f32>::new(SampleFormat::S12PackedLE, 2).unwrap();
+        reader.push_bytes(&[0x00, 0x02, 0xC0, 0x00, 0x02]);
+        assert_eq!(reader.available_frames(), 1);
+        assert_eq!(reader.pending_bytes(), &[0x00, 0x02]);
+        reader.push_bytes(&[0xC0, 0x00, 0x02, 0xC0, 0x00]);
+        assert_eq!(reader.available_frames(), 3);
+        // only whole frames that fit in the slice are read
+        let mut values = vec![0.0; 5];
+        assert_eq!(reader.read_frames(&mut values), 2);
+        assert_eq!(values, vec![0.25, -0.5, 0.25, -0.5, 0.0]);
+        assert_eq!(reader.available_frames(), 1);
+        assert_eq!(reader.pending_bytes(), &[0x00]);
+        reader.clear();
+        assert_eq!(reader.available_frames(), 0);
+        assert!(reader.pending_bytes().is_empty());
+    }
+
+    #[test]
+    fn stream_invalid_format() {
+        assert!(StreamReader::<f32>::new(SampleFormat::S12PackedBE, 3).is_err());
+        assert!(StreamReader::<f32>::new(SampleFormat::S16LE, 0).is_err());
+    }
+}